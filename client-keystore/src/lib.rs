@@ -42,6 +42,10 @@ use std::{
 
 use sc_keystore::{Error, Result};
 
+pub use signer::KeystoreSigner;
+
+mod signer;
+
 /// A local based keystore that is either memory-based or filesystem-based.
 pub struct LocalKeystore(RwLock<KeystoreInner>);
 
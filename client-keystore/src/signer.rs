@@ -0,0 +1,127 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+//! Signer of extrinsics for keys held by a [`LocalKeystore`].
+
+use crate::LocalKeystore;
+use ac_primitives::{AccountId, SignError, Signer};
+use sc_keystore::{Error, Result};
+use sp_core::{crypto::KeyTypeId, ecdsa, ed25519, sr25519, Pair as PairT};
+use sp_keystore::SyncCryptoStore;
+use sp_runtime::{traits::IdentifyAccount, MultiSignature, MultiSigner};
+use std::sync::Arc;
+
+/// Signs extrinsics with a key of a [`LocalKeystore`], without the key leaving the keystore.
+#[derive(Clone)]
+pub struct KeystoreSigner {
+    keystore: Arc<LocalKeystore>,
+    key_type: KeyTypeId,
+    public: MultiSigner,
+}
+
+impl KeystoreSigner {
+    /// Signer for the sr25519, ed25519 or ecdsa key `public` of type `key_type`. Fails if the
+    /// keystore does not hold the key.
+    pub fn new(
+        keystore: Arc<LocalKeystore>,
+        key_type: KeyTypeId,
+        public: impl Into<MultiSigner>,
+    ) -> Result<Self> {
+        let public = public.into();
+        if !SyncCryptoStore::has_keys(&*keystore, &[(public.as_ref().to_vec(), key_type)]) {
+            return Err(Error::KeyNotSupported(key_type));
+        }
+        Ok(Self {
+            keystore,
+            key_type,
+            public,
+        })
+    }
+
+    pub fn public(&self) -> &MultiSigner {
+        &self.public
+    }
+
+    fn sign_with_key(&self, payload: &[u8]) -> Result<MultiSignature> {
+        match &self.public {
+            MultiSigner::Sr25519(public) => self.sign_with::<sr25519::Pair>(public, payload),
+            MultiSigner::Ed25519(public) => self.sign_with::<ed25519::Pair>(public, payload),
+            MultiSigner::Ecdsa(public) => self.sign_with::<ecdsa::Pair>(public, payload),
+        }
+    }
+
+    fn sign_with<Pair: PairT>(
+        &self,
+        public: &Pair::Public,
+        payload: &[u8],
+    ) -> Result<MultiSignature>
+    where
+        MultiSignature: From<Pair::Signature>,
+    {
+        let pair = self
+            .keystore
+            .0
+            .read()
+            .key_pair_by_type::<Pair>(public, self.key_type)?
+            .ok_or(Error::KeyNotSupported(self.key_type))?;
+        Ok(PairT::sign(&pair, payload).into())
+    }
+}
+
+impl Signer for KeystoreSigner {
+    fn account_id(&self) -> AccountId {
+        self.public.clone().into_account()
+    }
+
+    /// Fails if the key has been removed from the keystore since the signer was created.
+    fn sign(&self, payload: &[u8]) -> std::result::Result<MultiSignature, SignError> {
+        self.sign_with_key(payload)
+            .map_err(|e| SignError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sp_runtime::traits::Verify;
+
+    const TEST_KEY_TYPE: KeyTypeId = KeyTypeId(*b"test");
+
+    #[test]
+    fn signs_with_keys_of_the_keystore() {
+        let keystore = Arc::new(LocalKeystore::in_memory());
+        let public =
+            SyncCryptoStore::ed25519_generate_new(&*keystore, TEST_KEY_TYPE, None).unwrap();
+
+        let signer = KeystoreSigner::new(keystore, TEST_KEY_TYPE, public).unwrap();
+        let signature = signer.sign(b"payload").unwrap();
+
+        assert_eq!(signer.account_id(), AccountId::from(public));
+        assert!(signature.verify(&b"payload"[..], &signer.account_id()));
+    }
+
+    #[test]
+    fn rejects_keys_missing_in_the_keystore() {
+        let keystore = Arc::new(LocalKeystore::in_memory());
+        let (pair, _) = sr25519::Pair::generate();
+
+        assert!(matches!(
+            KeystoreSigner::new(keystore, TEST_KEY_TYPE, pair.public()),
+            Err(Error::KeyNotSupported(TEST_KEY_TYPE))
+        ));
+    }
+}
@@ -0,0 +1,420 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+//! Generates a typed api for a runtime from its metadata, meant to be used in a build script.
+//!
+//! For every pallet, a module is generated containing
+//! * `calls`: a builder function per call, returning a `Call` that can be passed to
+//!   `compose_extrinsic_offline!`,
+//! * `storage`: a typed accessor per storage entry,
+//! * `events`: a struct per event, implementing `StaticEvent`,
+//! * `constants`: a typed accessor per constant.
+//!
+//! All types of the runtime are generated in the module `types`, following their path.
+//!
+//! ```ignore
+//! // build.rs
+//! fn main() {
+//!     let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("runtime.rs");
+//!     ac_codegen::generate_to_file("node_template_metadata.scale", "crate::runtime", out).unwrap();
+//! }
+//!
+//! // lib.rs
+//! pub mod runtime {
+//!     include!(concat!(env!("OUT_DIR"), "/runtime.rs"));
+//! }
+//! ```
+//!
+//! The generated code refers to `codec` (parity-scale-codec), `sp-core`, `sp-runtime` and
+//! `substrate-api-client`, and to `bitvec` if the runtime uses bit sequences. They need to be
+//! dependencies of the crate including it.
+
+use std::fs;
+use std::path::Path;
+
+use codec::Decode;
+use frame_metadata::{
+    v14::PalletMetadata, v15, RuntimeMetadata, RuntimeMetadataPrefixed, META_RESERVED,
+};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use scale_info::form::PortableForm;
+
+use crate::types::TypeGenerator;
+
+mod pallets;
+mod types;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CodegenError {
+    #[error("Could not read the metadata file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Error deserializing the json response: {0}")]
+    Deserializing(#[from] serde_json::Error),
+    #[error("The json response does not contain the metadata as result")]
+    InvalidRpcResponse,
+    #[error("Error decoding the metadata: {0}")]
+    Decoding(#[from] codec::Error),
+    #[error("Received invalid hex string: {0}")]
+    InvalidHexString(#[from] hex::FromHexError),
+    #[error("Metadata has an invalid prefix")]
+    InvalidPrefix,
+    #[error("Unsupported metadata version, only V14 and V15 are supported")]
+    UnsupportedVersion,
+    #[error("Invalid module path: {0}")]
+    InvalidModulePath(String),
+    #[error("Type {0} is missing from the type registry")]
+    TypeNotFound(u32),
+    #[error("Type {0} is not a variant type")]
+    TypeDefNotVariant(u32),
+    #[error("Type {0} can not be compact encoded")]
+    InvalidCompactType(u32),
+    #[error("Key of storage map {0} does not match its hashers")]
+    InvalidStorageKey(String),
+}
+
+/// Reads the metadata from `path`. The file either contains the SCALE encoded metadata, the hex
+/// string returned by `state_getMetadata` or the whole json response of the node to it.
+pub fn read_metadata(path: impl AsRef<Path>) -> Result<RuntimeMetadataPrefixed, CodegenError> {
+    let content = fs::read(path)?;
+    let text = String::from_utf8_lossy(&content);
+    let text = text.trim();
+    let hex = if text.starts_with('{') {
+        let response: serde_json::Value = serde_json::from_str(text)?;
+        response["result"]
+            .as_str()
+            .ok_or(CodegenError::InvalidRpcResponse)?
+            .to_string()
+    } else {
+        text.trim_matches('"').to_string()
+    };
+    match hex.strip_prefix("0x") {
+        Some(hex) => Ok(RuntimeMetadataPrefixed::decode(
+            &mut hex::decode(hex)?.as_slice(),
+        )?),
+        None => Ok(RuntimeMetadataPrefixed::decode(&mut content.as_slice())?),
+    }
+}
+
+/// Generates the typed api for `metadata`. `root` is the path of the module the generated code
+/// is included in, e.g. `crate::runtime`.
+pub fn generate_runtime_api(
+    metadata: &RuntimeMetadataPrefixed,
+    root: &str,
+) -> Result<TokenStream, CodegenError> {
+    if metadata.0 != META_RESERVED {
+        return Err(CodegenError::InvalidPrefix);
+    }
+    let (types, pallets) = match &metadata.1 {
+        RuntimeMetadata::V14(metadata) => (&metadata.types, metadata.pallets.clone()),
+        RuntimeMetadata::V15(metadata) => (&metadata.types, v15_pallets(&metadata.pallets)),
+        _ => return Err(CodegenError::UnsupportedVersion),
+    };
+    let root: TokenStream = root
+        .parse()
+        .map_err(|_| CodegenError::InvalidModulePath(root.to_string()))?;
+
+    let types = TypeGenerator::new(types, root);
+    let type_definitions = types.generate()?;
+    let pallets = pallets
+        .iter()
+        .map(|pallet| pallets::generate_pallet(&types, pallet))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(quote! {
+        #type_definitions
+        #(#pallets)*
+    })
+}
+
+/// The pallets of V15 metadata, which only add docs to the ones of V14.
+fn v15_pallets(pallets: &[v15::PalletMetadata<PortableForm>]) -> Vec<PalletMetadata<PortableForm>> {
+    pallets
+        .iter()
+        .map(|pallet| PalletMetadata {
+            name: pallet.name.clone(),
+            storage: pallet.storage.clone(),
+            calls: pallet.calls.clone(),
+            event: pallet.event.clone(),
+            constants: pallet.constants.clone(),
+            error: pallet.error.clone(),
+            index: pallet.index,
+        })
+        .collect()
+}
+
+/// Reads the metadata from `metadata_path` and writes the generated api to `out_path`. See
+/// [`read_metadata`] and [`generate_runtime_api`].
+pub fn generate_to_file(
+    metadata_path: impl AsRef<Path>,
+    root: &str,
+    out_path: impl AsRef<Path>,
+) -> Result<(), CodegenError> {
+    let metadata = read_metadata(metadata_path)?;
+    let api = generate_runtime_api(&metadata, root)?;
+    fs::write(out_path, api.to_string())?;
+    Ok(())
+}
+
+/// Doc attributes for the `docs` of the metadata.
+pub(crate) fn docs(docs: &[String]) -> TokenStream {
+    quote!(#(#[doc = #docs])*)
+}
+
+/// Identifier for `name`, raw if `name` is a keyword. The keywords that can not be raw
+/// identifiers get an underscore appended.
+pub(crate) fn ident(name: &str) -> Ident {
+    const KEYWORDS: &[&str] = &[
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+        "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let",
+        "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+        "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+        "virtual", "where", "while", "yield",
+    ];
+    const NOT_RAW: &[&str] = &["crate", "self", "Self", "super", "_"];
+    if KEYWORDS.contains(&name) {
+        Ident::new_raw(name, Span::call_site())
+    } else if NOT_RAW.contains(&name) {
+        Ident::new(&format!("{}_", name), Span::call_site())
+    } else {
+        Ident::new(name, Span::call_site())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::{CompactAs, Encode};
+    use frame_metadata::v14::{
+        ExtrinsicMetadata, PalletCallMetadata, PalletConstantMetadata, PalletEventMetadata,
+        PalletStorageMetadata, RuntimeMetadataV14, StorageEntryMetadata, StorageEntryModifier,
+        StorageEntryType, StorageHasher,
+    };
+    use scale_info::{meta_type, TypeInfo};
+
+    #[derive(Encode, TypeInfo)]
+    struct AccountId32([u8; 32]);
+
+    #[derive(Encode, CompactAs, TypeInfo)]
+    struct Perbill(u32);
+
+    #[derive(TypeInfo)]
+    enum Void {}
+
+    #[derive(Encode, TypeInfo)]
+    #[allow(non_camel_case_types)]
+    enum Call {
+        transfer {
+            dest: AccountId32,
+            #[codec(compact)]
+            value: u128,
+        },
+        remark {
+            remark: Vec<u8>,
+        },
+        set_rate {
+            #[codec(compact)]
+            rate: Perbill,
+        },
+        dispatch {
+            call: Box<Call>,
+        },
+        batch {
+            calls: Vec<Call>,
+        },
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum Event {
+        /// Transfer succeeded.
+        Transfer {
+            from: AccountId32,
+            to: AccountId32,
+            amount: u128,
+        },
+        Remarked(Option<u32>),
+    }
+
+    fn metadata() -> RuntimeMetadataPrefixed {
+        let pallet = PalletMetadata {
+            name: "Balances",
+            storage: Some(PalletStorageMetadata {
+                prefix: "Balances",
+                entries: vec![
+                    StorageEntryMetadata {
+                        name: "TotalIssuance",
+                        modifier: StorageEntryModifier::Default,
+                        ty: StorageEntryType::Plain(meta_type::<u128>()),
+                        default: vec![0; 16],
+                        docs: vec![],
+                    },
+                    StorageEntryMetadata {
+                        name: "Locks",
+                        modifier: StorageEntryModifier::Default,
+                        ty: StorageEntryType::Map {
+                            hashers: vec![StorageHasher::Blake2_128Concat],
+                            key: meta_type::<AccountId32>(),
+                            value: meta_type::<Vec<u64>>(),
+                        },
+                        default: vec![0],
+                        docs: vec![],
+                    },
+                    StorageEntryMetadata {
+                        name: "Approvals",
+                        modifier: StorageEntryModifier::Optional,
+                        ty: StorageEntryType::Map {
+                            hashers: vec![
+                                StorageHasher::Blake2_128Concat,
+                                StorageHasher::Twox64Concat,
+                                StorageHasher::Identity,
+                            ],
+                            key: meta_type::<(u32, AccountId32, u64)>(),
+                            value: meta_type::<u128>(),
+                        },
+                        default: vec![0],
+                        docs: vec![],
+                    },
+                    StorageEntryMetadata {
+                        name: "Never",
+                        modifier: StorageEntryModifier::Optional,
+                        ty: StorageEntryType::Plain(meta_type::<Void>()),
+                        default: vec![0],
+                        docs: vec![],
+                    },
+                ],
+            }),
+            calls: Some(PalletCallMetadata {
+                ty: meta_type::<Call>(),
+            }),
+            event: Some(PalletEventMetadata {
+                ty: meta_type::<Event>(),
+            }),
+            constants: vec![PalletConstantMetadata {
+                name: "ExistentialDeposit",
+                ty: meta_type::<u128>(),
+                value: 500u128.encode(),
+                docs: vec![],
+            }],
+            error: None,
+            index: 5,
+        };
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 4,
+            signed_extensions: vec![],
+        };
+        RuntimeMetadataV14::new(vec![pallet], extrinsic, meta_type::<()>()).into()
+    }
+
+    fn generated() -> String {
+        generate_runtime_api(&metadata(), "crate::runtime")
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn generates_types_following_their_path() {
+        let generated = generated();
+        assert!(generated.contains("pub mod types"));
+        assert!(generated.contains("pub struct AccountId32"));
+        assert!(generated.contains("pub enum Call"));
+        assert!(generated.contains("# [codec (compact)] value : u128"));
+    }
+
+    #[test]
+    fn generates_pallet_module() {
+        let generated = generated();
+        assert!(generated.contains("pub mod balances"));
+        assert!(generated.contains("pub const PALLET_INDEX : u8 = 5u8"));
+        // calls
+        assert!(generated.contains("pub fn transfer"));
+        assert!(generated.contains("pub fn remark"));
+        // storage
+        assert!(generated.contains("pub fn total_issuance"));
+        assert!(generated.contains("pub fn locks"));
+        // events
+        assert!(generated.contains("pub struct Transfer"));
+        assert!(generated.contains("pub struct Remarked"));
+        assert!(generated.contains("\" Transfer succeeded.\""));
+        assert!(generated.contains(":: core :: option :: Option < u32 >"));
+        // constants
+        assert!(generated.contains("pub fn existential_deposit"));
+    }
+
+    #[test]
+    fn boxes_fields_containing_their_type() {
+        let generated = generated();
+        assert!(generated.contains(
+            "dispatch { call : :: std :: boxed :: Box < crate :: runtime :: types :: ac_codegen :: tests :: Call > , }"
+        ));
+        assert!(generated.contains(
+            "batch { calls : :: std :: vec :: Vec < crate :: runtime :: types :: ac_codegen :: tests :: Call > , }"
+        ));
+        assert!(generated.contains(
+            "pub fn dispatch (call : crate :: runtime :: types :: ac_codegen :: tests :: Call ,)"
+        ));
+    }
+
+    #[test]
+    fn keeps_compact_composites() {
+        let generated = generated();
+        assert!(generated.contains(
+            "# [codec (compact)] rate : crate :: runtime :: types :: ac_codegen :: tests :: Perbill"
+        ));
+        assert!(generated.contains("# [derive (:: codec :: CompactAs)] pub struct Perbill"));
+        assert!(!generated.contains("# [derive (:: codec :: CompactAs)] pub struct AccountId32"));
+    }
+
+    #[test]
+    fn implements_codec_for_empty_enums() {
+        let generated = generated();
+        assert!(generated.contains("pub enum Void { }"));
+        assert!(generated.contains("impl :: codec :: Encode for Void"));
+        assert!(generated.contains("impl :: codec :: Decode for Void"));
+        assert!(generated.contains("pub fn never"));
+    }
+
+    #[test]
+    fn builds_keys_of_n_maps() {
+        let generated = generated();
+        assert!(generated.contains("pub fn approvals"));
+        assert!(generated.contains(
+            "key1 : u32 , key2 : crate :: runtime :: types :: ac_codegen :: tests :: AccountId32 , key3 : u64 ,"
+        ));
+        assert!(generated.contains(":: sp_core :: hashing :: twox_64"));
+        assert!(generated.contains("api . get_storage_by_key_hash"));
+    }
+
+    #[test]
+    fn rejects_invalid_prefix() {
+        let mut metadata = metadata();
+        metadata.0 = 0;
+        assert!(matches!(
+            generate_runtime_api(&metadata, "crate::runtime"),
+            Err(CodegenError::InvalidPrefix)
+        ));
+    }
+
+    #[test]
+    fn keywords_become_raw_identifiers() {
+        assert_eq!(ident("type").to_string(), "r#type");
+        assert_eq!(ident("macro").to_string(), "r#macro");
+        assert_eq!(ident("final").to_string(), "r#final");
+        assert_eq!(ident("self").to_string(), "self_");
+        assert_eq!(ident("dest").to_string(), "dest");
+    }
+}
@@ -0,0 +1,374 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+//! One module per pallet, with call builders, storage accessors, events and constants.
+
+use frame_metadata::v14::{PalletMetadata, StorageEntryType, StorageHasher};
+use heck::{ToSnakeCase, ToUpperCamelCase};
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use scale_info::{form::PortableForm, TypeDef};
+
+use crate::types::{derives, is_named, TypeGenerator};
+use crate::{docs, ident, CodegenError};
+
+pub(crate) fn generate_pallet(
+    types: &TypeGenerator,
+    pallet: &PalletMetadata<PortableForm>,
+) -> Result<TokenStream, CodegenError> {
+    let mod_name = ident(&pallet.name.to_snake_case());
+    let name = &pallet.name;
+    let index = pallet.index;
+    let calls = generate_calls(types, pallet)?;
+    let storage = generate_storage(types, pallet)?;
+    let events = generate_events(types, pallet)?;
+    let constants = generate_constants(types, pallet)?;
+
+    Ok(quote! {
+        #[allow(dead_code, clippy::all)]
+        pub mod #mod_name {
+            /// Name of the pallet in the runtime.
+            pub const PALLET: &str = #name;
+            /// Index of the pallet in the runtime.
+            pub const PALLET_INDEX: u8 = #index;
+
+            #calls
+            #storage
+            #events
+            #constants
+        }
+    })
+}
+
+/// Bounds of the `Api` the storage and constant accessors are generic over.
+fn api_bounds() -> (TokenStream, TokenStream) {
+    (
+        quote!(api: &::substrate_api_client::Api<P, Client, Params>),
+        quote! {
+            where
+                Client: ::substrate_api_client::RpcClient,
+                Params: ::substrate_api_client::ExtrinsicParams,
+        },
+    )
+}
+
+fn generate_calls(
+    types: &TypeGenerator,
+    pallet: &PalletMetadata<PortableForm>,
+) -> Result<TokenStream, CodegenError> {
+    let call = match &pallet.calls {
+        Some(call) => call,
+        None => return Ok(quote!()),
+    };
+    let call_ty = types.type_path(call.ty.id())?;
+    let variants = match types.resolve(call.ty.id())?.type_def() {
+        TypeDef::Variant(variant) => variant.variants(),
+        _ => return Err(CodegenError::TypeDefNotVariant(call.ty.id())),
+    };
+
+    let builders = variants
+        .iter()
+        .map(|variant| {
+            let fn_name = ident(&variant.name().to_snake_case());
+            let variant_name = ident(&variant.name().to_upper_camel_case());
+            let docs = docs(variant.docs());
+            let named = is_named(variant.fields());
+
+            let mut args = Vec::new();
+            let mut values = Vec::new();
+            for (i, field) in variant.fields().iter().enumerate() {
+                let field_type = types.field_type(field, Some(call.ty.id()))?;
+                let arg = match field.name() {
+                    Some(name) if named => ident(name),
+                    _ => format_ident!("arg{}", i),
+                };
+                let ty = &field_type.ty;
+                args.push(quote!(#arg: #ty));
+                let value = if field_type.boxed {
+                    quote!(::std::boxed::Box::new(#arg))
+                } else {
+                    quote!(#arg)
+                };
+                values.push(if named { quote!(#arg: #value) } else { value });
+            }
+            let construct = if variant.fields().is_empty() {
+                quote!(#call_ty::#variant_name)
+            } else if named {
+                quote!(#call_ty::#variant_name { #(#values,)* })
+            } else {
+                quote!(#call_ty::#variant_name( #(#values,)* ))
+            };
+
+            Ok(quote! {
+                #docs
+                pub fn #fn_name(#(#args,)*) -> Call {
+                    Call(#construct)
+                }
+            })
+        })
+        .collect::<Result<Vec<_>, CodegenError>>()?;
+
+    Ok(quote! {
+        pub mod calls {
+            /// A call of this pallet. Encodes with the pallet index in front, as expected by the
+            /// runtime, e.g. by `compose_extrinsic_offline!`.
+            #[derive(Clone, Debug, PartialEq, Eq)]
+            pub struct Call(pub #call_ty);
+
+            impl ::codec::Encode for Call {
+                fn size_hint(&self) -> usize {
+                    1 + ::codec::Encode::size_hint(&self.0)
+                }
+
+                fn encode_to<T: ::codec::Output + ?Sized>(&self, dest: &mut T) {
+                    ::codec::Encode::encode_to(&super::PALLET_INDEX, dest);
+                    ::codec::Encode::encode_to(&self.0, dest);
+                }
+            }
+
+            #(#builders)*
+        }
+    })
+}
+
+fn generate_storage(
+    types: &TypeGenerator,
+    pallet: &PalletMetadata<PortableForm>,
+) -> Result<TokenStream, CodegenError> {
+    let storage = match &pallet.storage {
+        Some(storage) => storage,
+        None => return Ok(quote!()),
+    };
+    let (api, bounds) = api_bounds();
+
+    let mut accessors = Vec::new();
+    for entry in &storage.entries {
+        let fn_name = ident(&entry.name.to_snake_case());
+        let name = &entry.name;
+        let docs = docs(&entry.docs);
+        let accessor = match &entry.ty {
+            StorageEntryType::Plain(value) => {
+                let value = types.type_path(value.id())?;
+                quote! {
+                    #docs
+                    pub fn #fn_name<P, Client, Params>(
+                        #api,
+                        at_block: Option<::sp_core::H256>,
+                    ) -> ::substrate_api_client::ApiResult<Option<#value>>
+                    #bounds
+                    {
+                        api.get_storage_value(super::PALLET, #name, at_block)
+                    }
+                }
+            }
+            StorageEntryType::Map {
+                hashers,
+                key,
+                value,
+            } => {
+                let value = types.type_path(value.id())?;
+                match hashers.len() {
+                    1 => {
+                        let key = types.type_path(key.id())?;
+                        quote! {
+                            #docs
+                            pub fn #fn_name<P, Client, Params>(
+                                #api,
+                                key: #key,
+                                at_block: Option<::sp_core::H256>,
+                            ) -> ::substrate_api_client::ApiResult<Option<#value>>
+                            #bounds
+                            {
+                                api.get_storage_map(super::PALLET, #name, key, at_block)
+                            }
+                        }
+                    }
+                    2 => {
+                        let (first, second) = match types.resolve(key.id())?.type_def() {
+                            TypeDef::Tuple(tuple) if tuple.fields().len() == 2 => (
+                                types.type_path(tuple.fields()[0].id())?,
+                                types.type_path(tuple.fields()[1].id())?,
+                            ),
+                            _ => return Err(CodegenError::InvalidStorageKey(name.clone())),
+                        };
+                        quote! {
+                            #docs
+                            pub fn #fn_name<P, Client, Params>(
+                                #api,
+                                first: #first,
+                                second: #second,
+                                at_block: Option<::sp_core::H256>,
+                            ) -> ::substrate_api_client::ApiResult<Option<#value>>
+                            #bounds
+                            {
+                                api.get_storage_double_map(
+                                    super::PALLET,
+                                    #name,
+                                    first,
+                                    second,
+                                    at_block,
+                                )
+                            }
+                        }
+                    }
+                    // The api has no accessor for maps with more keys, so the storage key is
+                    // built here.
+                    count => {
+                        let keys = match types.resolve(key.id())?.type_def() {
+                            TypeDef::Tuple(tuple) if tuple.fields().len() == count => tuple
+                                .fields()
+                                .iter()
+                                .map(|field| types.type_path(field.id()))
+                                .collect::<Result<Vec<_>, _>>()?,
+                            _ => return Err(CodegenError::InvalidStorageKey(name.clone())),
+                        };
+                        let args: Vec<_> = (1..=count).map(|i| format_ident!("key{}", i)).collect();
+                        let hashed = hashers.iter().zip(&args).map(|(hasher, arg)| {
+                            hash_key(hasher, quote!(::codec::Encode::encode(&#arg)))
+                        });
+                        let prefix = &storage.prefix;
+                        quote! {
+                            #docs
+                            pub fn #fn_name<P, Client, Params>(
+                                #api,
+                                #(#args: #keys,)*
+                                at_block: Option<::sp_core::H256>,
+                            ) -> ::substrate_api_client::ApiResult<Option<#value>>
+                            #bounds
+                            {
+                                let mut key = ::sp_core::hashing::twox_128(#prefix.as_bytes()).to_vec();
+                                key.extend(::sp_core::hashing::twox_128(#name.as_bytes()));
+                                #(key.extend(#hashed);)*
+                                api.get_storage_by_key_hash(::sp_core::storage::StorageKey(key), at_block)
+                            }
+                        }
+                    }
+                }
+            }
+        };
+        accessors.push(accessor);
+    }
+
+    Ok(quote! {
+        pub mod storage {
+            #(#accessors)*
+        }
+    })
+}
+
+/// Returns the bytes `encoded` contributes to a storage key, given its `hasher`.
+fn hash_key(hasher: &StorageHasher, encoded: TokenStream) -> TokenStream {
+    let hashing = quote!(::sp_core::hashing);
+    match hasher {
+        StorageHasher::Blake2_128 => quote!(#hashing::blake2_128(&#encoded).to_vec()),
+        StorageHasher::Blake2_256 => quote!(#hashing::blake2_256(&#encoded).to_vec()),
+        StorageHasher::Blake2_128Concat => quote!({
+            let encoded = #encoded;
+            [#hashing::blake2_128(&encoded).to_vec(), encoded].concat()
+        }),
+        StorageHasher::Twox128 => quote!(#hashing::twox_128(&#encoded).to_vec()),
+        StorageHasher::Twox256 => quote!(#hashing::twox_256(&#encoded).to_vec()),
+        StorageHasher::Twox64Concat => quote!({
+            let encoded = #encoded;
+            [#hashing::twox_64(&encoded).to_vec(), encoded].concat()
+        }),
+        StorageHasher::Identity => encoded,
+    }
+}
+
+fn generate_events(
+    types: &TypeGenerator,
+    pallet: &PalletMetadata<PortableForm>,
+) -> Result<TokenStream, CodegenError> {
+    let event = match &pallet.event {
+        Some(event) => event,
+        None => return Ok(quote!()),
+    };
+    let variants = match types.resolve(event.ty.id())?.type_def() {
+        TypeDef::Variant(variant) => variant.variants(),
+        _ => return Err(CodegenError::TypeDefNotVariant(event.ty.id())),
+    };
+    let derives = derives();
+
+    let events = variants
+        .iter()
+        .map(|variant| {
+            let name: Ident = ident(&variant.name().to_upper_camel_case());
+            let event = variant.name();
+            let docs = docs(variant.docs());
+            let fields = types.fields(variant.fields(), quote!(pub), None)?;
+            let semicolon = if is_named(variant.fields()) {
+                quote!()
+            } else {
+                quote!(;)
+            };
+            Ok(quote! {
+                #docs
+                #derives
+                pub struct #name #fields #semicolon
+
+                impl ::substrate_api_client::StaticEvent for #name {
+                    const PALLET: &'static str = super::PALLET;
+                    const EVENT: &'static str = #event;
+                }
+            })
+        })
+        .collect::<Result<Vec<_>, CodegenError>>()?;
+
+    Ok(quote! {
+        pub mod events {
+            #(#events)*
+        }
+    })
+}
+
+fn generate_constants(
+    types: &TypeGenerator,
+    pallet: &PalletMetadata<PortableForm>,
+) -> Result<TokenStream, CodegenError> {
+    if pallet.constants.is_empty() {
+        return Ok(quote!());
+    }
+    let (api, bounds) = api_bounds();
+
+    let constants = pallet
+        .constants
+        .iter()
+        .map(|constant| {
+            let fn_name = ident(&constant.name.to_snake_case());
+            let name = &constant.name;
+            let ty = types.type_path(constant.ty.id())?;
+            let docs = docs(&constant.docs);
+            Ok(quote! {
+                #docs
+                pub fn #fn_name<P, Client, Params>(
+                    #api,
+                ) -> ::substrate_api_client::ApiResult<#ty>
+                #bounds
+                {
+                    api.get_constant(super::PALLET, #name)
+                }
+            })
+        })
+        .collect::<Result<Vec<_>, CodegenError>>()?;
+
+    Ok(quote! {
+        pub mod constants {
+            #(#constants)*
+        }
+    })
+}
@@ -0,0 +1,498 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+//! Rust types for the types of the metadata's type registry.
+//!
+//! Generic types are generated once per instantiation, as the registry only contains those. If
+//! several types end up with the same name in the same module, a counter is appended. Fields
+//! containing their enclosing type, e.g. calls within calls, are boxed.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use heck::ToUpperCamelCase;
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+use scale_info::{form::PortableForm, Field, PortableRegistry, Type, TypeDef, TypeDefPrimitive};
+
+use crate::{docs, ident, CodegenError};
+
+/// Type of a struct or enum field.
+pub(crate) struct FieldType {
+    /// The type itself, for compact fields the type within `Compact`.
+    pub ty: TokenStream,
+    /// The field is compact encoded.
+    pub compact: bool,
+    /// The field is boxed, as it contains the type it is declared in.
+    pub boxed: bool,
+}
+
+impl FieldType {
+    /// The type as it is declared in the struct or enum.
+    pub fn declared(&self) -> TokenStream {
+        let ty = &self.ty;
+        if self.boxed {
+            quote!(::std::boxed::Box<#ty>)
+        } else {
+            quote!(#ty)
+        }
+    }
+
+    /// The attributes the field is declared with.
+    pub fn attributes(&self) -> TokenStream {
+        if self.compact {
+            quote!(#[codec(compact)])
+        } else {
+            quote!()
+        }
+    }
+}
+
+pub(crate) struct TypeGenerator<'a> {
+    types: &'a PortableRegistry,
+    /// Path to the module the generated code is included in.
+    root: TokenStream,
+    /// Module path and name of the types that get a definition.
+    names: BTreeMap<u32, (Vec<String>, String)>,
+    /// Composites that are compact encoded and therefore derive `CompactAs`.
+    compact_as: BTreeSet<u32>,
+}
+
+impl<'a> TypeGenerator<'a> {
+    pub fn new(types: &'a PortableRegistry, root: TokenStream) -> Self {
+        let mut names = BTreeMap::new();
+        let mut taken = BTreeSet::new();
+        for portable in types.types() {
+            let ty = portable.ty();
+            if !matches!(ty.type_def(), TypeDef::Composite(_) | TypeDef::Variant(_))
+                || substitute(ty).is_some()
+            {
+                continue;
+            }
+            let segments = ty.path().segments();
+            let (module, ident) = match segments.split_last() {
+                Some((ident, module)) => (module.to_vec(), ident.clone()),
+                None => (Vec::new(), format!("Type{}", portable.id())),
+            };
+            let mut name = ident.clone();
+            let mut counter = 1;
+            while !taken.insert((module.clone(), name.clone())) {
+                counter += 1;
+                name = format!("{}{}", ident, counter);
+            }
+            names.insert(portable.id(), (module, name));
+        }
+
+        let mut compact_as = BTreeSet::new();
+        for portable in types.types() {
+            if let TypeDef::Compact(compact) = portable.ty().type_def() {
+                let mut inner = compact.type_param().id();
+                while let Some(TypeDef::Composite(composite)) =
+                    types.resolve(inner).map(|ty| ty.type_def())
+                {
+                    match composite.fields() {
+                        [field] => {
+                            compact_as.insert(inner);
+                            inner = field.ty().id();
+                        }
+                        _ => break,
+                    }
+                }
+            }
+        }
+        Self {
+            types,
+            root,
+            names,
+            compact_as,
+        }
+    }
+
+    pub fn resolve(&self, type_id: u32) -> Result<&'a Type<PortableForm>, CodegenError> {
+        self.types
+            .resolve(type_id)
+            .ok_or(CodegenError::TypeNotFound(type_id))
+    }
+
+    /// Returns the path of the Rust type for `type_id`.
+    pub fn type_path(&self, type_id: u32) -> Result<TokenStream, CodegenError> {
+        let ty = self.resolve(type_id)?;
+        if let Some(substitute) = substitute(ty) {
+            let params = ty
+                .type_params()
+                .iter()
+                .map(|param| match param.ty() {
+                    Some(param) => self.type_path(param.id()),
+                    None => Ok(quote!(())),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(substitute(&params));
+        }
+        if let Some((module, name)) = self.names.get(&type_id) {
+            let root = &self.root;
+            let module = module.iter().map(|m| ident(m));
+            let name = ident(name);
+            return Ok(quote!(#root::types::#(#module::)*#name));
+        }
+
+        let path = match ty.type_def() {
+            TypeDef::Primitive(primitive) => primitive_path(primitive),
+            TypeDef::Sequence(seq) => {
+                let inner = self.type_path(seq.type_param().id())?;
+                quote!(::std::vec::Vec<#inner>)
+            }
+            TypeDef::Array(arr) => {
+                let inner = self.type_path(arr.type_param().id())?;
+                let len = Literal::u32_unsuffixed(arr.len());
+                quote!([#inner; #len])
+            }
+            TypeDef::Tuple(tuple) => {
+                let fields = tuple
+                    .fields()
+                    .iter()
+                    .map(|field| self.type_path(field.id()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                quote!((#(#fields,)*))
+            }
+            TypeDef::Compact(compact) => {
+                let inner = self.compact_inner(compact.type_param().id())?;
+                quote!(::codec::Compact<#inner>)
+            }
+            TypeDef::BitSequence(bit_sequence) => {
+                let store = self.type_path(bit_sequence.bit_store_type().id())?;
+                let order = match self
+                    .resolve(bit_sequence.bit_order_type().id())?
+                    .path()
+                    .ident()
+                    .as_deref()
+                {
+                    Some("Msb0") => quote!(::bitvec::order::Msb0),
+                    _ => quote!(::bitvec::order::Lsb0),
+                };
+                quote!(::bitvec::vec::BitVec<#store, #order>)
+            }
+            // All composites and variants have a name.
+            TypeDef::Composite(_) | TypeDef::Variant(_) => {
+                return Err(CodegenError::TypeNotFound(type_id))
+            }
+        };
+        Ok(path)
+    }
+
+    /// Returns the type of a struct or enum field declared in the type `parent`, if any.
+    pub fn field_type(
+        &self,
+        field: &Field<PortableForm>,
+        parent: Option<u32>,
+    ) -> Result<FieldType, CodegenError> {
+        let boxed = match parent {
+            Some(parent) => self.contains(field.ty().id(), parent, &mut BTreeSet::new())?,
+            None => false,
+        };
+        let (ty, compact) = match self.resolve(field.ty().id())?.type_def() {
+            TypeDef::Compact(compact) => (self.compact_inner(compact.type_param().id())?, true),
+            _ => (self.type_path(field.ty().id())?, false),
+        };
+        Ok(FieldType { ty, compact, boxed })
+    }
+
+    /// Whether a value of `type_id` contains a value of `target` without indirection, i.e. not
+    /// within a `Vec`.
+    fn contains(
+        &self,
+        type_id: u32,
+        target: u32,
+        visited: &mut BTreeSet<u32>,
+    ) -> Result<bool, CodegenError> {
+        if type_id == target {
+            return Ok(true);
+        }
+        if !visited.insert(type_id) {
+            return Ok(false);
+        }
+        let ty = self.resolve(type_id)?;
+        let inner: Vec<u32> = if substitute(ty).is_some() {
+            ty.type_params()
+                .iter()
+                .filter_map(|param| param.ty())
+                .map(|param| param.id())
+                .collect()
+        } else {
+            match ty.type_def() {
+                TypeDef::Composite(composite) => composite
+                    .fields()
+                    .iter()
+                    .map(|field| field.ty().id())
+                    .collect(),
+                TypeDef::Variant(variant) => variant
+                    .variants()
+                    .iter()
+                    .flat_map(|variant| variant.fields())
+                    .map(|field| field.ty().id())
+                    .collect(),
+                TypeDef::Array(arr) => vec![arr.type_param().id()],
+                TypeDef::Tuple(tuple) => tuple.fields().iter().map(|field| field.id()).collect(),
+                _ => Vec::new(),
+            }
+        };
+        for inner in inner {
+            if self.contains(inner, target, visited)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Returns the type within `Compact`. It is either an unsigned integer or a composite
+    /// wrapping one, e.g. `Perbill`, which derives `CompactAs`.
+    fn compact_inner(&self, type_id: u32) -> Result<TokenStream, CodegenError> {
+        self.check_compact(type_id)?;
+        self.type_path(type_id)
+    }
+
+    fn check_compact(&self, type_id: u32) -> Result<(), CodegenError> {
+        let ty = self.resolve(type_id)?;
+        match ty.type_def() {
+            TypeDef::Composite(composite) if substitute(ty).is_none() => match composite.fields() {
+                [field] => self.check_compact(field.ty().id()),
+                _ => Err(CodegenError::InvalidCompactType(type_id)),
+            },
+            TypeDef::Primitive(
+                TypeDefPrimitive::U8
+                | TypeDefPrimitive::U16
+                | TypeDefPrimitive::U32
+                | TypeDefPrimitive::U64
+                | TypeDefPrimitive::U128,
+            ) => Ok(()),
+            _ => Err(CodegenError::InvalidCompactType(type_id)),
+        }
+    }
+
+    /// Returns the fields of a struct, enum variant or event, i.e. `{ a: A }`, `(A)` or nothing.
+    /// `visibility` is put in front of every field, `parent` is the type they are declared in.
+    pub fn fields(
+        &self,
+        fields: &[Field<PortableForm>],
+        visibility: TokenStream,
+        parent: Option<u32>,
+    ) -> Result<TokenStream, CodegenError> {
+        let named = is_named(fields);
+        let declared = fields
+            .iter()
+            .map(|field| {
+                let field_type = self.field_type(field, parent)?;
+                let attributes = field_type.attributes();
+                let ty = field_type.declared();
+                let docs = docs(field.docs());
+                Ok(match field.name() {
+                    Some(name) if named => {
+                        let name = ident(name);
+                        quote!(#docs #attributes #visibility #name: #ty)
+                    }
+                    _ => quote!(#docs #attributes #visibility #ty),
+                })
+            })
+            .collect::<Result<Vec<_>, CodegenError>>()?;
+
+        Ok(if fields.is_empty() {
+            quote!()
+        } else if named {
+            quote!({ #(#declared,)* })
+        } else {
+            quote!(( #(#declared,)* ))
+        })
+    }
+
+    /// Returns the definitions of all named types, within modules according to their path.
+    pub fn generate(&self) -> Result<TokenStream, CodegenError> {
+        let mut root = Module::default();
+        for (type_id, (module, name)) in &self.names {
+            let definition = self.definition(*type_id, name)?;
+            let mut current = &mut root;
+            for segment in module {
+                current = current.children.entry(segment.clone()).or_default();
+            }
+            current.items.push(definition);
+        }
+        let types = root.render();
+        Ok(quote! {
+            #[allow(dead_code, non_camel_case_types, clippy::all)]
+            pub mod types {
+                #types
+            }
+        })
+    }
+
+    fn definition(&self, type_id: u32, type_name: &str) -> Result<TokenStream, CodegenError> {
+        let ty = self.resolve(type_id)?;
+        let name = ident(type_name);
+        let docs = docs(ty.docs());
+        let derives = derives();
+        let definition = match ty.type_def() {
+            TypeDef::Composite(composite) => {
+                let fields = self.fields(composite.fields(), quote!(pub), Some(type_id))?;
+                let semicolon = if fields.is_empty() || !is_named(composite.fields()) {
+                    quote!(;)
+                } else {
+                    quote!()
+                };
+                let compact_as = if self.compact_as.contains(&type_id) {
+                    quote!(#[derive(::codec::CompactAs)])
+                } else {
+                    quote!()
+                };
+                quote! {
+                    #docs
+                    #derives
+                    #compact_as
+                    pub struct #name #fields #semicolon
+                }
+            }
+            // The codec derives do not support enums without variants, e.g. `Void`.
+            TypeDef::Variant(variant) if variant.variants().is_empty() => {
+                let error = format!("{} has no variants", type_name);
+                quote! {
+                    #docs
+                    #[derive(Clone, Debug, PartialEq, Eq)]
+                    pub enum #name {}
+
+                    impl ::codec::Encode for #name {
+                        fn encode_to<T: ::codec::Output + ?Sized>(&self, _dest: &mut T) {
+                            match *self {}
+                        }
+                    }
+
+                    impl ::codec::Decode for #name {
+                        fn decode<I: ::codec::Input>(_input: &mut I) -> Result<Self, ::codec::Error> {
+                            Err(#error.into())
+                        }
+                    }
+                }
+            }
+            TypeDef::Variant(variant) => {
+                let variants = variant
+                    .variants()
+                    .iter()
+                    .map(|v| {
+                        let variant_name = ident(&v.name().to_upper_camel_case());
+                        let index = Literal::u8_unsuffixed(v.index());
+                        let fields = self.fields(v.fields(), quote!(), Some(type_id))?;
+                        let docs = docs(v.docs());
+                        Ok(quote! {
+                            #docs
+                            #[codec(index = #index)]
+                            #variant_name #fields
+                        })
+                    })
+                    .collect::<Result<Vec<_>, CodegenError>>()?;
+                quote! {
+                    #docs
+                    #derives
+                    pub enum #name {
+                        #(#variants,)*
+                    }
+                }
+            }
+            _ => return Err(CodegenError::TypeNotFound(type_id)),
+        };
+        Ok(definition)
+    }
+}
+
+/// Derives of all generated types.
+pub(crate) fn derives() -> TokenStream {
+    quote!(#[derive(::codec::Encode, ::codec::Decode, Clone, Debug, PartialEq, Eq)])
+}
+
+pub(crate) fn is_named(fields: &[Field<PortableForm>]) -> bool {
+    !fields.is_empty() && fields.iter().all(|field| field.name().is_some())
+}
+
+#[derive(Default)]
+struct Module {
+    children: BTreeMap<String, Module>,
+    items: Vec<TokenStream>,
+}
+
+impl Module {
+    fn render(&self) -> TokenStream {
+        let items = &self.items;
+        let children = self.children.iter().map(|(name, module)| {
+            let name = ident(name);
+            let content = module.render();
+            quote! {
+                pub mod #name {
+                    #content
+                }
+            }
+        });
+        quote! {
+            #(#items)*
+            #(#children)*
+        }
+    }
+}
+
+/// Types that are not generated, but replaced by the corresponding type of the std library or
+/// substrate, given their type parameters.
+#[allow(clippy::type_complexity)]
+fn substitute(ty: &Type<PortableForm>) -> Option<fn(&[TokenStream]) -> TokenStream> {
+    let path = ty.path().segments().join("::");
+    let substitute: fn(&[TokenStream]) -> TokenStream = match path.as_str() {
+        "Option" => |params| {
+            let inner = &params[0];
+            quote!(::core::option::Option<#inner>)
+        },
+        "Result" => |params| {
+            let (ok, err) = (&params[0], &params[1]);
+            quote!(::core::result::Result<#ok, #err>)
+        },
+        "Cow" => |params| params[0].clone(),
+        "sp_core::crypto::AccountId32" => |_| quote!(::sp_runtime::AccountId32),
+        "primitive_types::H256" => |_| quote!(::sp_core::H256),
+        _ => return None,
+    };
+    // The type parameters must be known to substitute generic types.
+    let expected_params = match path.as_str() {
+        "Option" | "Cow" => 1,
+        "Result" => 2,
+        _ => 0,
+    };
+    if ty.type_params().len() < expected_params {
+        return None;
+    }
+    Some(substitute)
+}
+
+fn primitive_path(primitive: &TypeDefPrimitive) -> TokenStream {
+    match primitive {
+        TypeDefPrimitive::Bool => quote!(bool),
+        TypeDefPrimitive::Char => quote!(char),
+        TypeDefPrimitive::Str => quote!(::std::string::String),
+        TypeDefPrimitive::U8 => quote!(u8),
+        TypeDefPrimitive::U16 => quote!(u16),
+        TypeDefPrimitive::U32 => quote!(u32),
+        TypeDefPrimitive::U64 => quote!(u64),
+        TypeDefPrimitive::U128 => quote!(u128),
+        TypeDefPrimitive::U256 => quote!([u8; 32]),
+        TypeDefPrimitive::I8 => quote!(i8),
+        TypeDefPrimitive::I16 => quote!(i16),
+        TypeDefPrimitive::I32 => quote!(i32),
+        TypeDefPrimitive::I64 => quote!(i64),
+        TypeDefPrimitive::I128 => quote!(i128),
+        TypeDefPrimitive::I256 => quote!([u8; 32]),
+    }
+}
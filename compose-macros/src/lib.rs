@@ -35,16 +35,29 @@ pub use sp_runtime;
 /// * 'module' - Module name as &str for which the call is composed.
 /// * 'call' - Call name as &str
 /// * 'args' - Optional sequence of arguments of the call. They are not checked against the metadata.
-/// As of now the user needs to check himself that the correct arguments are supplied.
+/// As of now the user needs to check himself that the correct arguments are supplied, or use
+/// `PalletMetadata::encode_call_dynamic`, which does check them.
+///
+/// Panics if the module or the call is not in the metadata, see [`try_compose_call`] otherwise.
 #[macro_export]
 macro_rules! compose_call {
 ($node_metadata: expr, $pallet: expr, $call_name: expr $(, $args: expr) *) => {
         {
-            let pallet = $node_metadata.pallet($pallet).unwrap().to_owned();
-
-            let call_index = pallet.calls.get($call_name).unwrap();
+            $crate::try_compose_call!($node_metadata, $pallet, $call_name $(, $args) *).unwrap()
+        }
+    };
+}
 
-            ([pallet.index, *call_index as u8] $(, ($args)) *)
+/// Like [`compose_call`], but returns a `MetadataError` instead of panicking if the module or
+/// the call is not in the metadata.
+#[macro_export]
+macro_rules! try_compose_call {
+($node_metadata: expr, $pallet: expr, $call_name: expr $(, $args: expr) *) => {
+        {
+            $node_metadata.pallet($pallet).and_then(|pallet| {
+                let call_index = pallet.call_index($call_name)?;
+                Ok(([pallet.index, call_index] $(, ($args)) *))
+            })
         }
     };
 }
@@ -52,37 +65,40 @@ macro_rules! compose_call {
 /// Generates an Unchecked extrinsic for a given call
 /// # Arguments
 ///
-/// * 'signer' - AccountKey that is used to sign the extrinsic.
+/// * 'signer' - Signer of the extrinsic, any type implementing `ac_primitives::Signer`.
 /// * 'call' - call as returned by the compose_call! macro or via substrate's call enums.
-/// * 'nonce' - signer's account nonce: u32
-/// * 'era' - Era for extrinsic to be valid
-/// * 'genesis_hash' - sp-runtime::Hash256/[u8; 32].
-/// * 'runtime_spec_version' - RuntimeVersion.spec_version/u32
+/// * 'params' - ExtrinsicParams holding the nonce, era, genesis hash and runtime versions.
+///
+/// Panics if the signer fails to sign the extrinsic, see [`try_compose_extrinsic_offline`]
+/// otherwise.
 #[macro_export]
 macro_rules! compose_extrinsic_offline {
     ($signer: expr,
     $call: expr,
     $params: expr) => {{
-        use $crate::primitives::{
-            ExtrinsicParams, GenericAddress, SignedPayload, UncheckedExtrinsicV4,
-        };
-        use $crate::sp_core::crypto::Pair;
-        use $crate::sp_runtime::{generic::Era, traits::IdentifyAccount, MultiSigner};
+        $crate::try_compose_extrinsic_offline!($signer, $call, $params).unwrap()
+    }};
+}
+
+/// Like [`compose_extrinsic_offline`], but returns a `SignError` instead of panicking if the
+/// signer fails to sign the extrinsic.
+#[macro_export]
+macro_rules! try_compose_extrinsic_offline {
+    ($signer: expr,
+    $call: expr,
+    $params: expr) => {{
+        use $crate::primitives::{ExtrinsicParams, SignedPayload, Signer, UncheckedExtrinsicV4};
 
+        let signer = &$signer;
         let extra = $params.signed_extra();
         let raw_payload =
-            SignedPayload::from_raw($call.clone(), extra, $params.additional_signed());
+            SignedPayload::from_raw($call.clone(), extra.clone(), $params.additional_signed());
 
-        let signature = raw_payload.using_encoded(|payload| $signer.sign(payload));
-
-        let multi_signer: MultiSigner = $signer.public().into();
-
-        UncheckedExtrinsicV4::new_signed(
-            $call,
-            GenericAddress::from(multi_signer.into_account()),
-            signature.into(),
-            extra,
-        )
+        raw_payload
+            .using_encoded(|payload| Signer::sign(signer, payload))
+            .map(|signature| {
+                UncheckedExtrinsicV4::new_signed($call, Signer::address(signer), signature, extra)
+            })
     }};
 }
 
@@ -93,10 +109,34 @@ macro_rules! compose_extrinsic_offline {
 /// * 'module' - Module name as &str for which the call is composed.
 /// * 'call' - Call name as &str
 /// * 'args' - Optional sequence of arguments of the call. They are not checked against the metadata.
-/// As of now the user needs to check himself that the correct arguments are supplied.
+/// As of now the user needs to check himself that the correct arguments are supplied, or use
+/// `PalletMetadata::encode_call_dynamic`, which does check them.
+///
+/// Panics if the call can not be composed, the nonce not be fetched or the extrinsic not be
+/// signed, see [`try_compose_extrinsic`] otherwise.
 #[macro_export]
 #[cfg(feature = "std")]
 macro_rules! compose_extrinsic {
+	($api: expr,
+	$module: expr,
+	$call: expr
+	$(, $args: expr) *) => {
+		{
+            $crate::try_compose_extrinsic!($api, $module, $call $(, $args) *).unwrap()
+		}
+    };
+}
+
+/// Like [`compose_extrinsic`], but returns an `ApiResult` instead of panicking if the module or
+/// the call is not in the metadata, the nonce of the signer can not be fetched or the signer
+/// fails to sign the extrinsic.
+///
+/// The nonce is taken from `Api::next_nonce`, so it is reserved if the api has a nonce manager,
+/// and the params from `Api::default_extrinsic_params`, so the extrinsic is mortal if the api has
+/// been made mortal.
+#[macro_export]
+#[cfg(feature = "std")]
+macro_rules! try_compose_extrinsic {
 	($api: expr,
 	$module: expr,
 	$call: expr
@@ -109,18 +149,21 @@ macro_rules! compose_extrinsic {
             use $crate::sp_runtime::generic::Era;
 
             debug!("Composing generic extrinsic for module {:?} and call {:?}", $module, $call);
-            let call = $crate::compose_call!($api.metadata.clone(), $module, $call $(, ($args)) *);
-            if let Some(signer) = $api.signer.clone() {
-                $crate::compose_extrinsic_offline!(
-                    signer,
-                    call.clone(),
-                    $api.extrinsic_params($api.get_nonce().unwrap())
-                )
-            } else {
-                UncheckedExtrinsicV4 {
-                    signature: None,
-                    function: call.clone(),
-                }
+            match $crate::try_compose_call!($api.metadata.clone(), $module, $call $(, ($args)) *) {
+                Err(e) => Err(e.into()),
+                Ok(call) => match $api.signer.clone() {
+                    Some(signer) => $api
+                        .next_nonce()
+                        .and_then(|nonce| $api.default_extrinsic_params(nonce))
+                        .and_then(|params| {
+                            $crate::try_compose_extrinsic_offline!(signer, call.clone(), params)
+                                .map_err(|e| e.into())
+                        }),
+                    None => Ok(UncheckedExtrinsicV4 {
+                        signature: None,
+                        function: call.clone(),
+                    }),
+                },
             }
 		}
     };
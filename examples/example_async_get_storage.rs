@@ -0,0 +1,71 @@
+/*
+    Copyright 2019 Supercomputing Systems AG
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+///! Very simple example that shows how to get some simple storage values.
+
+///! Very simple example that shows how to use the async api with the `async-client` feature.
+use clap::{load_yaml, App};
+use futures::StreamExt;
+
+use node_template_runtime::Header;
+use sp_keyring::AccountKeyring;
+use substrate_api_client::rpc::AsyncWsRpcClient;
+use substrate_api_client::{AccountInfo, AsyncApi, PlainTipExtrinsicParams};
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    let url = get_node_url_from_cli();
+
+    let client = AsyncWsRpcClient::new(&url).await.unwrap();
+    let api = AsyncApi::<sp_core::sr25519::Pair, _, PlainTipExtrinsicParams>::new(client)
+        .await
+        .unwrap();
+
+    // get some plain storage value
+    let result: u128 = api
+        .get_storage_value("Balances", "TotalIssuance", None)
+        .await
+        .unwrap()
+        .unwrap();
+    println!("[+] TotalIssuance is {}", result);
+
+    // get StorageMap
+    let account = AccountKeyring::Alice.public();
+    let result: AccountInfo = api
+        .get_storage_map("System", "Account", account, None)
+        .await
+        .unwrap()
+        .unwrap_or_default();
+    println!("[+] AccountInfo for Alice is {:?}", result);
+
+    // the subscription shares the connection with the requests above
+    let mut heads = api.subscribe_finalized_heads::<Header>().await.unwrap();
+    for _ in 0..3 {
+        let head = heads.next().await.unwrap().unwrap();
+        println!("[+] Finalized head: {:?}", head);
+    }
+}
+
+pub fn get_node_url_from_cli() -> String {
+    let yml = load_yaml!("cli.yml");
+    let matches = App::from_yaml(yml).get_matches();
+
+    let node_ip = matches.value_of("node-server").unwrap_or("ws://127.0.0.1");
+    let node_port = matches.value_of("node-port").unwrap_or("9944");
+    let url = format!("{}:{}", node_ip, node_port);
+    println!("Interacting with node on {}\n", url);
+    url
+}
@@ -25,7 +25,8 @@ use sp_keyring::AccountKeyring;
 
 use substrate_api_client::rpc::WsRpcClient;
 use substrate_api_client::{
-    compose_extrinsic_offline, Api, PlainTipExtrinsicParams, UncheckedExtrinsicV4, XtStatus,
+    compose_extrinsic_offline, Api, NonceManager, PlainTipExtrinsicParams, UncheckedExtrinsicV4,
+    XtStatus,
 };
 
 fn main() {
@@ -33,10 +34,11 @@ fn main() {
     let url = get_node_url_from_cli();
 
     // initialize api and set the signer (sender) that is used to sign the extrinsics
+    // the nonce manager hands out the nonces without waiting for the extrinsics to be included
     let from = AccountKeyring::Alice.pair();
     let client = WsRpcClient::new(&url);
     let api = Api::<_, _, PlainTipExtrinsicParams>::new(client)
-        .map(|api| api.set_signer(from))
+        .map(|api| api.set_signer(from).set_nonce_manager(NonceManager::new()))
         .unwrap();
 
     println!(
@@ -47,9 +49,8 @@ fn main() {
     // define the recipient
     let to = AccountKeyring::Bob.to_account_id();
 
-    let mut nonce = api.get_nonce().unwrap();
-    let first_nonce = nonce;
-    while nonce < first_nonce + 500 {
+    for _ in 0..500 {
+        let nonce = api.next_nonce().unwrap();
         // compose the extrinsic with all the element
         #[allow(clippy::redundant_clone)]
         let xt: UncheckedExtrinsicV4<_, _> = compose_extrinsic_offline!(
@@ -58,15 +59,13 @@ fn main() {
                 dest: GenericAddress::Id(to.clone()),
                 value: 1_000_000
             }),
-            api.extrinsic_params(nonce)
+            api.extrinsic_params(nonce).unwrap()
         );
         // send and watch extrinsic until finalized
         println!("sending extrinsic with nonce {}", nonce);
         let _blockh = api
             .send_extrinsic(xt.hex_encode(), XtStatus::Ready)
             .unwrap();
-
-        nonce += 1;
     }
 }
 
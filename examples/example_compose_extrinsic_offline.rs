@@ -14,19 +14,20 @@
 */
 
 //! This examples shows how to use the compose_extrinsic_offline macro which generates an extrinsic
-//! without asking the node for nonce and does not need to know the metadata
+//! without asking the node for nonce and does not need to know the metadata. It then composes
+//! the same extrinsic with the signed extensions listed in the metadata of the node.
 
 use clap::{load_yaml, App};
 
 use ac_primitives::PlainTipExtrinsicParamsBuilder;
-use node_template_runtime::{BalancesCall, Header, RuntimeCall};
+use node_template_runtime::{BalancesCall, RuntimeCall};
 use sp_keyring::AccountKeyring;
-use sp_runtime::generic::Era;
 use sp_runtime::MultiAddress;
 
 use substrate_api_client::rpc::WsRpcClient;
 use substrate_api_client::{
-    compose_extrinsic_offline, Api, PlainTipExtrinsicParams, UncheckedExtrinsicV4, XtStatus,
+    compose_extrinsic_offline, Api, EraCheckpoint, MetadataExtrinsicParams,
+    MetadataExtrinsicParamsBuilder, PlainTipExtrinsicParams, UncheckedExtrinsicV4, XtStatus,
 };
 
 fn main() {
@@ -41,10 +42,8 @@ fn main() {
         .map(|api| api.set_signer(from))
         .unwrap();
 
-    // Information for Era for mortal transactions
-    let head = api.get_finalized_head().unwrap().unwrap();
-    let h: Header = api.get_header(Some(head)).unwrap().unwrap();
-    let period = 5;
+    // Era for mortal transactions, starting at the latest finalized block
+    let (era, checkpoint) = api.mortal_era(8, EraCheckpoint::Finalized).unwrap();
 
     println!(
         "[+] Alice's Account Nonce is {}\n",
@@ -55,7 +54,7 @@ fn main() {
     let to = MultiAddress::Id(AccountKeyring::Bob.to_account_id());
 
     let tx_params = PlainTipExtrinsicParamsBuilder::new()
-        .era(Era::mortal(period, h.number.into()), head)
+        .era(era, checkpoint)
         .tip(0);
 
     let updated_api = api.set_extrinsic_params_builder(tx_params);
@@ -68,7 +67,7 @@ fn main() {
             dest: to.clone(),
             value: 42
         }),
-        updated_api.extrinsic_params(nonce)
+        updated_api.extrinsic_params(nonce).unwrap()
     );
 
     println!("[+] Composed Extrinsic:\n {:?}\n", xt);
@@ -80,6 +79,32 @@ fn main() {
         .send_extrinsic(xt.hex_encode(), XtStatus::InBlock)
         .unwrap();
     println!("[+] Transaction got included in block {:?}", blockh);
+
+    // Chains with custom signed extensions: encode the signed extensions the metadata lists.
+    // The builder is created anew from the metadata after every runtime upgrade.
+    let metadata_api = Api::<_, _, MetadataExtrinsicParams>::new(WsRpcClient::new(&url))
+        .and_then(|api| {
+            api.set_extrinsic_params_builder_from_metadata(|metadata| {
+                Ok(MetadataExtrinsicParamsBuilder::new(metadata)?.tip(0))
+            })
+        })
+        .map(|api| api.set_signer(AccountKeyring::Alice.pair()))
+        .unwrap();
+
+    let nonce = metadata_api.get_nonce().unwrap();
+    let xt: UncheckedExtrinsicV4<_, _> = compose_extrinsic_offline!(
+        metadata_api.signer.clone().unwrap(),
+        RuntimeCall::Balances(BalancesCall::transfer {
+            dest: to,
+            value: 42
+        }),
+        metadata_api.extrinsic_params(nonce).unwrap()
+    );
+
+    let blockh = metadata_api
+        .send_extrinsic(xt.hex_encode(), XtStatus::InBlock)
+        .unwrap();
+    println!("[+] Transaction got included in block {:?}", blockh);
 }
 
 pub fn get_node_url_from_cli() -> String {
@@ -15,19 +15,10 @@
 
 //! This example is community maintained and not CI tested, therefore it may not work as is.
 
-use std::sync::mpsc::channel;
-
 use clap::{load_yaml, App};
-use codec::Decode;
 use sp_keyring::AccountKeyring;
-use substrate_api_client::{rpc::WsRpcClient, AccountId, Api, PlainTipExtrinsicParams, XtStatus};
-
-#[allow(unused)]
-#[derive(Decode)]
-struct ContractInstantiatedEventArgs {
-    deployer: AccountId,
-    contract: AccountId,
-}
+use substrate_api_client::static_events::contracts::Instantiated;
+use substrate_api_client::{rpc::WsRpcClient, Api, PlainTipExtrinsicParams, XtStatus};
 
 fn main() {
     env_logger::init();
@@ -50,9 +41,7 @@ fn main() {
 "#;
     let wasm = wabt::wat2wasm(CONTRACT).expect("invalid wabt");
 
-    let (events_in, events_out) = channel();
-    api.subscribe_events(events_in)
-        .expect("cannot subscribe to events");
+    let mut events = api.subscribe_events().expect("cannot subscribe to events");
 
     let xt = api.contract_instantiate_with_code(
         1_000_000_000_000_000,
@@ -73,9 +62,7 @@ fn main() {
 
     println!("[+] Waiting for the contracts.Instantiated event");
 
-    let args: ContractInstantiatedEventArgs = api
-        .wait_for_event("Contracts", "Instantiated", None, &events_out)
-        .unwrap();
+    let args: Instantiated = api.wait_for_event(&mut events).unwrap();
 
     println!(
         "[+] Event was received. Contract deployed at: {:?}\n",
@@ -14,22 +14,18 @@
 */
 
 ///! Very simple example that shows how to subscribe to events.
-use std::sync::mpsc::channel;
-
 use clap::{load_yaml, App};
 use codec::Decode;
-use log::{debug, error};
+use log::debug;
 use sp_core::sr25519;
-use sp_core::H256 as Hash;
 
 // This module depends on node_runtime.
 // To avoid dependency collisions, node_runtime has been removed from the substrate-api-client library.
 // Replace this crate by your own if you run a custom substrate node to get your custom events.
-use node_template_runtime::RuntimeEvent;
+use node_template_runtime::Runtime;
 
 use substrate_api_client::rpc::WsRpcClient;
-use substrate_api_client::utils::FromHexString;
-use substrate_api_client::{Api, PlainTipExtrinsicParams};
+use substrate_api_client::{Api, PlainTipExtrinsicParams, Raw};
 
 fn main() {
     env_logger::init();
@@ -39,39 +35,31 @@ fn main() {
     let api = Api::<sr25519::Pair, _, PlainTipExtrinsicParams>::new(client).unwrap();
 
     println!("Subscribe to events");
-    let (events_in, events_out) = channel();
-    api.subscribe_events(events_in).unwrap();
-
-    for _ in 0..5 {
-        let event_str = events_out.recv().unwrap();
+    let events = api.subscribe_events().unwrap();
 
-        let _unhex = Vec::from_hex(event_str).unwrap();
-        let mut _er_enc = _unhex.as_slice();
-        let _events = Vec::<system::EventRecord<RuntimeEvent, Hash>>::decode(&mut _er_enc);
-        match _events {
-            Ok(evts) => {
-                for evr in &evts {
-                    println!("decoded: {:?} {:?}", evr.phase, evr.event);
-                    match &evr.event {
-                        RuntimeEvent::Balances(be) => {
-                            println!(">>>>>>>>>> balances event: {:?}", be);
-                            match &be {
-                                balances::Event::Transfer { from, to, amount } => {
-                                    println!("Transactor: {:?}", from);
-                                    println!("Destination: {:?}", to);
-                                    println!("Value: {:?}", amount);
-                                    return;
-                                }
-                                _ => {
-                                    debug!("ignoring unsupported balances event");
-                                }
-                            }
+    for evts in events.take(5) {
+        for (phase, event) in evts.unwrap() {
+            println!("decoded: {:?} {:?}", phase, event);
+            match event {
+                Raw::Event(raw) if raw.pallet == "Balances" => {
+                    let mut encoded = vec![raw.variant_index];
+                    encoded.extend_from_slice(&raw.data);
+                    let be = balances::Event::<Runtime>::decode(&mut encoded.as_slice()).unwrap();
+                    println!(">>>>>>>>>> balances event: {:?}", be);
+                    match &be {
+                        balances::Event::Transfer { from, to, amount } => {
+                            println!("Transactor: {:?}", from);
+                            println!("Destination: {:?}", to);
+                            println!("Value: {:?}", amount);
+                            return;
+                        }
+                        _ => {
+                            debug!("ignoring unsupported balances event");
                         }
-                        _ => debug!("ignoring unsupported module event: {:?}", evr.event),
                     }
                 }
+                _ => debug!("ignoring unsupported module event: {:?}", event),
             }
-            Err(_) => error!("couldn't decode event record list"),
         }
     }
 }
@@ -13,26 +13,15 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
-use std::sync::mpsc::channel;
-
 use clap::{load_yaml, App};
-use codec::Decode;
 use sp_core::crypto::Pair;
 use sp_keyring::AccountKeyring;
 use sp_runtime::app_crypto::sp_core::sr25519;
-use sp_runtime::AccountId32 as AccountId;
 use sp_runtime::MultiAddress;
 use substrate_api_client::rpc::WsRpcClient;
+use substrate_api_client::static_events::balances::Transfer;
 use substrate_api_client::{Api, ApiResult, PlainTipExtrinsicParams, XtStatus};
 
-// Look at the how the transfer event looks like in in the metadata
-#[derive(Decode)]
-struct TransferEventArgs {
-    from: AccountId,
-    to: AccountId,
-    value: u128,
-}
-
 fn main() {
     env_logger::init();
     let url = get_node_url_from_cli();
@@ -81,16 +70,14 @@ fn main() {
     println!("[+] Transaction got included. Hash: {:?}\n", tx_hash);
 
     //Transfer will failed as Alice want to transfer all her balance. She has not enough money to pay the fee
-    let (events_in, events_out) = channel();
-    api.subscribe_events(events_in).unwrap();
-    let args: ApiResult<TransferEventArgs> =
-        api.wait_for_event("Balances", "Transfer", None, &events_out);
+    let mut events = api.subscribe_events().unwrap();
+    let args: ApiResult<Transfer> = api.wait_for_event(&mut events);
     match args {
         Ok(transfer_event) => {
             println!("Transfer event received!!!\n");
             println!("Transactor: {:?}", transfer_event.from);
             println!("Destination: {:?}", transfer_event.to);
-            println!("Value: {:?}", transfer_event.value);
+            println!("Value: {:?}", transfer_event.amount);
         }
         Err(e) => {
             println!(
@@ -15,14 +15,12 @@
 
 ///! Very simple example that shows how to subscribe to events generically
 /// implying no runtime needs to be imported
-use std::sync::mpsc::channel;
-
 use clap::{load_yaml, App};
 use codec::Decode;
 use sp_core::sr25519;
 use sp_runtime::AccountId32 as AccountId;
 use substrate_api_client::rpc::WsRpcClient;
-use substrate_api_client::{Api, PlainTipExtrinsicParams};
+use substrate_api_client::{Api, PlainTipExtrinsicParams, StaticEvent};
 
 // Look at the how the transfer event looks like in in the metadata
 #[derive(Decode)]
@@ -32,6 +30,11 @@ struct TransferEventArgs {
     value: u128,
 }
 
+impl StaticEvent for TransferEventArgs {
+    const PALLET: &'static str = "Balances";
+    const EVENT: &'static str = "Transfer";
+}
+
 fn main() {
     env_logger::init();
     let url = get_node_url_from_cli();
@@ -40,12 +43,8 @@ fn main() {
     let api = Api::<sr25519::Pair, _, PlainTipExtrinsicParams>::new(client).unwrap();
 
     println!("Subscribe to events");
-    let (events_in, events_out) = channel();
-
-    api.subscribe_events(events_in).unwrap();
-    let args: TransferEventArgs = api
-        .wait_for_event("Balances", "Transfer", None, &events_out)
-        .unwrap();
+    let mut events = api.subscribe_events().unwrap();
+    let args: TransferEventArgs = api.wait_for_event(&mut events).unwrap();
 
     println!("Transactor: {:?}", args.from);
     println!("Destination: {:?}", args.to);
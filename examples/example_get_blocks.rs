@@ -24,7 +24,6 @@ use clap::App;
 use node_template_runtime::{Block, Header};
 use sp_core::sr25519;
 use sp_runtime::generic::SignedBlock as SignedBlockG;
-use std::sync::mpsc::channel;
 use substrate_api_client::rpc::WsRpcClient;
 use substrate_api_client::{Api, PlainTipExtrinsicParams};
 
@@ -63,15 +62,10 @@ fn main() {
     );
 
     println!("Subscribing to finalized heads");
-    let (sender, receiver) = channel();
-    api.subscribe_finalized_heads(sender).unwrap();
-
-    for _ in 0..5 {
-        let head: Header = receiver
-            .recv()
-            .map(|header| serde_json::from_str(&header).unwrap())
-            .unwrap();
-        println!("Got new Block {:?}", head);
+    let heads = api.subscribe_finalized_heads::<Header>().unwrap();
+
+    for head in heads.take(5) {
+        println!("Got new Block {:?}", head.unwrap());
     }
 }
 
@@ -49,6 +49,15 @@ fn main() {
         .unwrap();
     println!("[+] AccountInfo for Alice is {:?}", result);
 
+    // decode the same storage entry without knowing its type at compile time
+    let result = api
+        .get_storage_map_dynamic("System", "Account", account, None)
+        .unwrap();
+    println!(
+        "[+] Dynamically decoded AccountInfo for Alice is {:?}",
+        result
+    );
+
     // get StorageMap key prefix
     let result = api.get_storage_map_key_prefix("System", "Account").unwrap();
     println!("[+] key prefix for System Account map is {:?}", result);
@@ -0,0 +1,70 @@
+/*
+Copyright 2019 Supercomputing Systems AG
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+///! Example that keeps sending transfers across runtime upgrades of the node.
+use std::thread;
+use std::time::Duration;
+
+use clap::{load_yaml, App};
+use sp_keyring::AccountKeyring;
+use sp_runtime::MultiAddress;
+
+use substrate_api_client::rpc::WsRpcClient;
+use substrate_api_client::{Api, PlainTipExtrinsicParams, XtStatus};
+
+fn main() {
+    env_logger::init();
+    let url = get_node_url_from_cli();
+
+    let from = AccountKeyring::Alice.pair();
+    let client = WsRpcClient::new(&url);
+    let mut api = Api::<_, _, PlainTipExtrinsicParams>::new(client)
+        .map(|api| api.set_signer(from))
+        .unwrap()
+        .set_on_runtime_update(|runtime_version, _metadata| {
+            println!(
+                "[+] Runtime upgraded to spec version {}",
+                runtime_version.spec_version
+            )
+        });
+    let mut runtime_versions = api.subscribe_runtime_version().unwrap();
+
+    let to = AccountKeyring::Bob.to_account_id();
+    loop {
+        // Signing with an outdated spec or transaction version gets the extrinsic rejected.
+        api.apply_runtime_upgrades(&mut runtime_versions).unwrap();
+
+        let xt = api.balance_transfer(MultiAddress::Id(to.clone()), 1000);
+        let block_hash = api
+            .send_extrinsic(xt.hex_encode(), XtStatus::InBlock)
+            .unwrap();
+        println!(
+            "[+] Transfer with spec version {} included in block {:?}",
+            api.runtime_version.spec_version, block_hash
+        );
+        thread::sleep(Duration::from_secs(6));
+    }
+}
+
+pub fn get_node_url_from_cli() -> String {
+    let yml = load_yaml!("cli.yml");
+    let matches = App::from_yaml(yml).get_matches();
+
+    let node_ip = matches.value_of("node-server").unwrap_or("ws://127.0.0.1");
+    let node_port = matches.value_of("node-port").unwrap_or("9944");
+    let url = format!("{}:{}", node_ip, node_port);
+    println!("Interacting with node on {}\n", url);
+    url
+}
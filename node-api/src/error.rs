@@ -22,6 +22,7 @@
 use crate::{
     events::EventsDecodingError,
     metadata::{InvalidMetadataError, Metadata, MetadataError},
+    value::EncodeError,
 };
 use codec::{Decode, Encode};
 use derive_more::From;
@@ -53,6 +54,8 @@ pub enum Error {
     Runtime(RuntimeError),
     /// Events decoding error.
     EventsDecoding(EventsDecodingError),
+    /// Dynamic value does not match the expected type.
+    ValueEncoding(EncodeError),
     /// Other error.
     Other(String),
 }
@@ -22,10 +22,11 @@
 use crate::{
     error::{Error, RuntimeError},
     metadata::{EventMetadata, Metadata, MetadataError},
+    value::{bit_sequence_bytes, bit_sequence_format, Composite, Value, Variant},
     Phase,
 };
 use ac_primitives::Hash;
-use codec::{Codec, Compact, Decode, Encode, Input};
+use codec::{Codec, Compact, Decode, Encode, Error as CodecError, Input};
 use scale_info::{prelude::format, TypeDef, TypeDefPrimitive};
 use sp_core::Bytes;
 use sp_std::marker::PhantomData;
@@ -33,6 +34,7 @@ use sp_std::marker::PhantomData;
 #[cfg(not(feature = "std"))]
 use alloc::{
     string::{String, ToString},
+    vec,
     vec::Vec,
 };
 
@@ -49,6 +51,50 @@ pub struct RawEvent {
     pub variant_index: u8,
     /// The raw Event data
     pub data: Bytes,
+    /// The topics the Event has been deposited with.
+    pub topics: Vec<Hash>,
+}
+
+/// An event with a static binding to its pallet and variant name.
+///
+/// This is **not** part of subxt.
+pub trait StaticEvent: Decode {
+    /// Name of the pallet emitting the event.
+    const PALLET: &'static str;
+    /// Name of the event variant.
+    const EVENT: &'static str;
+}
+
+/// This is **not** part of subxt.
+impl RawEvent {
+    /// Returns true if this is the event `E`.
+    pub fn is<E: StaticEvent>(&self) -> bool {
+        self.pallet == E::PALLET && self.variant == E::EVENT
+    }
+
+    /// Decodes the event data as `E`, if this is the event `E`.
+    pub fn as_event<E: StaticEvent>(&self) -> Result<Option<E>, CodecError> {
+        if !self.is::<E>() {
+            return Ok(None);
+        }
+        E::decode(&mut &self.data[..]).map(Some)
+    }
+
+    /// Decodes the event data into a [`Value::Variant`] named after the event, with the help of
+    /// the type information in `metadata`.
+    pub fn decode_dynamic(&self, metadata: &Metadata) -> Result<Value, Error> {
+        let event_metadata = metadata.event(self.pallet_index, self.variant_index)?;
+        let fields = Composite::decode_fields(
+            &metadata.runtime_metadata().types,
+            event_metadata.variant().fields(),
+            &mut &self.data[..],
+        )?;
+        Ok(Value::Variant(Variant {
+            name: self.variant.clone(),
+            index: self.variant_index,
+            fields,
+        }))
+    }
 }
 
 /// Events decoder.
@@ -100,18 +146,19 @@ impl EventsDecoder {
                 Ok(()) => {
                     log::debug!("raw bytes: {}", hex::encode(&event_data),);
 
+                    // topics come after the event data in EventRecord
+                    let topics = Vec::<Hash>::decode(input)?;
+                    log::debug!("topics: {:?}", topics);
+
                     let event = RawEvent {
                         pallet: event_metadata.pallet().to_string(),
                         pallet_index,
                         variant: event_metadata.event().to_string(),
                         variant_index,
                         data: event_data.into(),
+                        topics,
                     };
 
-                    // topics come after the event data in EventRecord
-                    let topics = Vec::<Hash>::decode(input)?;
-                    log::debug!("topics: {:?}", topics);
-
                     Raw::Event(event)
                 }
                 Err(err) => return Err(err),
@@ -174,12 +221,6 @@ impl EventsDecoder {
             .resolve_type(type_id)
             .ok_or(MetadataError::TypeNotFound(type_id))?;
 
-        fn decode_raw<T: Codec>(input: &mut &[u8], output: &mut Vec<u8>) -> Result<(), Error> {
-            let decoded = T::decode(input)?;
-            decoded.encode_to(output);
-            Ok(())
-        }
-
         match ty.type_def() {
             TypeDef::Composite(composite) => {
                 for field in composite.fields() {
@@ -192,7 +233,8 @@ impl EventsDecoder {
                 variant_index.encode_to(output);
                 let variant = variant
                     .variants()
-                    .get(variant_index as usize)
+                    .iter()
+                    .find(|v| v.index() == variant_index)
                     .ok_or_else(|| Error::Other(format!("Variant {} not found", variant_index)))?;
                 for field in variant.fields() {
                     self.decode_type(field.ty().id(), input, output)?;
@@ -222,7 +264,11 @@ impl EventsDecoder {
             TypeDef::Primitive(primitive) => match primitive {
                 TypeDefPrimitive::Bool => decode_raw::<bool>(input, output),
                 TypeDefPrimitive::Char => {
-                    Err(EventsDecodingError::UnsupportedPrimitive(TypeDefPrimitive::Char).into())
+                    let c = u32::decode(input)?;
+                    char::from_u32(c)
+                        .ok_or_else(|| Error::Other(format!("Invalid char {:#x}", c)))?;
+                    c.encode_to(output);
+                    Ok(())
                 }
                 TypeDefPrimitive::Str => decode_raw::<String>(input, output),
                 TypeDefPrimitive::U8 => decode_raw::<u8>(input, output),
@@ -230,64 +276,117 @@ impl EventsDecoder {
                 TypeDefPrimitive::U32 => decode_raw::<u32>(input, output),
                 TypeDefPrimitive::U64 => decode_raw::<u64>(input, output),
                 TypeDefPrimitive::U128 => decode_raw::<u128>(input, output),
-                TypeDefPrimitive::U256 => {
-                    Err(EventsDecodingError::UnsupportedPrimitive(TypeDefPrimitive::U256).into())
-                }
+                TypeDefPrimitive::U256 => decode_raw::<[u8; 32]>(input, output),
                 TypeDefPrimitive::I8 => decode_raw::<i8>(input, output),
                 TypeDefPrimitive::I16 => decode_raw::<i16>(input, output),
                 TypeDefPrimitive::I32 => decode_raw::<i32>(input, output),
                 TypeDefPrimitive::I64 => decode_raw::<i64>(input, output),
                 TypeDefPrimitive::I128 => decode_raw::<i128>(input, output),
-                TypeDefPrimitive::I256 => {
-                    Err(EventsDecodingError::UnsupportedPrimitive(TypeDefPrimitive::I256).into())
-                }
+                TypeDefPrimitive::I256 => decode_raw::<[u8; 32]>(input, output),
             },
-            TypeDef::Compact(_compact) => {
-                let inner = self
-                    .metadata
-                    .resolve_type(type_id)
-                    .ok_or(MetadataError::TypeNotFound(type_id))?;
-                let mut decode_compact_primitive = |primitive: &TypeDefPrimitive| match primitive {
-                    TypeDefPrimitive::U8 => decode_raw::<Compact<u8>>(input, output),
-                    TypeDefPrimitive::U16 => decode_raw::<Compact<u16>>(input, output),
-                    TypeDefPrimitive::U32 => decode_raw::<Compact<u32>>(input, output),
-                    TypeDefPrimitive::U64 => decode_raw::<Compact<u64>>(input, output),
-                    TypeDefPrimitive::U128 => decode_raw::<Compact<u128>>(input, output),
-                    prim => Err(EventsDecodingError::InvalidCompactPrimitive(prim.clone()).into()),
-                };
-                match inner.type_def() {
-                    TypeDef::Primitive(primitive) => decode_compact_primitive(primitive),
-                    TypeDef::Composite(composite) => match composite.fields() {
-                        [field] => {
-                            let field_ty = self
-                                .metadata
-                                .resolve_type(field.ty().id())
-                                .ok_or_else(|| MetadataError::TypeNotFound(field.ty().id()))?;
-                            if let TypeDef::Primitive(primitive) = field_ty.type_def() {
-                                decode_compact_primitive(primitive)
-                            } else {
-                                Err(EventsDecodingError::InvalidCompactType(
-                                    "Composite type must have a single primitive field".into(),
-                                )
-                                .into())
-                            }
-                        }
-                        _ => Err(EventsDecodingError::InvalidCompactType(
-                            "Composite type must have a single field".into(),
-                        )
-                        .into()),
-                    },
-                    _ => Err(EventsDecodingError::InvalidCompactType(
-                        "Compact type must be a primitive or a composite type".into(),
-                    )
-                    .into()),
-                }
+            TypeDef::Compact(compact) => {
+                self.decode_compact(compact.type_param().id(), input, output)
+            }
+            TypeDef::BitSequence(bitseq) => {
+                // Copy the store elements as they are, their layout is defined by the store and
+                // order types, which we only need to know the element size.
+                let (store_bits, _msb_first) =
+                    bit_sequence_format(&self.metadata.runtime_metadata().types, bitseq)?;
+                let len = <Compact<u32>>::decode(input)?;
+                len.encode_to(output);
+                let mut bits = vec![0u8; bit_sequence_bytes(len.0, store_bits, input.len())?];
+                input.read(&mut bits)?;
+                output.extend(bits);
+                Ok(())
             }
-            TypeDef::BitSequence(_bitseq) => {
-                // decode_raw::<bitvec::BitVec>
-                unimplemented!("BitVec decoding for events not implemented yet")
+        }
+    }
+
+    /// Decodes a compact encoded value of type `type_id`: an unsigned integer, or a composite
+    /// wrapping a single compact encodable field, e.g. `Perbill`, at any depth.
+    fn decode_compact(
+        &self,
+        type_id: u32,
+        input: &mut &[u8],
+        output: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let ty = self
+            .metadata
+            .resolve_type(type_id)
+            .ok_or(MetadataError::TypeNotFound(type_id))?;
+        match ty.type_def() {
+            TypeDef::Primitive(primitive) => match primitive {
+                TypeDefPrimitive::U8 => decode_raw::<Compact<u8>>(input, output),
+                TypeDefPrimitive::U16 => decode_raw::<Compact<u16>>(input, output),
+                TypeDefPrimitive::U32 => decode_raw::<Compact<u32>>(input, output),
+                TypeDefPrimitive::U64 => decode_raw::<Compact<u64>>(input, output),
+                TypeDefPrimitive::U128 => decode_raw::<Compact<u128>>(input, output),
+                prim => Err(EventsDecodingError::InvalidCompactPrimitive(prim.clone()).into()),
+            },
+            TypeDef::Composite(composite) => match composite.fields() {
+                [field] => self.decode_compact(field.ty().id(), input, output),
+                _ => Err(EventsDecodingError::InvalidCompactType(
+                    "Composite type must have a single field".into(),
+                )
+                .into()),
+            },
+            _ => Err(EventsDecodingError::InvalidCompactType(
+                "Compact type must be a primitive or a composite type".into(),
+            )
+            .into()),
+        }
+    }
+}
+
+/// Decodes a `T` and appends it, re-encoded, to `output`.
+fn decode_raw<T: Codec>(input: &mut &[u8], output: &mut Vec<u8>) -> Result<(), Error> {
+    let decoded = T::decode(input)?;
+    decoded.encode_to(output);
+    Ok(())
+}
+
+/// Helpers on the list of events returned by [`EventsDecoder::decode_events`].
+///
+/// This is **not** part of subxt.
+pub trait EventRecords {
+    /// Returns the events deposited with `topic`, together with their phase.
+    fn with_topic(&self, topic: &Hash) -> Vec<(&Phase, &RawEvent)>;
+
+    /// Returns the first event `E`, decoded.
+    fn find<E: StaticEvent>(&self) -> Result<Option<E>, CodecError>;
+
+    /// Returns all events `E`, decoded.
+    fn find_all<E: StaticEvent>(&self) -> Result<Vec<E>, CodecError>;
+}
+
+impl EventRecords for [(Phase, Raw)] {
+    fn find<E: StaticEvent>(&self) -> Result<Option<E>, CodecError> {
+        for (_phase, raw) in self {
+            if let Raw::Event(event) = raw {
+                if let Some(event) = event.as_event::<E>()? {
+                    return Ok(Some(event));
+                }
             }
         }
+        Ok(None)
+    }
+
+    fn find_all<E: StaticEvent>(&self) -> Result<Vec<E>, CodecError> {
+        self.iter()
+            .filter_map(|(_phase, raw)| match raw {
+                Raw::Event(event) => event.as_event::<E>().transpose(),
+                Raw::Error(_) => None,
+            })
+            .collect()
+    }
+
+    fn with_topic(&self, topic: &Hash) -> Vec<(&Phase, &RawEvent)> {
+        self.iter()
+            .filter_map(|(phase, raw)| match raw {
+                Raw::Event(event) if event.topics.contains(topic) => Some((phase, event)),
+                _ => None,
+            })
+            .collect()
     }
 }
 
@@ -307,4 +406,395 @@ pub enum EventsDecodingError {
     /// Invalid compact type, must be an unsigned int.
     InvalidCompactPrimitive(TypeDefPrimitive),
     InvalidCompactType(String),
+    /// Invalid bit sequence, unsupported store or order type.
+    InvalidBitSequenceType(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::tests::{I256, U256};
+    use bitvec::{
+        order::{Lsb0, Msb0},
+        vec::BitVec,
+    };
+    use codec::CompactAs;
+    use frame_metadata::{
+        v14::{ExtrinsicMetadata, PalletEventMetadata, PalletMetadata, RuntimeMetadataV14},
+        RuntimeMetadataPrefixed,
+    };
+    use frame_support::dispatch::DispatchInfo;
+    use scale_info::{meta_type, TypeInfo};
+    use sp_runtime::ModuleError;
+    use sp_runtime::{DispatchError, Perbill};
+
+    /// A compact encodable type wrapping another compact encodable composite.
+    #[derive(Encode, CompactAs, TypeInfo)]
+    struct Commission(Perbill);
+
+    /// Events with the same field types as the ones emitted by the node-template.
+    #[derive(Encode, TypeInfo)]
+    enum TestEvent {
+        Rewarded {
+            who: [u8; 32],
+            #[codec(compact)]
+            amount: u128,
+            #[codec(compact)]
+            commission: Perbill,
+        },
+        #[codec(index = 5)]
+        Signalled(char, Vec<u16>),
+        Wide(U256, I256),
+        Flagged(BitVec<u8, Lsb0>, BitVec<u32, Msb0>),
+        CommissionSet {
+            #[codec(compact)]
+            commission: Commission,
+        },
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum SystemEvent {
+        ExtrinsicFailed { dispatch_error: DispatchError },
+    }
+
+    fn pallet<E: TypeInfo + 'static>(name: &'static str, index: u8) -> PalletMetadata {
+        PalletMetadata {
+            name,
+            storage: None,
+            calls: None,
+            event: Some(PalletEventMetadata {
+                ty: meta_type::<E>(),
+            }),
+            constants: vec![],
+            error: None,
+            index,
+        }
+    }
+
+    fn metadata() -> Metadata {
+        let pallets = vec![
+            pallet::<SystemEvent>("System", 0),
+            pallet::<TestEvent>("Test", 8),
+        ];
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 4,
+            signed_extensions: vec![],
+        };
+        let runtime_metadata = RuntimeMetadataV14::new(pallets, extrinsic, meta_type::<()>());
+        Metadata::try_from(RuntimeMetadataPrefixed::from(runtime_metadata)).unwrap()
+    }
+
+    fn encode_events<E: Encode>(pallet_index: u8, events: &[E]) -> Vec<u8> {
+        encode_events_with_topics(pallet_index, events, |_| vec![])
+    }
+
+    fn encode_events_with_topics<E: Encode>(
+        pallet_index: u8,
+        events: &[E],
+        topics: impl Fn(usize) -> Vec<Hash>,
+    ) -> Vec<u8> {
+        let mut encoded = Compact(events.len() as u32).encode();
+        for (i, event) in events.iter().enumerate() {
+            Phase::ApplyExtrinsic(1).encode_to(&mut encoded);
+            pallet_index.encode_to(&mut encoded);
+            event.encode_to(&mut encoded);
+            topics(i).encode_to(&mut encoded);
+        }
+        encoded
+    }
+
+    fn decode_single(encoded: Vec<u8>) -> RawEvent {
+        let mut events = EventsDecoder::new(metadata())
+            .decode_events(&mut encoded.as_slice())
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        match events.remove(0) {
+            (Phase::ApplyExtrinsic(1), Raw::Event(event)) => event,
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decodes_compact_primitive_and_compact_wrapped_types() {
+        let event = TestEvent::Rewarded {
+            who: [7; 32],
+            amount: 1_000_000_000_000,
+            commission: Perbill::from_percent(10),
+        };
+        let raw = decode_single(encode_events(8, &[&event]));
+
+        assert_eq!(raw.pallet, "Test");
+        assert_eq!(raw.variant, "Rewarded");
+        // The event data is re-encoded without the variant index.
+        assert_eq!(raw.data.0, event.encode()[1..]);
+    }
+
+    #[test]
+    fn decodes_chars_and_non_consecutive_variant_indices() {
+        let event = TestEvent::Signalled('ü', vec![1, 2, 3]);
+        let raw = decode_single(encode_events(8, &[&event]));
+
+        assert_eq!(raw.variant, "Signalled");
+        assert_eq!(raw.variant_index, 5);
+        assert_eq!(raw.data.0, event.encode()[1..]);
+    }
+
+    #[test]
+    fn invalid_char_is_an_error() {
+        let mut encoded = encode_events(8, &[&TestEvent::Signalled('a', vec![])]);
+        // overwrite the char following compact length, phase, pallet and variant index
+        encoded[8..12].copy_from_slice(&0xd800u32.encode());
+
+        let result = EventsDecoder::new(metadata()).decode_events(&mut encoded.as_slice());
+        assert!(matches!(result, Err(Error::Other(_))));
+    }
+
+    #[test]
+    fn decodes_256_bit_integers() {
+        let mut i256 = [0xff; 32];
+        i256[0] = 0xfe;
+        let event = TestEvent::Wide(U256([3; 32]), I256(i256));
+        let raw = decode_single(encode_events(8, &[&event]));
+
+        assert_eq!(raw.variant, "Wide");
+        assert_eq!(raw.data.0, event.encode()[1..]);
+    }
+
+    #[test]
+    fn decodes_bit_sequences_of_any_store_and_order() {
+        // Neither length is a multiple of the store size, so the last element is partial.
+        let lsb0_u8: BitVec<u8, Lsb0> = [true, false, true, true, false, false, true, false, true]
+            .into_iter()
+            .collect();
+        let msb0_u32: BitVec<u32, Msb0> = (0..40).map(|i| i % 3 == 0).collect();
+        let event = TestEvent::Flagged(lsb0_u8, msb0_u32);
+        let encoded = encode_events(8, &[&event]);
+        let raw = decode_single(encoded);
+
+        assert_eq!(raw.variant, "Flagged");
+        assert_eq!(raw.data.0, event.encode()[1..]);
+
+        let fields = match raw.decode_dynamic(&metadata()).unwrap() {
+            Value::Variant(Variant {
+                fields: Composite::Unnamed(fields),
+                ..
+            }) => fields,
+            other => panic!("unexpected value {:?}", other),
+        };
+        assert_eq!(
+            fields[0],
+            Value::BitSequence(vec![
+                true, false, true, true, false, false, true, false, true
+            ])
+        );
+        assert_eq!(
+            fields[1],
+            Value::BitSequence((0..40).map(|i| i % 3 == 0).collect())
+        );
+    }
+
+    #[test]
+    fn bit_sequence_longer_than_the_input_is_an_error() {
+        let event = TestEvent::Flagged(BitVec::new(), BitVec::new());
+        let mut encoded = Compact(1u32).encode();
+        Phase::ApplyExtrinsic(1).encode_to(&mut encoded);
+        encoded.push(8);
+        encoded.push(event.encode()[0]);
+        // A bit length that would need gigabytes, followed by a single store element.
+        Compact(u32::MAX).encode_to(&mut encoded);
+        encoded.push(0xff);
+
+        let result = EventsDecoder::new(metadata()).decode_events(&mut encoded.as_slice());
+        assert!(matches!(result, Err(Error::Codec(_))));
+    }
+
+    #[test]
+    fn decodes_compact_composites_wrapping_composites() {
+        let event = TestEvent::CommissionSet {
+            commission: Commission(Perbill::from_parts(1_000_000)),
+        };
+        let raw = decode_single(encode_events(8, &[&event]));
+
+        assert_eq!(raw.variant, "CommissionSet");
+        assert_eq!(raw.data.0, event.encode()[1..]);
+        assert_eq!(
+            raw.decode_dynamic(&metadata())
+                .unwrap()
+                .field("commission")
+                .unwrap(),
+            &Value::Composite(Composite::Unnamed(vec![Value::Composite(
+                Composite::Unnamed(vec![Value::Primitive(crate::value::Primitive::U128(
+                    1_000_000
+                ))])
+            )]))
+        );
+    }
+
+    #[test]
+    fn decodes_events_with_node_template_metadata() {
+        use node_template_runtime::{Runtime, RuntimeEvent};
+
+        let encoded_metadata = Runtime::metadata().encode();
+        let metadata = Metadata::try_from(
+            RuntimeMetadataPrefixed::decode(&mut encoded_metadata.as_slice()).unwrap(),
+        )
+        .unwrap();
+        let balances_index = metadata.pallet("Balances").unwrap().index;
+        let events = vec![
+            RuntimeEvent::System(frame_system::Event::ExtrinsicSuccess {
+                dispatch_info: DispatchInfo::default(),
+            }),
+            RuntimeEvent::Balances(pallet_balances::Event::Transfer {
+                from: [1; 32].into(),
+                to: [2; 32].into(),
+                amount: 1_000_000_000_000,
+            }),
+            RuntimeEvent::System(frame_system::Event::ExtrinsicFailed {
+                dispatch_error: DispatchError::Module(ModuleError {
+                    index: balances_index,
+                    error: [2, 0, 0, 0],
+                    message: None,
+                }),
+                dispatch_info: DispatchInfo::default(),
+            }),
+        ];
+        let records: Vec<_> = events
+            .iter()
+            .map(|event| frame_system::EventRecord {
+                phase: frame_system::Phase::ApplyExtrinsic(1),
+                event: event.clone(),
+                topics: Vec::<Hash>::new(),
+            })
+            .collect();
+
+        let decoded = EventsDecoder::new(metadata)
+            .decode_events(&mut records.encode().as_slice())
+            .unwrap();
+        assert_eq!(decoded.len(), 3);
+        for ((phase, raw), event) in decoded.iter().zip(&events).take(2) {
+            assert_eq!(phase, &Phase::ApplyExtrinsic(1));
+            match raw {
+                // The event data is re-encoded without the pallet and variant index.
+                Raw::Event(raw) => assert_eq!(raw.data.0, event.encode()[2..]),
+                other => panic!("unexpected event {:?}", other),
+            }
+        }
+        assert!(matches!(
+            &decoded[2].1,
+            Raw::Error(RuntimeError::Module(error))
+                if error.pallet == "Balances" && error.error == "InsufficientBalance"
+        ));
+    }
+
+    #[test]
+    fn dynamic_decoding_of_compact_wrapped_types() {
+        let metadata = metadata();
+        let event = TestEvent::Rewarded {
+            who: [7; 32],
+            amount: 1_000,
+            commission: Perbill::from_parts(42),
+        };
+        let raw = decode_single(encode_events(8, &[&event]));
+
+        let value = raw.decode_dynamic(&metadata).unwrap();
+        assert_eq!(value.field("amount").unwrap().as_u128(), Some(1_000));
+        assert_eq!(
+            value.field("commission").unwrap(),
+            &Value::Composite(Composite::Unnamed(vec![Value::Primitive(
+                crate::value::Primitive::U128(42)
+            )]))
+        );
+    }
+
+    #[test]
+    fn failed_extrinsic_is_decoded_as_runtime_error() {
+        let event = SystemEvent::ExtrinsicFailed {
+            dispatch_error: DispatchError::BadOrigin,
+        };
+        let encoded = encode_events(0, &[&event]);
+
+        let events = EventsDecoder::new(metadata())
+            .decode_events(&mut encoded.as_slice())
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![(
+                Phase::ApplyExtrinsic(1),
+                Raw::Error(RuntimeError::BadOrigin)
+            )]
+        );
+    }
+
+    #[test]
+    fn events_carry_their_topics() {
+        let events = [
+            TestEvent::Signalled('a', vec![]),
+            TestEvent::Signalled('b', vec![]),
+            TestEvent::Signalled('c', vec![]),
+        ];
+        let topic = Hash::repeat_byte(1);
+        let encoded = encode_events_with_topics(8, &events, |i| match i {
+            0 => vec![],
+            1 => vec![Hash::repeat_byte(2), topic],
+            _ => vec![topic],
+        });
+
+        let events = EventsDecoder::new(metadata())
+            .decode_events(&mut encoded.as_slice())
+            .unwrap();
+        assert_eq!(events.len(), 3);
+
+        let with_topic = events.with_topic(&topic);
+        let data: Vec<_> = with_topic
+            .iter()
+            .map(|(_, event)| event.data.0.clone())
+            .collect();
+        let expected: Vec<_> = ['b', 'c']
+            .iter()
+            .map(|c| TestEvent::Signalled(*c, vec![]).encode()[1..].to_vec())
+            .collect();
+        assert_eq!(data, expected);
+        assert_eq!(with_topic[0].1.topics, vec![Hash::repeat_byte(2), topic]);
+    }
+
+    #[derive(Decode, Debug, PartialEq)]
+    struct Signalled(char, Vec<u16>);
+
+    impl StaticEvent for Signalled {
+        const PALLET: &'static str = "Test";
+        const EVENT: &'static str = "Signalled";
+    }
+
+    #[test]
+    fn finds_static_events() {
+        let events = [
+            TestEvent::Rewarded {
+                who: [7; 32],
+                amount: 1,
+                commission: Perbill::zero(),
+            },
+            TestEvent::Signalled('a', vec![1]),
+            TestEvent::Signalled('b', vec![2]),
+        ];
+        let encoded = encode_events(8, &events);
+        let events = EventsDecoder::new(metadata())
+            .decode_events(&mut encoded.as_slice())
+            .unwrap();
+
+        assert_eq!(
+            events.find::<Signalled>().unwrap(),
+            Some(Signalled('a', vec![1]))
+        );
+        assert_eq!(
+            events.find_all::<Signalled>().unwrap(),
+            vec![Signalled('a', vec![1]), Signalled('b', vec![2])]
+        );
+        assert_eq!(
+            events
+                .find::<crate::static_events::balances::Transfer>()
+                .unwrap(),
+            None
+        );
+    }
 }
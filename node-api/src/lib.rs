@@ -25,12 +25,16 @@ use codec::{Decode, Encode};
 pub use error::*;
 pub use events::*;
 pub use metadata::*;
+pub use signed_extensions::*;
 pub use storage::*;
 
 pub mod error;
 pub mod events;
 pub mod metadata;
+pub mod signed_extensions;
+pub mod static_events;
 pub mod storage;
+pub mod value;
 
 #[cfg(feature = "std")]
 mod print_metadata;
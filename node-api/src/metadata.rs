@@ -19,13 +19,17 @@
 //!
 //! This file is mostly subxt.
 
-use crate::{storage::GetStorage, Encoded};
+use crate::{error::Error, storage::GetStorage, value::Composite, Encoded};
 use codec::{Decode, Encode, Error as CodecError};
 use frame_metadata::{
-    PalletConstantMetadata, RuntimeMetadata, RuntimeMetadataLastVersion, RuntimeMetadataPrefixed,
-    StorageEntryMetadata, META_RESERVED,
+    v14::{
+        ExtrinsicMetadata, PalletConstantMetadata, RuntimeMetadataV14, SignedExtensionMetadata,
+        StorageEntryMetadata,
+    },
+    v15::{RuntimeApiMetadata, RuntimeApiMethodMetadata, RuntimeMetadataV15},
+    RuntimeMetadata, RuntimeMetadataPrefixed, META_RESERVED,
 };
-use scale_info::{form::PortableForm, Type, Variant};
+use scale_info::{form::PortableForm, PortableRegistry, Type, Variant};
 use sp_core::storage::StorageKey;
 
 #[cfg(feature = "std")]
@@ -41,6 +45,13 @@ use alloc::{
     vec::Vec,
 };
 
+pub use compatibility::*;
+
+mod compatibility;
+
+/// Metadata versions [`Metadata`] can be created from, newest first.
+pub const SUPPORTED_METADATA_VERSIONS: [u32; 2] = [15, 14];
+
 /// Metadata error.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MetadataError {
@@ -49,13 +60,15 @@ pub enum MetadataError {
     /// Pallet is not in metadata.
     PalletIndexNotFound(u8),
     /// Call is not in metadata.
-    CallNotFound(&'static str),
+    CallNotFound { pallet: String, call: String },
     /// Event is not in metadata.
     EventNotFound(u8, u8),
     /// Error is not in metadata.
     ErrorNotFound(u8, u8),
     /// Storage is not in metadata.
     StorageNotFound(&'static str),
+    /// No encoder is known for the signed extension, or for its types in the metadata.
+    UnknownSignedExtension(String),
     /// Storage type does not match requested type.
     StorageTypeError,
     /// Map value type does not match requested type.
@@ -73,7 +86,12 @@ pub enum MetadataError {
 /// Runtime metadata.
 #[derive(Clone, Debug, Encode, Decode)]
 pub struct Metadata {
-    pub metadata: RuntimeMetadataLastVersion,
+    /// The metadata in the V14 layout, also if the node provided V15, see [`Self::version`].
+    pub metadata: RuntimeMetadataV14,
+    /// Version of the metadata provided by the node.
+    pub version: u32,
+    /// Runtime apis by name. Only described by V15 metadata, empty otherwise.
+    pub runtime_apis: BTreeMap<String, RuntimeApiMetadata<PortableForm>>,
     pub pallets: BTreeMap<String, PalletMetadata>,
     pub events: BTreeMap<(u8, u8), EventMetadata>,
     pub errors: BTreeMap<(u8, u8), ErrorMetadata>,
@@ -81,7 +99,7 @@ pub struct Metadata {
 
 impl Metadata {
     /// Returns a reference to [`PalletMetadata`].
-    pub fn pallet(&self, name: &'static str) -> Result<&PalletMetadata, MetadataError> {
+    pub fn pallet(&self, name: &str) -> Result<&PalletMetadata, MetadataError> {
         self.pallets
             .get(name)
             .ok_or_else(|| MetadataError::PalletNotFound(name.to_string()))
@@ -139,10 +157,28 @@ impl Metadata {
     }
 
     /// Return the runtime metadata.
-    pub fn runtime_metadata(&self) -> &RuntimeMetadataLastVersion {
+    pub fn runtime_metadata(&self) -> &RuntimeMetadataV14 {
         &self.metadata
     }
 
+    /// Returns the metadata of the runtime api `name`, e.g. `AccountNonceApi`.
+    pub fn runtime_api(&self, name: &str) -> Option<&RuntimeApiMetadata<PortableForm>> {
+        self.runtime_apis.get(name)
+    }
+
+    /// Returns the metadata of `method` of the runtime api `api`, e.g. `account_nonce` of
+    /// `AccountNonceApi`.
+    pub fn runtime_api_method(
+        &self,
+        api: &str,
+        method: &str,
+    ) -> Option<&RuntimeApiMethodMetadata<PortableForm>> {
+        self.runtime_api(api)?
+            .methods
+            .iter()
+            .find(|m| m.name == method)
+    }
+
     #[cfg(feature = "std")]
     pub fn pretty_format(metadata: &RuntimeMetadataPrefixed) -> Option<String> {
         let buf = Vec::new();
@@ -158,6 +194,7 @@ pub struct PalletMetadata {
     pub index: u8,
     pub name: String,
     pub calls: BTreeMap<String, u8>,
+    pub call_variants: BTreeMap<String, Variant<PortableForm>>,
     pub storage: BTreeMap<String, StorageEntryMetadata<PortableForm>>,
     pub constants: BTreeMap<String, PalletConstantMetadata<PortableForm>>,
 }
@@ -167,15 +204,47 @@ impl PalletMetadata {
     where
         C: Encode,
     {
-        let fn_index = self
-            .calls
-            .get(call_name)
-            .ok_or(MetadataError::CallNotFound(call_name))?;
-        let mut bytes = vec![self.index, *fn_index];
+        let fn_index = self.call_index(call_name)?;
+        let mut bytes = vec![self.index, fn_index];
         bytes.extend(args.encode());
         Ok(Encoded(bytes))
     }
 
+    /// Encodes the call `call_name` with dynamic arguments, checking them against the types of
+    /// the call's fields in `types`, the registry of the runtime metadata. Arguments are given
+    /// either by name, in any order, or positionally.
+    ///
+    /// This is **not** part of subxt.
+    pub fn encode_call_dynamic(
+        &self,
+        types: &PortableRegistry,
+        call_name: &'static str,
+        args: &Composite,
+    ) -> Result<Encoded, Error> {
+        let variant = self
+            .call_variants
+            .get(call_name)
+            .ok_or_else(|| self.call_not_found(call_name))?;
+        let mut bytes = vec![self.index, variant.index()];
+        args.encode_as_fields(types, variant.fields(), &mut bytes)?;
+        Ok(Encoded(bytes))
+    }
+
+    /// Returns the index of the call `call_name`.
+    pub fn call_index(&self, call_name: &str) -> Result<u8, MetadataError> {
+        self.calls
+            .get(call_name)
+            .copied()
+            .ok_or_else(|| self.call_not_found(call_name))
+    }
+
+    fn call_not_found(&self, call_name: &str) -> MetadataError {
+        MetadataError::CallNotFound {
+            pallet: self.name.clone(),
+            call: call_name.to_string(),
+        }
+    }
+
     pub fn storage(
         &self,
         key: &'static str,
@@ -261,8 +330,16 @@ impl TryFrom<RuntimeMetadataPrefixed> for Metadata {
         if metadata.0 != META_RESERVED {
             return Err(InvalidMetadataError::InvalidPrefix);
         }
-        let metadata = match metadata.1 {
-            RuntimeMetadata::V14(meta) => meta,
+        let (metadata, version, runtime_apis) = match metadata.1 {
+            RuntimeMetadata::V14(meta) => (meta, 14, BTreeMap::new()),
+            RuntimeMetadata::V15(meta) => {
+                let runtime_apis = meta
+                    .apis
+                    .iter()
+                    .map(|api| (api.name.clone(), api.clone()))
+                    .collect();
+                (v15_to_v14(meta), 15, runtime_apis)
+            }
             _ => return Err(InvalidMetadataError::InvalidVersion),
         };
 
@@ -281,15 +358,20 @@ impl TryFrom<RuntimeMetadataPrefixed> for Metadata {
             .pallets
             .iter()
             .map(|pallet| {
-                let calls = pallet.calls.as_ref().map_or(Ok(BTreeMap::new()), |call| {
-                    let type_def_variant = get_type_def_variant(call.ty.id())?;
-                    let calls = type_def_variant
-                        .variants()
-                        .iter()
-                        .map(|v| (v.name().clone(), v.index()))
-                        .collect();
-                    Ok(calls)
-                })?;
+                let call_variants: BTreeMap<String, Variant<PortableForm>> =
+                    pallet.calls.as_ref().map_or(Ok(BTreeMap::new()), |call| {
+                        let type_def_variant = get_type_def_variant(call.ty.id())?;
+                        let call_variants = type_def_variant
+                            .variants()
+                            .iter()
+                            .map(|v| (v.name().clone(), v.clone()))
+                            .collect();
+                        Ok(call_variants)
+                    })?;
+                let calls = call_variants
+                    .iter()
+                    .map(|(name, v)| (name.clone(), v.index()))
+                    .collect();
 
                 let storage = pallet.storage.as_ref().map_or(BTreeMap::new(), |storage| {
                     storage
@@ -309,6 +391,7 @@ impl TryFrom<RuntimeMetadataPrefixed> for Metadata {
                     index: pallet.index,
                     name: pallet.name.to_string(),
                     calls,
+                    call_variants,
                     storage,
                     constants,
                 };
@@ -369,6 +452,8 @@ impl TryFrom<RuntimeMetadataPrefixed> for Metadata {
 
         Ok(Self {
             metadata,
+            version,
+            runtime_apis,
             pallets,
             events,
             errors,
@@ -376,6 +461,46 @@ impl TryFrom<RuntimeMetadataPrefixed> for Metadata {
     }
 }
 
+/// Converts V15 metadata to the V14 layout. The pallets and types are the same, but V15 does not
+/// describe the extrinsic type as a whole anymore, so `extrinsic.ty` refers to the call type.
+///
+/// This is **not** part of subxt.
+fn v15_to_v14(metadata: RuntimeMetadataV15) -> RuntimeMetadataV14 {
+    let pallets = metadata
+        .pallets
+        .into_iter()
+        .map(|pallet| frame_metadata::v14::PalletMetadata {
+            name: pallet.name,
+            storage: pallet.storage,
+            calls: pallet.calls,
+            event: pallet.event,
+            constants: pallet.constants,
+            error: pallet.error,
+            index: pallet.index,
+        })
+        .collect();
+    let signed_extensions = metadata
+        .extrinsic
+        .signed_extensions
+        .into_iter()
+        .map(|extension| SignedExtensionMetadata {
+            identifier: extension.identifier,
+            ty: extension.ty,
+            additional_signed: extension.additional_signed,
+        })
+        .collect();
+    RuntimeMetadataV14 {
+        types: metadata.types,
+        pallets,
+        extrinsic: ExtrinsicMetadata {
+            ty: metadata.extrinsic.call_ty,
+            version: metadata.extrinsic.version,
+            signed_extensions,
+        },
+        ty: metadata.ty,
+    }
+}
+
 /// Get the storage keys corresponding to a storage
 ///
 /// This is **not** part of subxt.
@@ -429,3 +554,153 @@ impl Metadata {
             .key(first, second))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frame_metadata::{
+        v14::PalletCallMetadata,
+        v15::{
+            CustomMetadata, OuterEnums, PalletMetadata as PalletMetadataV15,
+            RuntimeApiMethodParamMetadata, SignedExtensionMetadata as SignedExtensionMetadataV15,
+        },
+    };
+    use scale_info::{meta_type, TypeInfo};
+
+    #[derive(TypeInfo)]
+    #[allow(non_camel_case_types, dead_code)]
+    enum Call {
+        remark { remark: Vec<u8> },
+    }
+
+    fn metadata_v15() -> RuntimeMetadataPrefixed {
+        let pallet = PalletMetadataV15 {
+            name: "System",
+            storage: None,
+            calls: Some(PalletCallMetadata {
+                ty: meta_type::<Call>(),
+            }),
+            event: None,
+            constants: vec![],
+            error: None,
+            index: 0,
+            docs: vec![],
+        };
+        let extrinsic = frame_metadata::v15::ExtrinsicMetadata {
+            version: 4,
+            address_ty: meta_type::<[u8; 32]>(),
+            call_ty: meta_type::<Call>(),
+            signature_ty: meta_type::<[u8; 64]>(),
+            extra_ty: meta_type::<()>(),
+            signed_extensions: vec![SignedExtensionMetadataV15 {
+                identifier: "CheckNonce",
+                ty: meta_type::<u32>(),
+                additional_signed: meta_type::<()>(),
+            }],
+        };
+        let api = RuntimeApiMetadata {
+            name: "AccountNonceApi",
+            methods: vec![RuntimeApiMethodMetadata {
+                name: "account_nonce",
+                inputs: vec![RuntimeApiMethodParamMetadata {
+                    name: "account",
+                    ty: meta_type::<[u8; 32]>(),
+                }],
+                output: meta_type::<u32>(),
+                docs: vec![],
+            }],
+            docs: vec![],
+        };
+        let outer_enums = OuterEnums {
+            call_enum_ty: meta_type::<Call>(),
+            event_enum_ty: meta_type::<()>(),
+            error_enum_ty: meta_type::<()>(),
+        };
+        let custom = CustomMetadata {
+            map: Default::default(),
+        };
+        RuntimeMetadataV15::new(
+            vec![pallet],
+            extrinsic,
+            meta_type::<()>(),
+            vec![api],
+            outer_enums,
+            custom,
+        )
+        .into()
+    }
+
+    #[test]
+    fn parses_v15_metadata() {
+        let metadata = Metadata::try_from(metadata_v15()).unwrap();
+
+        assert_eq!(metadata.version, 15);
+        assert_eq!(metadata.pallet("System").unwrap().calls["remark"], 0);
+        assert_eq!(metadata.metadata.extrinsic.signed_extensions.len(), 1);
+        assert_eq!(
+            metadata.metadata.extrinsic.signed_extensions[0].identifier,
+            "CheckNonce"
+        );
+    }
+
+    #[test]
+    fn surfaces_runtime_apis_of_v15_metadata() {
+        let metadata = Metadata::try_from(metadata_v15()).unwrap();
+
+        let method = metadata
+            .runtime_api_method("AccountNonceApi", "account_nonce")
+            .unwrap();
+        assert_eq!(method.inputs.len(), 1);
+        assert_eq!(method.inputs[0].name, "account");
+        assert!(metadata
+            .runtime_api_method("AccountNonceApi", "unknown")
+            .is_none());
+        assert!(metadata.runtime_api("Core").is_none());
+    }
+
+    #[test]
+    fn missing_call_error_names_pallet_and_call() {
+        let metadata = Metadata::try_from(metadata_v15()).unwrap();
+        let pallet = metadata.pallet("System").unwrap();
+
+        assert_eq!(pallet.call_index("remark"), Ok(0));
+        assert_eq!(
+            pallet.call_index("transfer"),
+            Err(MetadataError::CallNotFound {
+                pallet: "System".to_string(),
+                call: "transfer".to_string(),
+            })
+        );
+        assert_eq!(
+            metadata.pallet("Balances").unwrap_err(),
+            MetadataError::PalletNotFound("Balances".to_string())
+        );
+    }
+
+    #[test]
+    fn parses_metadata_encoded_by_substrate() {
+        // The runtime encodes the metadata with substrate's version of frame-metadata.
+        let encoded = node_template_runtime::Runtime::metadata().encode();
+        let metadata =
+            Metadata::try_from(RuntimeMetadataPrefixed::decode(&mut encoded.as_slice()).unwrap())
+                .unwrap();
+
+        assert_eq!(metadata.version, 14);
+        assert!(metadata
+            .pallet("Balances")
+            .unwrap()
+            .calls
+            .contains_key("transfer"));
+        assert!(metadata.metadata.extrinsic.signed_extensions.len() > 1);
+    }
+
+    #[test]
+    fn rejects_invalid_prefix() {
+        let mut metadata = metadata_v15();
+        metadata.0 = 0;
+        assert_eq!(
+            Metadata::try_from(metadata).unwrap_err(),
+            InvalidMetadataError::InvalidPrefix
+        );
+    }
+}
@@ -0,0 +1,587 @@
+/*
+    Copyright 2021 Integritee AG and Supercomputing Systems AG
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+        http://www.apache.org/licenses/LICENSE-2.0
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Compatibility of the calls, storage entries, events and constants of two runtimes.
+//!
+//! Every item is hashed over its shape: the indices it is encoded with, the names of its fields
+//! and the structure of their types. Type paths and ids are ignored, as they do not affect the
+//! encoding. Items of two runtimes with equal hashes are encoded the same way.
+//!
+//! This is **not** part of subxt.
+
+use super::{Metadata, MetadataError};
+use codec::Encode;
+use frame_metadata::v14::{StorageEntryMetadata, StorageEntryType};
+use scale_info::{form::PortableForm, Field, PortableRegistry, TypeDef, Variant};
+use sp_core::hashing::blake2_256;
+use sp_std::collections::btree_map::BTreeMap;
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+/// Hash of the shape of a metadata item.
+pub type ItemHash = [u8; 32];
+
+/// An item of the metadata our code may depend on.
+#[derive(Clone, Debug, PartialEq, Eq, Ord, PartialOrd)]
+pub enum MetadataItem {
+    /// Index of the pallet.
+    Pallet { pallet: String },
+    /// Indices and arguments of a call.
+    Call { pallet: String, call: String },
+    /// Hashers, key and value of a storage entry.
+    Storage { pallet: String, entry: String },
+    /// Indices and fields of an event.
+    Event { pallet: String, event: String },
+    /// Type of a constant.
+    Constant { pallet: String, constant: String },
+}
+
+/// How an item differs between two runtimes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ItemChange {
+    /// The item only exists in the new runtime.
+    Added,
+    /// The item only exists in the old runtime.
+    Removed,
+    /// The item exists in both runtimes, but is encoded differently.
+    Changed { old: ItemHash, new: ItemHash },
+}
+
+/// Differences between two runtimes, see [`Metadata::check_compatibility`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompatibilityReport {
+    pub changes: BTreeMap<MetadataItem, ItemChange>,
+}
+
+impl CompatibilityReport {
+    /// Returns true if no item has been removed or changed. Added items do not break code
+    /// written against the old runtime.
+    pub fn is_compatible(&self) -> bool {
+        self.changes
+            .values()
+            .all(|change| *change == ItemChange::Added)
+    }
+
+    /// Returns true if none of `items` has been removed or changed.
+    pub fn is_compatible_with<'a>(
+        &self,
+        items: impl IntoIterator<Item = &'a MetadataItem>,
+    ) -> bool {
+        items
+            .into_iter()
+            .all(|item| matches!(self.changes.get(item), None | Some(ItemChange::Added)))
+    }
+
+    /// Returns how `item` has changed, or `None` if it has not.
+    pub fn change(&self, item: &MetadataItem) -> Option<&ItemChange> {
+        self.changes.get(item)
+    }
+
+    /// Compares the item hashes of an old runtime with the ones of a new runtime.
+    pub fn from_hashes(
+        old: &BTreeMap<MetadataItem, ItemHash>,
+        new: &BTreeMap<MetadataItem, ItemHash>,
+    ) -> Self {
+        let mut changes = BTreeMap::new();
+        for (item, old_hash) in old {
+            match new.get(item) {
+                None => {
+                    changes.insert(item.clone(), ItemChange::Removed);
+                }
+                Some(new_hash) if new_hash != old_hash => {
+                    let change = ItemChange::Changed {
+                        old: *old_hash,
+                        new: *new_hash,
+                    };
+                    changes.insert(item.clone(), change);
+                }
+                Some(_) => {}
+            }
+        }
+        for item in new.keys().filter(|item| !old.contains_key(item)) {
+            changes.insert(item.clone(), ItemChange::Added);
+        }
+        Self { changes }
+    }
+}
+
+impl Metadata {
+    /// Compares this runtime with the `new` one.
+    pub fn check_compatibility(
+        &self,
+        new: &Metadata,
+    ) -> Result<CompatibilityReport, MetadataError> {
+        Ok(CompatibilityReport::from_hashes(
+            &self.item_hashes()?,
+            &new.item_hashes()?,
+        ))
+    }
+
+    /// Compares hashes pinned by our code, e.g. computed from the runtime it has been written
+    /// against, with the ones of this runtime. Only the pinned items are reported.
+    pub fn check_pinned(
+        &self,
+        pinned: &BTreeMap<MetadataItem, ItemHash>,
+    ) -> Result<CompatibilityReport, MetadataError> {
+        let mut hashes = self.item_hashes()?;
+        hashes.retain(|item, _| pinned.contains_key(item));
+        Ok(CompatibilityReport::from_hashes(pinned, &hashes))
+    }
+
+    /// Returns the hash of every pallet, call, storage entry, event and constant.
+    pub fn item_hashes(&self) -> Result<BTreeMap<MetadataItem, ItemHash>, MetadataError> {
+        let mut hasher = TypeHasher::new(&self.metadata.types);
+        let mut hashes = BTreeMap::new();
+        for pallet in self.pallets.values() {
+            let item = MetadataItem::Pallet {
+                pallet: pallet.name.clone(),
+            };
+            hashes.insert(item, blake2_256(&pallet.index.encode()));
+
+            for (call, variant) in &pallet.call_variants {
+                let item = MetadataItem::Call {
+                    pallet: pallet.name.clone(),
+                    call: call.clone(),
+                };
+                hashes.insert(item, hasher.variant_hash(pallet.index, variant)?);
+            }
+            for (entry, metadata) in &pallet.storage {
+                let item = MetadataItem::Storage {
+                    pallet: pallet.name.clone(),
+                    entry: entry.clone(),
+                };
+                hashes.insert(item, hasher.storage_hash(metadata)?);
+            }
+            for (constant, metadata) in &pallet.constants {
+                let item = MetadataItem::Constant {
+                    pallet: pallet.name.clone(),
+                    constant: constant.clone(),
+                };
+                hashes.insert(item, hasher.type_hash(metadata.ty.id())?);
+            }
+        }
+        for event in self.events.values() {
+            let pallet = self.pallets.get(event.pallet()).map_or(0, |p| p.index);
+            let item = MetadataItem::Event {
+                pallet: event.pallet().into(),
+                event: event.event().into(),
+            };
+            hashes.insert(item, hasher.variant_hash(pallet, event.variant())?);
+        }
+        Ok(hashes)
+    }
+
+    /// Returns the hash of `item`, or `None` if it is not part of this runtime.
+    pub fn item_hash(&self, item: &MetadataItem) -> Result<Option<ItemHash>, MetadataError> {
+        Ok(self.item_hashes()?.remove(item))
+    }
+}
+
+/// Hashes types by their structure. Hashes of types that are not part of a cycle are cached.
+struct TypeHasher<'a> {
+    types: &'a PortableRegistry,
+    cache: BTreeMap<u32, ItemHash>,
+    /// Types currently being hashed, to detect recursive types.
+    stack: Vec<u32>,
+}
+
+impl<'a> TypeHasher<'a> {
+    fn new(types: &'a PortableRegistry) -> Self {
+        Self {
+            types,
+            cache: BTreeMap::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    fn type_hash(&mut self, id: u32) -> Result<ItemHash, MetadataError> {
+        self.hash(id).map(|(hash, _)| hash)
+    }
+
+    fn variant_hash(
+        &mut self,
+        pallet_index: u8,
+        variant: &Variant<PortableForm>,
+    ) -> Result<ItemHash, MetadataError> {
+        let mut bytes = (pallet_index, variant.index()).encode();
+        self.encode_fields(variant.fields(), &mut bytes, &mut usize::MAX)?;
+        Ok(blake2_256(&bytes))
+    }
+
+    fn storage_hash(
+        &mut self,
+        entry: &StorageEntryMetadata<PortableForm>,
+    ) -> Result<ItemHash, MetadataError> {
+        let mut bytes = entry.modifier.encode();
+        match &entry.ty {
+            StorageEntryType::Plain(value) => {
+                0u8.encode_to(&mut bytes);
+                self.type_hash(value.id())?.encode_to(&mut bytes);
+            }
+            StorageEntryType::Map {
+                hashers,
+                key,
+                value,
+            } => {
+                1u8.encode_to(&mut bytes);
+                hashers.encode_to(&mut bytes);
+                self.type_hash(key.id())?.encode_to(&mut bytes);
+                self.type_hash(value.id())?.encode_to(&mut bytes);
+            }
+        }
+        Ok(blake2_256(&bytes))
+    }
+
+    /// Returns the hash of type `id` together with the lowest position in the stack it refers to,
+    /// `usize::MAX` if it does not refer to any type being hashed.
+    fn hash(&mut self, id: u32) -> Result<(ItemHash, usize), MetadataError> {
+        if let Some(hash) = self.cache.get(&id) {
+            return Ok((*hash, usize::MAX));
+        }
+        if let Some(position) = self.stack.iter().position(|ty| *ty == id) {
+            // The distance to the recursion does not depend on where the hashing started.
+            let distance = (self.stack.len() - position) as u32;
+            return Ok((blake2_256(&(u8::MAX, distance).encode()), position));
+        }
+
+        let ty = self
+            .types
+            .resolve(id)
+            .ok_or(MetadataError::TypeNotFound(id))?;
+        let position = self.stack.len();
+        self.stack.push(id);
+        let mut lowest = usize::MAX;
+        let mut bytes = Vec::new();
+        let result = self.encode_type_def(ty.type_def(), &mut bytes, &mut lowest);
+        self.stack.pop();
+        result?;
+
+        let hash = blake2_256(&bytes);
+        if lowest >= position {
+            self.cache.insert(id, hash);
+        }
+        Ok((hash, lowest))
+    }
+
+    fn encode_type_def(
+        &mut self,
+        type_def: &TypeDef<PortableForm>,
+        bytes: &mut Vec<u8>,
+        lowest: &mut usize,
+    ) -> Result<(), MetadataError> {
+        match type_def {
+            TypeDef::Composite(composite) => {
+                0u8.encode_to(bytes);
+                self.encode_fields(composite.fields(), bytes, lowest)?;
+            }
+            TypeDef::Variant(variant) => {
+                1u8.encode_to(bytes);
+                (variant.variants().len() as u32).encode_to(bytes);
+                for v in variant.variants() {
+                    (v.name(), v.index()).encode_to(bytes);
+                    self.encode_fields(v.fields(), bytes, lowest)?;
+                }
+            }
+            TypeDef::Sequence(sequence) => {
+                2u8.encode_to(bytes);
+                self.encode_type(sequence.type_param().id(), bytes, lowest)?;
+            }
+            TypeDef::Array(array) => {
+                3u8.encode_to(bytes);
+                array.len().encode_to(bytes);
+                self.encode_type(array.type_param().id(), bytes, lowest)?;
+            }
+            TypeDef::Tuple(tuple) => {
+                4u8.encode_to(bytes);
+                (tuple.fields().len() as u32).encode_to(bytes);
+                for field in tuple.fields() {
+                    self.encode_type(field.id(), bytes, lowest)?;
+                }
+            }
+            TypeDef::Primitive(primitive) => {
+                5u8.encode_to(bytes);
+                primitive.encode_to(bytes);
+            }
+            TypeDef::Compact(compact) => {
+                6u8.encode_to(bytes);
+                self.encode_type(compact.type_param().id(), bytes, lowest)?;
+            }
+            TypeDef::BitSequence(bit_sequence) => {
+                7u8.encode_to(bytes);
+                self.encode_type(bit_sequence.bit_store_type().id(), bytes, lowest)?;
+                self.encode_type(bit_sequence.bit_order_type().id(), bytes, lowest)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_fields(
+        &mut self,
+        fields: &[Field<PortableForm>],
+        bytes: &mut Vec<u8>,
+        lowest: &mut usize,
+    ) -> Result<(), MetadataError> {
+        (fields.len() as u32).encode_to(bytes);
+        for field in fields {
+            field.name().encode_to(bytes);
+            self.encode_type(field.ty().id(), bytes, lowest)?;
+        }
+        Ok(())
+    }
+
+    fn encode_type(
+        &mut self,
+        id: u32,
+        bytes: &mut Vec<u8>,
+        lowest: &mut usize,
+    ) -> Result<(), MetadataError> {
+        let (hash, referred) = self.hash(id)?;
+        *lowest = (*lowest).min(referred);
+        hash.encode_to(bytes);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frame_metadata::{
+        v14::{
+            ExtrinsicMetadata, PalletCallMetadata, PalletConstantMetadata, PalletEventMetadata,
+            PalletStorageMetadata, RuntimeMetadataV14, StorageEntryModifier, StorageHasher,
+        },
+        RuntimeMetadataPrefixed,
+    };
+    use scale_info::{meta_type, TypeInfo};
+
+    mod v1 {
+        use super::*;
+
+        #[derive(TypeInfo)]
+        #[allow(non_camel_case_types, dead_code)]
+        pub enum Call {
+            transfer { dest: [u8; 32], value: u128 },
+            remark { remark: Vec<u8> },
+        }
+
+        #[derive(TypeInfo)]
+        #[allow(dead_code)]
+        pub enum Event {
+            Transfer { from: [u8; 32], amount: u128 },
+        }
+
+        /// Recursive, like the runtime call in `Utility::batch`.
+        #[derive(TypeInfo)]
+        #[allow(dead_code)]
+        pub enum Nested {
+            Leaf(u32),
+            Node(Vec<Nested>),
+        }
+    }
+
+    mod v2 {
+        use super::*;
+
+        #[derive(TypeInfo)]
+        #[allow(non_camel_case_types, dead_code)]
+        pub enum Call {
+            transfer {
+                dest: [u8; 32],
+                #[codec(compact)]
+                value: u128,
+            },
+            remark {
+                remark: Vec<u8>,
+            },
+            burn {
+                value: u128,
+            },
+        }
+
+        /// Same shape as [`v1::Event`], but a different path.
+        #[derive(TypeInfo)]
+        #[allow(dead_code)]
+        pub enum Event {
+            Transfer { from: [u8; 32], amount: u128 },
+        }
+
+        #[derive(TypeInfo)]
+        #[allow(dead_code)]
+        pub enum Nested {
+            Leaf(u64),
+            Node(Vec<Nested>),
+        }
+    }
+
+    fn metadata<C, E, N>(index: u8, hasher: StorageHasher) -> Metadata
+    where
+        C: TypeInfo + 'static,
+        E: TypeInfo + 'static,
+        N: TypeInfo + 'static,
+    {
+        let pallet = frame_metadata::v14::PalletMetadata {
+            name: "Balances",
+            storage: Some(PalletStorageMetadata {
+                prefix: "Balances",
+                entries: vec![StorageEntryMetadata {
+                    name: "Account",
+                    modifier: StorageEntryModifier::Default,
+                    ty: StorageEntryType::Map {
+                        hashers: vec![hasher],
+                        key: meta_type::<[u8; 32]>(),
+                        value: meta_type::<u128>(),
+                    },
+                    default: vec![0; 16],
+                    docs: vec![],
+                }],
+            }),
+            calls: Some(PalletCallMetadata {
+                ty: meta_type::<C>(),
+            }),
+            event: Some(PalletEventMetadata {
+                ty: meta_type::<E>(),
+            }),
+            constants: vec![PalletConstantMetadata {
+                name: "Nested",
+                ty: meta_type::<N>(),
+                value: vec![0, 0, 0, 0, 0],
+                docs: vec![],
+            }],
+            error: None,
+            index,
+        };
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 4,
+            signed_extensions: vec![],
+        };
+        let runtime_metadata = RuntimeMetadataV14::new(vec![pallet], extrinsic, meta_type::<()>());
+        Metadata::try_from(RuntimeMetadataPrefixed::from(runtime_metadata)).unwrap()
+    }
+
+    fn call(call: &str) -> MetadataItem {
+        MetadataItem::Call {
+            pallet: "Balances".into(),
+            call: call.into(),
+        }
+    }
+
+    fn event(event: &str) -> MetadataItem {
+        MetadataItem::Event {
+            pallet: "Balances".into(),
+            event: event.into(),
+        }
+    }
+
+    fn storage(entry: &str) -> MetadataItem {
+        MetadataItem::Storage {
+            pallet: "Balances".into(),
+            entry: entry.into(),
+        }
+    }
+
+    fn constant(constant: &str) -> MetadataItem {
+        MetadataItem::Constant {
+            pallet: "Balances".into(),
+            constant: constant.into(),
+        }
+    }
+
+    #[test]
+    fn same_runtime_is_compatible() {
+        let old = metadata::<v1::Call, v1::Event, v1::Nested>(5, StorageHasher::Blake2_128Concat);
+        let new = metadata::<v1::Call, v1::Event, v1::Nested>(5, StorageHasher::Blake2_128Concat);
+
+        let report = old.check_compatibility(&new).unwrap();
+        assert!(report.changes.is_empty());
+        assert!(report.is_compatible());
+    }
+
+    #[test]
+    fn reports_changed_added_and_unchanged_items() {
+        let old = metadata::<v1::Call, v1::Event, v1::Nested>(5, StorageHasher::Blake2_128Concat);
+        let new = metadata::<v2::Call, v2::Event, v2::Nested>(5, StorageHasher::Twox64Concat);
+
+        let report = old.check_compatibility(&new).unwrap();
+        assert!(!report.is_compatible());
+        assert!(matches!(
+            report.change(&call("transfer")),
+            Some(ItemChange::Changed { .. })
+        ));
+        assert_eq!(report.change(&call("burn")), Some(&ItemChange::Added));
+        assert_eq!(report.change(&call("remark")), None);
+        // Type paths do not matter.
+        assert_eq!(report.change(&event("Transfer")), None);
+        assert!(matches!(
+            report.change(&storage("Account")),
+            Some(ItemChange::Changed { .. })
+        ));
+        assert!(matches!(
+            report.change(&constant("Nested")),
+            Some(ItemChange::Changed { .. })
+        ));
+
+        assert!(report.is_compatible_with(&[call("remark"), call("burn"), event("Transfer")]));
+        assert!(!report.is_compatible_with(&[call("remark"), call("transfer")]));
+    }
+
+    #[test]
+    fn pallet_index_changes_calls_and_events() {
+        let old = metadata::<v1::Call, v1::Event, v1::Nested>(5, StorageHasher::Blake2_128Concat);
+        let new = metadata::<v1::Call, v1::Event, v1::Nested>(6, StorageHasher::Blake2_128Concat);
+
+        let report = old.check_compatibility(&new).unwrap();
+        let pallet = MetadataItem::Pallet {
+            pallet: "Balances".into(),
+        };
+        assert!(report.change(&pallet).is_some());
+        assert!(report.change(&call("remark")).is_some());
+        assert!(report.change(&event("Transfer")).is_some());
+        assert_eq!(report.change(&storage("Account")), None);
+    }
+
+    #[test]
+    fn checks_pinned_hashes_only() {
+        let old = metadata::<v1::Call, v1::Event, v1::Nested>(5, StorageHasher::Blake2_128Concat);
+        let new = metadata::<v2::Call, v2::Event, v2::Nested>(5, StorageHasher::Blake2_128Concat);
+
+        let mut pinned = BTreeMap::new();
+        pinned.insert(
+            call("remark"),
+            old.item_hash(&call("remark")).unwrap().unwrap(),
+        );
+        pinned.insert(
+            event("Transfer"),
+            old.item_hash(&event("Transfer")).unwrap().unwrap(),
+        );
+        assert!(new.check_pinned(&pinned).unwrap().changes.is_empty());
+
+        pinned.insert(
+            call("transfer"),
+            old.item_hash(&call("transfer")).unwrap().unwrap(),
+        );
+        let report = new.check_pinned(&pinned).unwrap();
+        assert_eq!(report.changes.len(), 1);
+        assert!(report.change(&call("transfer")).is_some());
+    }
+
+    #[test]
+    fn hashes_recursive_types() {
+        let metadata =
+            metadata::<v1::Call, v1::Event, v1::Nested>(5, StorageHasher::Blake2_128Concat);
+        let hash = metadata.item_hash(&constant("Nested")).unwrap();
+        assert!(hash.is_some());
+        assert_eq!(metadata.item_hash(&constant("Missing")).unwrap(), None);
+    }
+}
@@ -0,0 +1,642 @@
+/*
+    Copyright 2021 Integritee AG and Supercomputing Systems AG
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+        http://www.apache.org/licenses/LICENSE-2.0
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Signed extensions composed from the metadata of the node.
+//!
+//! [`ac_primitives::BaseExtrinsicParams`] assumes the signed extensions of a default substrate
+//! node. [`MetadataExtrinsicParams`] instead encodes the signed extensions the metadata lists, in
+//! their order, with the encoders of a [`SignedExtensionRegistry`].
+//!
+//! This is **not** part of subxt.
+
+use crate::{
+    metadata::{Metadata, MetadataError},
+    Encoded,
+};
+use ac_primitives::ExtrinsicParams;
+use codec::{Compact, Encode};
+use scale_info::{PortableRegistry, TypeDef, TypeDefPrimitive};
+use sp_core::H256;
+use sp_runtime::generic::Era;
+use sp_std::collections::btree_map::BTreeMap;
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+/// The values the signed extensions of an extrinsic are encoded from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedExtensionParams {
+    pub spec_version: u32,
+    pub transaction_version: u32,
+    pub nonce: u32,
+    pub genesis_hash: H256,
+    pub era: Era,
+    /// Block hash the era starts at, the genesis hash for immortal extrinsics.
+    pub mortality_checkpoint: H256,
+    pub tip: u128,
+    /// Asset the tip and the fees are paid in, the native currency if `None`.
+    pub tip_asset: Option<u32>,
+}
+
+/// Encodes the data of one signed extension.
+pub trait SignedExtensionEncoder: Send + Sync {
+    /// Encodes the data sent along with the extrinsic.
+    fn encode_extra_to(&self, params: &SignedExtensionParams, dest: &mut Vec<u8>);
+
+    /// Encodes the data that is signed, but not sent along with the extrinsic.
+    fn encode_additional_signed_to(&self, params: &SignedExtensionParams, dest: &mut Vec<u8>);
+
+    /// Returns whether the encoder produces the extra of type `extra_ty`. The same signed
+    /// extension may carry different data depending on the runtime, the encoder is not used for
+    /// types it does not accept.
+    fn accepts(&self, _types: &PortableRegistry, _extra_ty: u32) -> bool {
+        true
+    }
+}
+
+type EncodeFn = fn(&SignedExtensionParams, &mut Vec<u8>);
+
+/// Encoder of the signed extensions known to the default registry.
+struct KnownExtension {
+    extra: EncodeFn,
+    additional_signed: EncodeFn,
+    accepts_extra: fn(&PortableRegistry, u32) -> bool,
+}
+
+impl SignedExtensionEncoder for KnownExtension {
+    fn encode_extra_to(&self, params: &SignedExtensionParams, dest: &mut Vec<u8>) {
+        (self.extra)(params, dest)
+    }
+
+    fn encode_additional_signed_to(&self, params: &SignedExtensionParams, dest: &mut Vec<u8>) {
+        (self.additional_signed)(params, dest)
+    }
+
+    fn accepts(&self, types: &PortableRegistry, extra_ty: u32) -> bool {
+        (self.accepts_extra)(types, extra_ty)
+    }
+}
+
+fn nothing(_: &SignedExtensionParams, _: &mut Vec<u8>) {}
+
+fn any_type(_: &PortableRegistry, _: u32) -> bool {
+    true
+}
+
+/// Maps signed extension identifiers to the encoders of their data.
+///
+/// The default registry knows the signed extensions of `frame-system`, `pallet-transaction-payment`,
+/// `pallet-asset-tx-payment`, `frame-metadata-hash-extension` and the polkadot claims pallet. Register
+/// encoders for custom signed extensions with [`Self::register`].
+#[derive(Clone)]
+pub struct SignedExtensionRegistry {
+    encoders: BTreeMap<String, Arc<dyn SignedExtensionEncoder>>,
+}
+
+impl SignedExtensionRegistry {
+    /// Registry without any encoders.
+    pub fn empty() -> Self {
+        Self {
+            encoders: BTreeMap::new(),
+        }
+    }
+
+    /// Registers `encoder` for the signed extension `identifier`, replacing a previous one.
+    pub fn register(
+        mut self,
+        identifier: &str,
+        encoder: impl SignedExtensionEncoder + 'static,
+    ) -> Self {
+        self.encoders
+            .insert(identifier.to_string(), Arc::new(encoder));
+        self
+    }
+
+    pub fn get(&self, identifier: &str) -> Option<&Arc<dyn SignedExtensionEncoder>> {
+        self.encoders.get(identifier)
+    }
+
+    fn register_known(
+        self,
+        identifier: &str,
+        extra: EncodeFn,
+        additional_signed: EncodeFn,
+    ) -> Self {
+        self.register(
+            identifier,
+            KnownExtension {
+                extra,
+                additional_signed,
+                accepts_extra: any_type,
+            },
+        )
+    }
+}
+
+impl Default for SignedExtensionRegistry {
+    fn default() -> Self {
+        Self::empty()
+            .register_known("CheckNonZeroSender", nothing, nothing)
+            .register_known("CheckSpecVersion", nothing, |p, dest| {
+                p.spec_version.encode_to(dest)
+            })
+            .register_known("CheckTxVersion", nothing, |p, dest| {
+                p.transaction_version.encode_to(dest)
+            })
+            .register_known("CheckGenesis", nothing, |p, dest| {
+                p.genesis_hash.encode_to(dest)
+            })
+            .register_known(
+                "CheckMortality",
+                |p, dest| p.era.encode_to(dest),
+                |p, dest| p.mortality_checkpoint.encode_to(dest),
+            )
+            .register_known(
+                "CheckEra",
+                |p, dest| p.era.encode_to(dest),
+                |p, dest| p.mortality_checkpoint.encode_to(dest),
+            )
+            .register_known(
+                "CheckNonce",
+                |p, dest| Compact(p.nonce).encode_to(dest),
+                nothing,
+            )
+            .register_known("CheckWeight", nothing, nothing)
+            .register_known(
+                "ChargeTransactionPayment",
+                |p, dest| Compact(p.tip).encode_to(dest),
+                nothing,
+            )
+            // `pallet-asset-conversion-tx-payment` uses the same identifier with other assets.
+            .register(
+                "ChargeAssetTxPayment",
+                KnownExtension {
+                    extra: |p, dest| (Compact(p.tip), p.tip_asset).encode_to(dest),
+                    additional_signed: nothing,
+                    accepts_extra: is_tip_with_u32_asset,
+                },
+            )
+            // Metadata hash checks disabled: the mode is `Disabled` and no hash is signed.
+            .register_known(
+                "CheckMetadataHash",
+                |_, dest| 0u8.encode_to(dest),
+                |_, dest| None::<[u8; 32]>.encode_to(dest),
+            )
+            .register_known("PrevalidateAttests", nothing, nothing)
+    }
+}
+
+/// Builds [`MetadataExtrinsicParams`] from the signed extensions the metadata of the node lists.
+///
+/// The signed extensions are resolved once. A builder has to be created anew from the metadata of
+/// an upgraded runtime, which the `Api` does when the builder is set with
+/// `Api::set_extrinsic_params_builder_from_metadata`.
+#[derive(Clone)]
+pub struct MetadataExtrinsicParamsBuilder {
+    signed_extensions: Vec<Arc<dyn SignedExtensionEncoder>>,
+    era: Era,
+    mortality_checkpoint: Option<H256>,
+    tip: u128,
+    tip_asset: Option<u32>,
+}
+
+impl MetadataExtrinsicParamsBuilder {
+    /// Resolves the signed extensions of `metadata` with the default [`SignedExtensionRegistry`].
+    pub fn new(metadata: &Metadata) -> Result<Self, MetadataError> {
+        Self::with_registry(metadata, &SignedExtensionRegistry::default())
+    }
+
+    /// Resolves the signed extensions of `metadata` with `registry`.
+    ///
+    /// Signed extensions without data do not need an encoder. Any other signed extension missing
+    /// in `registry` results in [`MetadataError::UnknownSignedExtension`].
+    pub fn with_registry(
+        metadata: &Metadata,
+        registry: &SignedExtensionRegistry,
+    ) -> Result<Self, MetadataError> {
+        let runtime_metadata = metadata.runtime_metadata();
+        let signed_extensions = runtime_metadata
+            .extrinsic
+            .signed_extensions
+            .iter()
+            .map(|extension| match registry.get(&extension.identifier) {
+                Some(encoder) if encoder.accepts(&runtime_metadata.types, extension.ty.id()) => {
+                    Ok(encoder.clone())
+                }
+                None if is_empty_type(&runtime_metadata.types, extension.ty.id())
+                    && is_empty_type(&runtime_metadata.types, extension.additional_signed.id()) =>
+                {
+                    Ok(Arc::new(KnownExtension {
+                        extra: nothing,
+                        additional_signed: nothing,
+                        accepts_extra: any_type,
+                    }) as Arc<dyn SignedExtensionEncoder>)
+                }
+                _ => Err(MetadataError::UnknownSignedExtension(
+                    extension.identifier.clone(),
+                )),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            signed_extensions,
+            era: Era::Immortal,
+            mortality_checkpoint: None,
+            tip: 0,
+            tip_asset: None,
+        })
+    }
+
+    /// Set the [`Era`], which defines how long the transaction will be valid for. The second
+    /// argument is the block hash after which the transaction becomes valid, see
+    /// [`ac_primitives::BaseExtrinsicParamsBuilder::era`].
+    pub fn era(mut self, era: Era, checkpoint: H256) -> Self {
+        self.era = era;
+        self.mortality_checkpoint = Some(checkpoint);
+        self
+    }
+
+    /// Set the tip you'd like to give to the block author for this transaction.
+    pub fn tip(mut self, tip: u128) -> Self {
+        self.tip = tip;
+        self
+    }
+
+    /// Pay the tip and the fees in `asset` instead of the native currency. Only has an effect on
+    /// nodes with the `ChargeAssetTxPayment` signed extension.
+    pub fn tip_asset(mut self, asset: u32) -> Self {
+        self.tip_asset = Some(asset);
+        self
+    }
+}
+
+/// An implementation of [`ExtrinsicParams`] encoding the signed extensions the metadata of the
+/// node lists, see [`MetadataExtrinsicParamsBuilder`].
+#[derive(Clone)]
+pub struct MetadataExtrinsicParams {
+    params: SignedExtensionParams,
+    signed_extensions: Vec<Arc<dyn SignedExtensionEncoder>>,
+}
+
+impl MetadataExtrinsicParams {
+    pub fn params(&self) -> &SignedExtensionParams {
+        &self.params
+    }
+}
+
+impl ExtrinsicParams for MetadataExtrinsicParams {
+    type OtherParams = MetadataExtrinsicParamsBuilder;
+    type SignedExtra = Encoded;
+    type AdditionalSigned = Encoded;
+
+    fn new(
+        spec_version: u32,
+        transaction_version: u32,
+        nonce: u32,
+        genesis_hash: H256,
+        other_params: Self::OtherParams,
+    ) -> Self {
+        let params = SignedExtensionParams {
+            spec_version,
+            transaction_version,
+            nonce,
+            genesis_hash,
+            era: other_params.era,
+            mortality_checkpoint: other_params.mortality_checkpoint.unwrap_or(genesis_hash),
+            tip: other_params.tip,
+            tip_asset: other_params.tip_asset,
+        };
+        Self {
+            params,
+            signed_extensions: other_params.signed_extensions,
+        }
+    }
+
+    fn signed_extra(&self) -> Self::SignedExtra {
+        let mut extra = Vec::new();
+        for extension in &self.signed_extensions {
+            extension.encode_extra_to(&self.params, &mut extra);
+        }
+        Encoded(extra)
+    }
+
+    fn additional_signed(&self) -> Self::AdditionalSigned {
+        let mut additional_signed = Vec::new();
+        for extension in &self.signed_extensions {
+            extension.encode_additional_signed_to(&self.params, &mut additional_signed);
+        }
+        Encoded(additional_signed)
+    }
+
+    /// There are no default params, the signed extensions have to be resolved from the metadata
+    /// with [`MetadataExtrinsicParamsBuilder::new`].
+    fn default_other_params() -> Option<Self::OtherParams> {
+        None
+    }
+
+    fn with_era(
+        other_params: Self::OtherParams,
+        era: Era,
+        checkpoint: H256,
+    ) -> Option<Self::OtherParams> {
+        Some(other_params.era(era, checkpoint))
+    }
+}
+
+/// Whether values of the type `id` are always encoded to zero bytes.
+fn is_empty_type(types: &PortableRegistry, id: u32) -> bool {
+    match types.resolve(id).map(|ty| ty.type_def()) {
+        Some(TypeDef::Tuple(tuple)) => tuple
+            .fields()
+            .iter()
+            .all(|field| is_empty_type(types, field.id())),
+        Some(TypeDef::Composite(composite)) => composite
+            .fields()
+            .iter()
+            .all(|field| is_empty_type(types, field.ty().id())),
+        Some(TypeDef::Array(array)) => {
+            array.len() == 0 || is_empty_type(types, array.type_param().id())
+        }
+        _ => false,
+    }
+}
+
+/// Whether `id` is `(Compact<u128>, Option<u32>)`, the tip and asset of `pallet-asset-tx-payment`
+/// with `u32` asset ids.
+fn is_tip_with_u32_asset(types: &PortableRegistry, id: u32) -> bool {
+    let fields: Vec<u32> = match types.resolve(id).map(|ty| ty.type_def()) {
+        Some(TypeDef::Composite(composite)) => {
+            composite.fields().iter().map(|f| f.ty().id()).collect()
+        }
+        Some(TypeDef::Tuple(tuple)) => tuple.fields().iter().map(|f| f.id()).collect(),
+        _ => return false,
+    };
+    match fields.as_slice() {
+        [tip, asset] => is_compact_u128(types, *tip) && is_option_u32(types, *asset),
+        _ => false,
+    }
+}
+
+fn is_compact_u128(types: &PortableRegistry, id: u32) -> bool {
+    match types.resolve(id).map(|ty| ty.type_def()) {
+        Some(TypeDef::Compact(compact)) => {
+            is_primitive(types, compact.type_param().id(), TypeDefPrimitive::U128)
+        }
+        _ => false,
+    }
+}
+
+fn is_option_u32(types: &PortableRegistry, id: u32) -> bool {
+    let ty = match types.resolve(id) {
+        Some(ty) if ty.path().ident().as_deref() == Some("Option") => ty,
+        _ => return false,
+    };
+    match ty.type_def() {
+        TypeDef::Variant(variant) => variant.variants().iter().any(|v| {
+            v.name() == "Some"
+                && matches!(v.fields(), [field] if is_primitive(types, field.ty().id(), TypeDefPrimitive::U32))
+        }),
+        _ => false,
+    }
+}
+
+fn is_primitive(types: &PortableRegistry, id: u32, primitive: TypeDefPrimitive) -> bool {
+    matches!(types.resolve(id).map(|ty| ty.type_def()), Some(TypeDef::Primitive(p)) if *p == primitive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ac_primitives::{PlainTipExtrinsicParams, PlainTipExtrinsicParamsBuilder};
+    use frame_metadata::{
+        v14::{ExtrinsicMetadata, RuntimeMetadataV14, SignedExtensionMetadata},
+        RuntimeMetadataPrefixed,
+    };
+    use scale_info::{meta_type, MetaType, TypeInfo};
+
+    #[derive(TypeInfo)]
+    struct CheckClaim;
+
+    #[derive(TypeInfo)]
+    struct ChargeAssetTxPayment {
+        #[codec(compact)]
+        _tip: u128,
+        _asset_id: Option<u32>,
+    }
+
+    #[derive(TypeInfo)]
+    struct MultiLocation {
+        _parents: u8,
+    }
+
+    #[derive(TypeInfo)]
+    struct ChargeAssetConversionTxPayment {
+        #[codec(compact)]
+        _tip: u128,
+        _asset_id: Option<MultiLocation>,
+    }
+
+    fn metadata(signed_extensions: Vec<(&'static str, MetaType, MetaType)>) -> Metadata {
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 4,
+            signed_extensions: signed_extensions
+                .into_iter()
+                .map(
+                    |(identifier, ty, additional_signed)| SignedExtensionMetadata {
+                        identifier,
+                        ty,
+                        additional_signed,
+                    },
+                )
+                .collect(),
+        };
+        let runtime_metadata = RuntimeMetadataV14::new(vec![], extrinsic, meta_type::<()>());
+        Metadata::try_from(RuntimeMetadataPrefixed::from(runtime_metadata)).unwrap()
+    }
+
+    fn substrate_metadata() -> Metadata {
+        metadata(vec![
+            ("CheckNonZeroSender", meta_type::<()>(), meta_type::<()>()),
+            ("CheckSpecVersion", meta_type::<()>(), meta_type::<u32>()),
+            ("CheckTxVersion", meta_type::<()>(), meta_type::<u32>()),
+            ("CheckGenesis", meta_type::<()>(), meta_type::<H256>()),
+            ("CheckMortality", meta_type::<Era>(), meta_type::<H256>()),
+            ("CheckNonce", meta_type::<Compact<u32>>(), meta_type::<()>()),
+            ("CheckWeight", meta_type::<()>(), meta_type::<()>()),
+            (
+                "ChargeTransactionPayment",
+                meta_type::<Compact<u128>>(),
+                meta_type::<()>(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn encodes_substrate_signed_extensions_like_base_extrinsic_params() {
+        let genesis_hash = H256::from([1u8; 32]);
+        let checkpoint = H256::from([2u8; 32]);
+        let era = Era::mortal(64, 12);
+
+        let builder = MetadataExtrinsicParamsBuilder::new(&substrate_metadata())
+            .unwrap()
+            .era(era, checkpoint)
+            .tip(5);
+        let params = MetadataExtrinsicParams::new(1, 2, 3, genesis_hash, builder);
+        let base_builder = PlainTipExtrinsicParamsBuilder::new()
+            .era(era, checkpoint)
+            .tip(5);
+        let base_params = PlainTipExtrinsicParams::new(1, 2, 3, genesis_hash, base_builder);
+
+        assert_eq!(
+            params.signed_extra().encode(),
+            base_params.signed_extra().encode()
+        );
+        assert_eq!(
+            params.additional_signed().encode(),
+            base_params.additional_signed().encode()
+        );
+    }
+
+    #[test]
+    fn has_no_default_builder() {
+        assert!(MetadataExtrinsicParams::default_other_params().is_none());
+        assert!(PlainTipExtrinsicParams::default_other_params().is_some());
+    }
+
+    #[test]
+    fn with_era_makes_extrinsics_mortal() {
+        let checkpoint = H256::from([2u8; 32]);
+        let builder = MetadataExtrinsicParamsBuilder::new(&substrate_metadata()).unwrap();
+        let builder =
+            MetadataExtrinsicParams::with_era(builder, Era::mortal(8, 3), checkpoint).unwrap();
+        let params = MetadataExtrinsicParams::new(1, 2, 3, H256::zero(), builder);
+
+        assert_eq!(params.params().era, Era::mortal(8, 3));
+        assert_eq!(params.params().mortality_checkpoint, checkpoint);
+    }
+
+    #[test]
+    fn follows_the_order_of_the_metadata() {
+        let metadata = metadata(vec![
+            ("CheckNonce", meta_type::<Compact<u32>>(), meta_type::<()>()),
+            (
+                "CheckMetadataHash",
+                meta_type::<u8>(),
+                meta_type::<Option<[u8; 32]>>(),
+            ),
+            ("CheckTxVersion", meta_type::<()>(), meta_type::<u32>()),
+            ("CheckSpecVersion", meta_type::<()>(), meta_type::<u32>()),
+        ]);
+        let builder = MetadataExtrinsicParamsBuilder::new(&metadata).unwrap();
+        let params = MetadataExtrinsicParams::new(1, 2, 3, H256::zero(), builder);
+
+        assert_eq!(params.signed_extra().0, vec![3 << 2, 0]);
+        assert_eq!(
+            params.additional_signed().0,
+            vec![0, 2, 0, 0, 0, 1, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn unknown_signed_extensions_without_data_need_no_encoder() {
+        let metadata = metadata(vec![
+            ("CheckClaim", meta_type::<CheckClaim>(), meta_type::<()>()),
+            ("CheckNonce", meta_type::<Compact<u32>>(), meta_type::<()>()),
+        ]);
+        let builder = MetadataExtrinsicParamsBuilder::new(&metadata).unwrap();
+        let params = MetadataExtrinsicParams::new(1, 2, 3, H256::zero(), builder);
+
+        assert_eq!(params.signed_extra().0, vec![3 << 2]);
+        assert!(params.additional_signed().0.is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_signed_extensions_with_data() {
+        let metadata = metadata(vec![
+            ("CheckNonce", meta_type::<Compact<u32>>(), meta_type::<()>()),
+            ("ChargeCustomFees", meta_type::<u32>(), meta_type::<()>()),
+        ]);
+
+        assert_eq!(
+            MetadataExtrinsicParamsBuilder::new(&metadata).err(),
+            Some(MetadataError::UnknownSignedExtension(
+                "ChargeCustomFees".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn registered_encoders_handle_custom_signed_extensions() {
+        struct ChargeCustomFees;
+
+        impl SignedExtensionEncoder for ChargeCustomFees {
+            fn encode_extra_to(&self, params: &SignedExtensionParams, dest: &mut Vec<u8>) {
+                (params.tip as u32).encode_to(dest)
+            }
+
+            fn encode_additional_signed_to(&self, _: &SignedExtensionParams, _: &mut Vec<u8>) {}
+        }
+
+        let metadata = metadata(vec![(
+            "ChargeCustomFees",
+            meta_type::<u32>(),
+            meta_type::<()>(),
+        )]);
+        let registry =
+            SignedExtensionRegistry::default().register("ChargeCustomFees", ChargeCustomFees);
+        let builder = MetadataExtrinsicParamsBuilder::with_registry(&metadata, &registry)
+            .unwrap()
+            .tip(7);
+        let params = MetadataExtrinsicParams::new(1, 2, 3, H256::zero(), builder);
+
+        assert_eq!(params.signed_extra().0, vec![7, 0, 0, 0]);
+    }
+
+    #[test]
+    fn charge_asset_tx_payment_with_u32_assets_is_encoded() {
+        let metadata = metadata(vec![(
+            "ChargeAssetTxPayment",
+            meta_type::<ChargeAssetTxPayment>(),
+            meta_type::<()>(),
+        )]);
+        let builder = MetadataExtrinsicParamsBuilder::new(&metadata)
+            .unwrap()
+            .tip(1)
+            .tip_asset(2);
+        let params = MetadataExtrinsicParams::new(1, 2, 3, H256::zero(), builder);
+
+        assert_eq!(params.signed_extra().0, vec![1 << 2, 1, 2, 0, 0, 0]);
+    }
+
+    #[test]
+    fn rejects_charge_asset_tx_payment_with_other_assets() {
+        let metadata = metadata(vec![(
+            "ChargeAssetTxPayment",
+            meta_type::<ChargeAssetConversionTxPayment>(),
+            meta_type::<()>(),
+        )]);
+
+        assert_eq!(
+            MetadataExtrinsicParamsBuilder::new(&metadata).err(),
+            Some(MetadataError::UnknownSignedExtension(
+                "ChargeAssetTxPayment".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,147 @@
+/*
+    Copyright 2021 Integritee AG and Supercomputing Systems AG
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+        http://www.apache.org/licenses/LICENSE-2.0
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Redefinitions of common events of substrate pallets, bound to their pallet and variant name
+//! with [`StaticEvent`](crate::events::StaticEvent). Like `AccountData`, they are redefined
+//! because pallets break `no_std` builds.
+
+pub mod balances {
+    use crate::events::StaticEvent;
+    use ac_primitives::{AccountId, Balance};
+    use codec::{Decode, Encode};
+
+    /// Transfer succeeded.
+    #[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+    pub struct Transfer {
+        pub from: AccountId,
+        pub to: AccountId,
+        pub amount: Balance,
+    }
+
+    impl StaticEvent for Transfer {
+        const PALLET: &'static str = "Balances";
+        const EVENT: &'static str = "Transfer";
+    }
+}
+
+pub mod system {
+    use crate::events::StaticEvent;
+    use codec::{Decode, Encode};
+    use frame_support::dispatch::DispatchInfo;
+    use sp_runtime::DispatchError;
+
+    /// An extrinsic completed successfully.
+    #[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+    pub struct ExtrinsicSuccess {
+        pub dispatch_info: DispatchInfo,
+    }
+
+    impl StaticEvent for ExtrinsicSuccess {
+        const PALLET: &'static str = "System";
+        const EVENT: &'static str = "ExtrinsicSuccess";
+    }
+
+    /// An extrinsic failed. Note that [`EventsDecoder`](crate::events::EventsDecoder) reports
+    /// this event as [`Raw::Error`](crate::events::Raw::Error) instead.
+    #[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+    pub struct ExtrinsicFailed {
+        pub dispatch_error: DispatchError,
+        pub dispatch_info: DispatchInfo,
+    }
+
+    impl StaticEvent for ExtrinsicFailed {
+        const PALLET: &'static str = "System";
+        const EVENT: &'static str = "ExtrinsicFailed";
+    }
+}
+
+pub mod contracts {
+    use crate::events::StaticEvent;
+    use ac_primitives::{AccountId, Hash};
+    use codec::{Decode, Encode};
+
+    #[cfg(not(feature = "std"))]
+    use alloc::vec::Vec;
+
+    /// Contract deployed by `deployer` at `contract`.
+    #[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+    pub struct Instantiated {
+        pub deployer: AccountId,
+        pub contract: AccountId,
+    }
+
+    impl StaticEvent for Instantiated {
+        const PALLET: &'static str = "Contracts";
+        const EVENT: &'static str = "Instantiated";
+    }
+
+    /// Contract has been removed, its remaining balance went to `beneficiary`.
+    #[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+    pub struct Terminated {
+        pub contract: AccountId,
+        pub beneficiary: AccountId,
+    }
+
+    impl StaticEvent for Terminated {
+        const PALLET: &'static str = "Contracts";
+        const EVENT: &'static str = "Terminated";
+    }
+
+    /// Code with the specified hash has been stored.
+    #[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+    pub struct CodeStored {
+        pub code_hash: Hash,
+    }
+
+    impl StaticEvent for CodeStored {
+        const PALLET: &'static str = "Contracts";
+        const EVENT: &'static str = "CodeStored";
+    }
+
+    /// A custom event emitted by the contract.
+    #[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+    pub struct ContractEmitted {
+        pub contract: AccountId,
+        /// Data supplied by the contract, its encoding is up to the contract.
+        pub data: Vec<u8>,
+    }
+
+    impl StaticEvent for ContractEmitted {
+        const PALLET: &'static str = "Contracts";
+        const EVENT: &'static str = "ContractEmitted";
+    }
+
+    /// A code with the specified hash was removed.
+    #[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+    pub struct CodeRemoved {
+        pub code_hash: Hash,
+    }
+
+    impl StaticEvent for CodeRemoved {
+        const PALLET: &'static str = "Contracts";
+        const EVENT: &'static str = "CodeRemoved";
+    }
+
+    /// A contract's code was updated.
+    #[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+    pub struct ContractCodeUpdated {
+        pub contract: AccountId,
+        pub new_code_hash: Hash,
+        pub old_code_hash: Hash,
+    }
+
+    impl StaticEvent for ContractCodeUpdated {
+        const PALLET: &'static str = "Contracts";
+        const EVENT: &'static str = "ContractCodeUpdated";
+    }
+}
@@ -17,7 +17,7 @@
 
 use crate::metadata::MetadataError;
 use codec::Encode;
-use frame_metadata::{StorageEntryMetadata, StorageEntryType, StorageHasher};
+use frame_metadata::v14::{StorageEntryMetadata, StorageEntryType, StorageHasher};
 use scale_info::form::PortableForm;
 use sp_core::storage::StorageKey;
 use sp_std::marker::PhantomData;
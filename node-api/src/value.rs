@@ -0,0 +1,1051 @@
+/*
+    Copyright 2021 Integritee AG and Supercomputing Systems AG
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+        http://www.apache.org/licenses/LICENSE-2.0
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Dynamic representation of SCALE encoded values, decoded with the help of the type
+//! information contained in the metadata. Allows to inspect storage, events and constants of any
+//! chain without knowing its runtime types at compile time.
+
+use crate::{error::Error, events::EventsDecodingError, metadata::MetadataError};
+use codec::{Compact, Decode, Encode};
+use scale_info::{
+    form::PortableForm, prelude::format, Field, PortableRegistry, TypeDef, TypeDefBitSequence,
+    TypeDefPrimitive,
+};
+
+#[cfg(feature = "std")]
+use serde::Serialize;
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+/// A dynamically decoded value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize))]
+pub enum Value {
+    /// Struct or tuple.
+    Composite(Composite),
+    /// Enum variant.
+    Variant(Variant),
+    /// Vector or fixed size array.
+    Sequence(Vec<Value>),
+    /// Bit sequence, bits in their logical order.
+    BitSequence(Vec<bool>),
+    /// Primitive, also used for the content of compact encoded values.
+    Primitive(Primitive),
+}
+
+/// Fields of a struct, a tuple or an enum variant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize))]
+pub enum Composite {
+    Named(Vec<(String, Value)>),
+    Unnamed(Vec<Value>),
+}
+
+/// A single enum variant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize))]
+pub struct Variant {
+    pub name: String,
+    pub index: u8,
+    pub fields: Composite,
+}
+
+/// Primitive value. Integers are widened to 128 bits, 256 bit integers are kept as their little
+/// endian bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize))]
+pub enum Primitive {
+    Bool(bool),
+    Char(char),
+    Str(String),
+    U128(u128),
+    I128(i128),
+    U256([u8; 32]),
+    I256([u8; 32]),
+}
+
+impl Value {
+    /// Decodes a value of type `type_id` from `input`.
+    pub fn decode_as_type(
+        types: &PortableRegistry,
+        type_id: u32,
+        input: &mut &[u8],
+    ) -> Result<Self, Error> {
+        let ty = types
+            .resolve(type_id)
+            .ok_or(MetadataError::TypeNotFound(type_id))?;
+
+        let value = match ty.type_def() {
+            TypeDef::Composite(composite) => {
+                Value::Composite(Composite::decode_fields(types, composite.fields(), input)?)
+            }
+            TypeDef::Variant(variant) => {
+                let variant_index = u8::decode(input)?;
+                let variant = variant
+                    .variants()
+                    .iter()
+                    .find(|v| v.index() == variant_index)
+                    .ok_or_else(|| Error::Other(format!("Variant {} not found", variant_index)))?;
+                Value::Variant(Variant {
+                    name: variant.name().to_string(),
+                    index: variant_index,
+                    fields: Composite::decode_fields(types, variant.fields(), input)?,
+                })
+            }
+            TypeDef::Sequence(seq) => {
+                let len = <Compact<u32>>::decode(input)?;
+                let values = (0..len.0)
+                    .map(|_| Self::decode_as_type(types, seq.type_param().id(), input))
+                    .collect::<Result<_, _>>()?;
+                Value::Sequence(values)
+            }
+            TypeDef::Array(arr) => {
+                let values = (0..arr.len())
+                    .map(|_| Self::decode_as_type(types, arr.type_param().id(), input))
+                    .collect::<Result<_, _>>()?;
+                Value::Sequence(values)
+            }
+            TypeDef::Tuple(tuple) => {
+                let values = tuple
+                    .fields()
+                    .iter()
+                    .map(|field| Self::decode_as_type(types, field.id(), input))
+                    .collect::<Result<_, _>>()?;
+                Value::Composite(Composite::Unnamed(values))
+            }
+            TypeDef::Primitive(primitive) => Value::Primitive(decode_primitive(primitive, input)?),
+            TypeDef::Compact(compact) => decode_compact(types, compact.type_param().id(), input)?,
+            TypeDef::BitSequence(bit_sequence) => {
+                Value::BitSequence(decode_bit_sequence(types, bit_sequence, input)?)
+            }
+        };
+        Ok(value)
+    }
+
+    /// Returns the field `name` of a struct or an enum variant.
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Composite(composite) => composite.field(name),
+            Value::Variant(variant) => variant.fields.field(name),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Primitive(Primitive::Bool(b)) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Primitive(Primitive::Str(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_u128(&self) -> Option<u128> {
+        match self {
+            Value::Primitive(Primitive::U128(n)) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_i128(&self) -> Option<i128> {
+        match self {
+            Value::Primitive(Primitive::I128(n)) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+impl Composite {
+    /// Decodes the `fields` of a struct or an enum variant. Fields are either all named or all
+    /// unnamed.
+    pub fn decode_fields(
+        types: &PortableRegistry,
+        fields: &[Field<PortableForm>],
+        input: &mut &[u8],
+    ) -> Result<Self, Error> {
+        if fields.iter().all(|field| field.name().is_some()) && !fields.is_empty() {
+            let values = fields
+                .iter()
+                .map(|field| {
+                    let value = Value::decode_as_type(types, field.ty().id(), input)?;
+                    Ok((field.name().cloned().unwrap_or_default(), value))
+                })
+                .collect::<Result<_, Error>>()?;
+            Ok(Composite::Named(values))
+        } else {
+            let values = fields
+                .iter()
+                .map(|field| Value::decode_as_type(types, field.ty().id(), input))
+                .collect::<Result<_, _>>()?;
+            Ok(Composite::Unnamed(values))
+        }
+    }
+
+    /// Returns the field `name`, if the fields are named.
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Composite::Named(fields) => fields.iter().find(|(n, _)| n == name).map(|(_, v)| v),
+            Composite::Unnamed(_) => None,
+        }
+    }
+
+    /// Returns the values of all fields in order, ignoring their names.
+    pub fn values(&self) -> Vec<&Value> {
+        match self {
+            Composite::Named(fields) => fields.iter().map(|(_, v)| v).collect(),
+            Composite::Unnamed(values) => values.iter().collect(),
+        }
+    }
+}
+
+fn decode_primitive(primitive: &TypeDefPrimitive, input: &mut &[u8]) -> Result<Primitive, Error> {
+    let primitive = match primitive {
+        TypeDefPrimitive::Bool => Primitive::Bool(bool::decode(input)?),
+        TypeDefPrimitive::Char => {
+            let c = u32::decode(input)?;
+            Primitive::Char(
+                char::from_u32(c).ok_or_else(|| Error::Other(format!("Invalid char {:#x}", c)))?,
+            )
+        }
+        TypeDefPrimitive::Str => Primitive::Str(String::decode(input)?),
+        TypeDefPrimitive::U8 => Primitive::U128(u8::decode(input)?.into()),
+        TypeDefPrimitive::U16 => Primitive::U128(u16::decode(input)?.into()),
+        TypeDefPrimitive::U32 => Primitive::U128(u32::decode(input)?.into()),
+        TypeDefPrimitive::U64 => Primitive::U128(u64::decode(input)?.into()),
+        TypeDefPrimitive::U128 => Primitive::U128(u128::decode(input)?),
+        TypeDefPrimitive::U256 => Primitive::U256(<[u8; 32]>::decode(input)?),
+        TypeDefPrimitive::I8 => Primitive::I128(i8::decode(input)?.into()),
+        TypeDefPrimitive::I16 => Primitive::I128(i16::decode(input)?.into()),
+        TypeDefPrimitive::I32 => Primitive::I128(i32::decode(input)?.into()),
+        TypeDefPrimitive::I64 => Primitive::I128(i64::decode(input)?.into()),
+        TypeDefPrimitive::I128 => Primitive::I128(i128::decode(input)?),
+        TypeDefPrimitive::I256 => Primitive::I256(<[u8; 32]>::decode(input)?),
+    };
+    Ok(primitive)
+}
+
+/// Decodes a compact encoded value of type `type_id`. Only unsigned integers and composites
+/// wrapping a single unsigned integer, e.g. `Perbill`, can be compact encoded.
+fn decode_compact(
+    types: &PortableRegistry,
+    type_id: u32,
+    input: &mut &[u8],
+) -> Result<Value, Error> {
+    let ty = types
+        .resolve(type_id)
+        .ok_or(MetadataError::TypeNotFound(type_id))?;
+    match ty.type_def() {
+        TypeDef::Primitive(primitive) => {
+            let n = match primitive {
+                TypeDefPrimitive::U8 => <Compact<u8>>::decode(input)?.0.into(),
+                TypeDefPrimitive::U16 => <Compact<u16>>::decode(input)?.0.into(),
+                TypeDefPrimitive::U32 => <Compact<u32>>::decode(input)?.0.into(),
+                TypeDefPrimitive::U64 => <Compact<u64>>::decode(input)?.0.into(),
+                TypeDefPrimitive::U128 => <Compact<u128>>::decode(input)?.0,
+                prim => {
+                    return Err(EventsDecodingError::InvalidCompactPrimitive(prim.clone()).into())
+                }
+            };
+            Ok(Value::Primitive(Primitive::U128(n)))
+        }
+        TypeDef::Composite(composite) => match composite.fields() {
+            [field] => {
+                let inner = decode_compact(types, field.ty().id(), input)?;
+                let fields = match field.name() {
+                    Some(name) => Composite::Named(vec![(name.clone(), inner)]),
+                    None => Composite::Unnamed(vec![inner]),
+                };
+                Ok(Value::Composite(fields))
+            }
+            _ => Err(EventsDecodingError::InvalidCompactType(
+                "Composite type must have a single field".into(),
+            )
+            .into()),
+        },
+        _ => Err(EventsDecodingError::InvalidCompactType(
+            "Compact type must be a primitive or a composite type".into(),
+        )
+        .into()),
+    }
+}
+
+/// Returns the number of bits per store element and whether the most significant bit of an
+/// element comes first, as given by the `Store` and `Order` types of a `BitVec<Store, Order>`.
+pub(crate) fn bit_sequence_format(
+    types: &PortableRegistry,
+    bit_sequence: &TypeDefBitSequence<PortableForm>,
+) -> Result<(usize, bool), Error> {
+    let store_id = bit_sequence.bit_store_type().id();
+    let store_bits = match types
+        .resolve(store_id)
+        .ok_or(MetadataError::TypeNotFound(store_id))?
+        .type_def()
+    {
+        TypeDef::Primitive(TypeDefPrimitive::U8) => 8,
+        TypeDef::Primitive(TypeDefPrimitive::U16) => 16,
+        TypeDef::Primitive(TypeDefPrimitive::U32) => 32,
+        TypeDef::Primitive(TypeDefPrimitive::U64) => 64,
+        _ => {
+            return Err(EventsDecodingError::InvalidBitSequenceType(
+                "Bit store type must be u8, u16, u32 or u64".into(),
+            )
+            .into())
+        }
+    };
+
+    let order_id = bit_sequence.bit_order_type().id();
+    let order = types
+        .resolve(order_id)
+        .ok_or(MetadataError::TypeNotFound(order_id))?
+        .path()
+        .ident();
+    let msb_first = match order.as_deref() {
+        Some("Lsb0") => false,
+        Some("Msb0") => true,
+        _ => {
+            return Err(EventsDecodingError::InvalidBitSequenceType(
+                "Bit order type must be Lsb0 or Msb0".into(),
+            )
+            .into())
+        }
+    };
+    Ok((store_bits, msb_first))
+}
+
+/// Returns the number of bytes of the store elements holding `len` bits. Fails if fewer bytes
+/// are `remaining` in the input, so that a malformed length does not cause a huge allocation.
+pub(crate) fn bit_sequence_bytes(
+    len: u32,
+    store_bits: usize,
+    remaining: usize,
+) -> Result<usize, Error> {
+    let len = len as usize;
+    let elements = len / store_bits + usize::from(len % store_bits != 0);
+    match elements.checked_mul(store_bits / 8) {
+        Some(bytes) if bytes <= remaining => Ok(bytes),
+        _ => Err(codec::Error::from("Bit sequence is longer than the input").into()),
+    }
+}
+
+/// Decodes a `BitVec<Store, Order>`: the number of bits as compact, followed by the store
+/// elements holding the bits.
+pub(crate) fn decode_bit_sequence(
+    types: &PortableRegistry,
+    bit_sequence: &TypeDefBitSequence<PortableForm>,
+    input: &mut &[u8],
+) -> Result<Vec<bool>, Error> {
+    let (store_bits, msb_first) = bit_sequence_format(types, bit_sequence)?;
+
+    let len = <Compact<u32>>::decode(input)?.0;
+    let elements = bit_sequence_bytes(len, store_bits, input.len())? / (store_bits / 8);
+    let len = len as usize;
+    let mut bits = Vec::with_capacity(len);
+    for _ in 0..elements {
+        let element = match store_bits {
+            8 => u8::decode(input)?.into(),
+            16 => u16::decode(input)?.into(),
+            32 => u32::decode(input)?.into(),
+            _ => u64::decode(input)?,
+        };
+        for i in 0..store_bits {
+            if bits.len() == len {
+                break;
+            }
+            let shift = if msb_first { store_bits - 1 - i } else { i };
+            bits.push((element >> shift) & 1 == 1);
+        }
+    }
+    Ok(bits)
+}
+
+/// Encodes `bits` the same way as a `BitVec<Store, Order>`.
+fn encode_bit_sequence(
+    types: &PortableRegistry,
+    bit_sequence: &TypeDefBitSequence<PortableForm>,
+    bits: &[bool],
+    output: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let (store_bits, msb_first) = bit_sequence_format(types, bit_sequence)?;
+
+    Compact(bits.len() as u32).encode_to(output);
+    for chunk in bits.chunks(store_bits) {
+        let element = chunk.iter().enumerate().fold(0u64, |element, (i, bit)| {
+            let shift = if msb_first { store_bits - 1 - i } else { i };
+            element | (u64::from(*bit) << shift)
+        });
+        match store_bits {
+            8 => (element as u8).encode_to(output),
+            16 => (element as u16).encode_to(output),
+            32 => (element as u32).encode_to(output),
+            _ => element.encode_to(output),
+        }
+    }
+    Ok(())
+}
+
+/// Reason why a [`Value`] could not be encoded as a given type. `field` is the path to the
+/// offending value, e.g. `dest.Id`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EncodeError {
+    /// The value has a different shape than the expected type.
+    TypeMismatch { field: String, expected: String },
+    /// A field of the type has not been given.
+    MissingField { field: String },
+    /// A field has been given, which the type does not have.
+    UnknownField { field: String },
+    /// Wrong number of fields or elements.
+    WrongLength {
+        field: String,
+        expected: usize,
+        actual: usize,
+    },
+    /// The integer does not fit into the expected type.
+    OutOfRange { field: String, expected: String },
+    /// The enum type has no variant with the given name.
+    VariantNotFound { field: String, variant: String },
+}
+
+impl Value {
+    /// Encodes the value as type `type_id`, checking that it matches the type.
+    ///
+    /// Composites with a single field, e.g. `AccountId32`, also accept the field's value
+    /// directly. Values of compact fields are given like their plain counterparts.
+    pub fn encode_as_type(
+        &self,
+        types: &PortableRegistry,
+        type_id: u32,
+        output: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        self.encode_at("", types, type_id, output)
+    }
+
+    fn encode_at(
+        &self,
+        field: &str,
+        types: &PortableRegistry,
+        type_id: u32,
+        output: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let ty = types
+            .resolve(type_id)
+            .ok_or(MetadataError::TypeNotFound(type_id))?;
+        let mismatch = || -> Error {
+            EncodeError::TypeMismatch {
+                field: field.to_string(),
+                expected: type_name(types, type_id),
+            }
+            .into()
+        };
+
+        match (ty.type_def(), self) {
+            (TypeDef::Composite(composite), Value::Composite(fields)) => {
+                fields.encode_fields(field, types, composite.fields(), output)
+            }
+            (TypeDef::Composite(composite), value) => match composite.fields() {
+                [inner] => value.encode_at(field, types, inner.ty().id(), output),
+                _ => Err(mismatch()),
+            },
+            (TypeDef::Variant(def), Value::Variant(variant)) => {
+                let found = def
+                    .variants()
+                    .iter()
+                    .find(|v| *v.name() == variant.name)
+                    .ok_or_else(|| EncodeError::VariantNotFound {
+                        field: field.to_string(),
+                        variant: variant.name.clone(),
+                    })?;
+                found.index().encode_to(output);
+                let path = join(field, &variant.name);
+                variant
+                    .fields
+                    .encode_fields(&path, types, found.fields(), output)
+            }
+            (TypeDef::Sequence(seq), Value::Sequence(values)) => {
+                Compact(values.len() as u32).encode_to(output);
+                encode_elements(field, types, seq.type_param().id(), values, output)
+            }
+            (TypeDef::Array(arr), Value::Sequence(values)) => {
+                check_length(field, arr.len() as usize, values.len())?;
+                encode_elements(field, types, arr.type_param().id(), values, output)
+            }
+            (TypeDef::Tuple(tuple), Value::Composite(Composite::Unnamed(values))) => {
+                check_length(field, tuple.fields().len(), values.len())?;
+                for (i, (ty, value)) in tuple.fields().iter().zip(values).enumerate() {
+                    value.encode_at(&join(field, &i.to_string()), types, ty.id(), output)?;
+                }
+                Ok(())
+            }
+            (TypeDef::Primitive(primitive), Value::Primitive(value)) => {
+                encode_primitive(field, primitive, value, output).unwrap_or_else(|| Err(mismatch()))
+            }
+            (TypeDef::Compact(compact), value) => {
+                encode_compact(field, types, compact.type_param().id(), value, output)
+            }
+            (TypeDef::BitSequence(bit_sequence), Value::BitSequence(bits)) => {
+                encode_bit_sequence(types, bit_sequence, bits, output)
+            }
+            _ => Err(mismatch()),
+        }
+    }
+}
+
+impl Composite {
+    /// Encodes the values for `fields` of a struct or an enum variant. Named values are encoded
+    /// in the order of the fields, unnamed ones must already be in that order.
+    pub fn encode_as_fields(
+        &self,
+        types: &PortableRegistry,
+        fields: &[Field<PortableForm>],
+        output: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        self.encode_fields("", types, fields, output)
+    }
+
+    fn encode_fields(
+        &self,
+        path: &str,
+        types: &PortableRegistry,
+        fields: &[Field<PortableForm>],
+        output: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        match self {
+            Composite::Named(values) => {
+                if let Some((name, _)) = values
+                    .iter()
+                    .find(|(name, _)| !fields.iter().any(|f| f.name() == Some(name)))
+                {
+                    return Err(EncodeError::UnknownField {
+                        field: join(path, name),
+                    }
+                    .into());
+                }
+                for field in fields {
+                    let name = field.name().cloned().unwrap_or_default();
+                    let value = self.field(&name).ok_or_else(|| EncodeError::MissingField {
+                        field: join(path, &name),
+                    })?;
+                    value.encode_at(&join(path, &name), types, field.ty().id(), output)?;
+                }
+                Ok(())
+            }
+            Composite::Unnamed(values) => {
+                check_length(path, fields.len(), values.len())?;
+                for (i, (field, value)) in fields.iter().zip(values).enumerate() {
+                    let name = field.name().cloned().unwrap_or_else(|| i.to_string());
+                    value.encode_at(&join(path, &name), types, field.ty().id(), output)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn encode_elements(
+    field: &str,
+    types: &PortableRegistry,
+    type_id: u32,
+    values: &[Value],
+    output: &mut Vec<u8>,
+) -> Result<(), Error> {
+    for (i, value) in values.iter().enumerate() {
+        value.encode_at(&join(field, &i.to_string()), types, type_id, output)?;
+    }
+    Ok(())
+}
+
+/// Returns `None` if the value is a different kind of primitive.
+fn encode_primitive(
+    field: &str,
+    primitive: &TypeDefPrimitive,
+    value: &Primitive,
+    output: &mut Vec<u8>,
+) -> Option<Result<(), Error>> {
+    fn int<T: TryFrom<u128> + TryFrom<i128> + Encode>(
+        field: &str,
+        expected: &str,
+        value: &Primitive,
+        output: &mut Vec<u8>,
+    ) -> Option<Result<(), Error>> {
+        let converted = match value {
+            Primitive::U128(n) => T::try_from(*n).ok(),
+            Primitive::I128(n) => T::try_from(*n).ok(),
+            _ => return None,
+        };
+        Some(match converted {
+            Some(n) => {
+                n.encode_to(output);
+                Ok(())
+            }
+            None => Err(EncodeError::OutOfRange {
+                field: field.to_string(),
+                expected: expected.to_string(),
+            }
+            .into()),
+        })
+    }
+
+    match (primitive, value) {
+        (TypeDefPrimitive::Bool, Primitive::Bool(b)) => b.encode_to(output),
+        (TypeDefPrimitive::Char, Primitive::Char(c)) => u32::from(*c).encode_to(output),
+        (TypeDefPrimitive::Str, Primitive::Str(s)) => s.encode_to(output),
+        (TypeDefPrimitive::U256, Primitive::U256(bytes))
+        | (TypeDefPrimitive::I256, Primitive::I256(bytes)) => bytes.encode_to(output),
+        (TypeDefPrimitive::U8, _) => return int::<u8>(field, "u8", value, output),
+        (TypeDefPrimitive::U16, _) => return int::<u16>(field, "u16", value, output),
+        (TypeDefPrimitive::U32, _) => return int::<u32>(field, "u32", value, output),
+        (TypeDefPrimitive::U64, _) => return int::<u64>(field, "u64", value, output),
+        (TypeDefPrimitive::U128, _) => return int::<u128>(field, "u128", value, output),
+        (TypeDefPrimitive::I8, _) => return int::<i8>(field, "i8", value, output),
+        (TypeDefPrimitive::I16, _) => return int::<i16>(field, "i16", value, output),
+        (TypeDefPrimitive::I32, _) => return int::<i32>(field, "i32", value, output),
+        (TypeDefPrimitive::I64, _) => return int::<i64>(field, "i64", value, output),
+        (TypeDefPrimitive::I128, _) => return int::<i128>(field, "i128", value, output),
+        _ => return None,
+    }
+    Some(Ok(()))
+}
+
+/// Compact encodes `value` as type `type_id`, the counterpart of [`decode_compact`].
+fn encode_compact(
+    field: &str,
+    types: &PortableRegistry,
+    type_id: u32,
+    value: &Value,
+    output: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let ty = types
+        .resolve(type_id)
+        .ok_or(MetadataError::TypeNotFound(type_id))?;
+    match ty.type_def() {
+        TypeDef::Primitive(primitive) => {
+            let mut plain = Vec::new();
+            value.encode_at(field, types, type_id, &mut plain)?;
+            let n = match primitive {
+                TypeDefPrimitive::U8 => Compact(u8::decode(&mut plain.as_slice())?).encode(),
+                TypeDefPrimitive::U16 => Compact(u16::decode(&mut plain.as_slice())?).encode(),
+                TypeDefPrimitive::U32 => Compact(u32::decode(&mut plain.as_slice())?).encode(),
+                TypeDefPrimitive::U64 => Compact(u64::decode(&mut plain.as_slice())?).encode(),
+                TypeDefPrimitive::U128 => Compact(u128::decode(&mut plain.as_slice())?).encode(),
+                prim => {
+                    return Err(EventsDecodingError::InvalidCompactPrimitive(prim.clone()).into())
+                }
+            };
+            output.extend(n);
+            Ok(())
+        }
+        TypeDef::Composite(composite) => match (composite.fields(), value) {
+            ([inner], Value::Composite(fields)) => match fields.values().as_slice() {
+                [value] => encode_compact(field, types, inner.ty().id(), value, output),
+                values => Err(EncodeError::WrongLength {
+                    field: field.to_string(),
+                    expected: 1,
+                    actual: values.len(),
+                }
+                .into()),
+            },
+            ([inner], value) => encode_compact(field, types, inner.ty().id(), value, output),
+            _ => Err(EventsDecodingError::InvalidCompactType(
+                "Composite type must have a single field".into(),
+            )
+            .into()),
+        },
+        _ => Err(EventsDecodingError::InvalidCompactType(
+            "Compact type must be a primitive or a composite type".into(),
+        )
+        .into()),
+    }
+}
+
+fn check_length(field: &str, expected: usize, actual: usize) -> Result<(), EncodeError> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(EncodeError::WrongLength {
+            field: field.to_string(),
+            expected,
+            actual,
+        })
+    }
+}
+
+fn join(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        field.to_string()
+    } else {
+        format!("{}.{}", path, field)
+    }
+}
+
+/// Human readable name of type `type_id` for error messages.
+fn type_name(types: &PortableRegistry, type_id: u32) -> String {
+    let ty = match types.resolve(type_id) {
+        Some(ty) => ty,
+        None => return format!("unknown type {}", type_id),
+    };
+    if let Some(ident) = ty.path().ident() {
+        return ident;
+    }
+    match ty.type_def() {
+        TypeDef::Composite(_) => "struct".into(),
+        TypeDef::Variant(_) => "enum".into(),
+        TypeDef::Sequence(seq) => format!("Vec<{}>", type_name(types, seq.type_param().id())),
+        TypeDef::Array(arr) => format!(
+            "[{}; {}]",
+            type_name(types, arr.type_param().id()),
+            arr.len()
+        ),
+        TypeDef::Tuple(tuple) => format!(
+            "({})",
+            tuple
+                .fields()
+                .iter()
+                .map(|ty| type_name(types, ty.id()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        TypeDef::Primitive(primitive) => format!("{:?}", primitive).to_lowercase(),
+        TypeDef::Compact(compact) => {
+            format!("Compact<{}>", type_name(types, compact.type_param().id()))
+        }
+        TypeDef::BitSequence(_) => "BitSequence".into(),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use bitvec::{
+        order::{Lsb0, Msb0},
+        vec::BitVec,
+    };
+    use scale_info::{MetaType, Registry, Type, TypeInfo};
+
+    /// The bytes of a `u256`, typed as the `u256` primitive.
+    #[derive(Encode)]
+    pub(crate) struct U256(pub [u8; 32]);
+
+    impl TypeInfo for U256 {
+        type Identity = Self;
+
+        fn type_info() -> Type {
+            TypeDefPrimitive::U256.into()
+        }
+    }
+
+    /// The bytes of an `i256`, typed as the `i256` primitive.
+    #[derive(Encode)]
+    pub(crate) struct I256(pub [u8; 32]);
+
+    impl TypeInfo for I256 {
+        type Identity = Self;
+
+        fn type_info() -> Type {
+            TypeDefPrimitive::I256.into()
+        }
+    }
+
+    #[derive(Encode, TypeInfo)]
+    struct Exotic {
+        initial: char,
+        big: U256,
+        signed: I256,
+        lsb0_u8: BitVec<u8, Lsb0>,
+        msb0_u8: BitVec<u8, Msb0>,
+        lsb0_u32: BitVec<u32, Lsb0>,
+        msb0_u32: BitVec<u32, Msb0>,
+    }
+
+    fn bits(len: usize) -> Vec<bool> {
+        (0..len).map(|i| i % 3 == 0 || i % 7 == 0).collect()
+    }
+
+    #[derive(Encode, TypeInfo)]
+    struct Account {
+        nonce: u32,
+        #[codec(compact)]
+        free: u128,
+        flags: (bool, char),
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum Event {
+        #[codec(index = 3)]
+        Transfer {
+            from: [u8; 2],
+            amount: u64,
+        },
+        Remarked(Vec<i16>),
+    }
+
+    fn registry_with<T: TypeInfo + 'static>() -> (PortableRegistry, u32) {
+        let mut registry = Registry::new();
+        let id = registry.register_type(&MetaType::new::<T>()).id();
+        (registry.into(), id)
+    }
+
+    #[test]
+    fn decodes_struct_with_named_fields() {
+        let (types, id) = registry_with::<Account>();
+        let encoded = Account {
+            nonce: 7,
+            free: 1_000,
+            flags: (true, 'x'),
+        }
+        .encode();
+
+        let value = Value::decode_as_type(&types, id, &mut encoded.as_slice()).unwrap();
+        assert_eq!(value.field("nonce").unwrap().as_u128(), Some(7));
+        assert_eq!(value.field("free").unwrap().as_u128(), Some(1_000));
+        assert_eq!(
+            value.field("flags"),
+            Some(&Value::Composite(Composite::Unnamed(vec![
+                Value::Primitive(Primitive::Bool(true)),
+                Value::Primitive(Primitive::Char('x')),
+            ])))
+        );
+    }
+
+    #[test]
+    fn decodes_variant_by_its_index() {
+        let (types, id) = registry_with::<Event>();
+        let encoded = Event::Transfer {
+            from: [1, 2],
+            amount: 42,
+        }
+        .encode();
+
+        let value = Value::decode_as_type(&types, id, &mut encoded.as_slice()).unwrap();
+        match &value {
+            Value::Variant(variant) => {
+                assert_eq!(variant.name, "Transfer");
+                assert_eq!(variant.index, 3);
+            }
+            other => panic!("expected variant, got {:?}", other),
+        }
+        assert_eq!(value.field("amount").unwrap().as_u128(), Some(42));
+
+        let encoded = Event::Remarked(vec![-1, 2]).encode();
+        let value = Value::decode_as_type(&types, id, &mut encoded.as_slice()).unwrap();
+        assert_eq!(
+            value,
+            Value::Variant(Variant {
+                name: "Remarked".into(),
+                index: 1,
+                fields: Composite::Unnamed(vec![Value::Sequence(vec![
+                    Value::Primitive(Primitive::I128(-1)),
+                    Value::Primitive(Primitive::I128(2)),
+                ])]),
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_variant_is_an_error() {
+        let (types, id) = registry_with::<Event>();
+        assert!(Value::decode_as_type(&types, id, &mut [9u8].as_slice()).is_err());
+    }
+
+    #[derive(Encode, TypeInfo)]
+    #[allow(non_camel_case_types)]
+    enum Call {
+        transfer {
+            dest: Event,
+            #[codec(compact)]
+            value: u128,
+        },
+    }
+
+    fn transfer_fields() -> (PortableRegistry, Vec<Field<PortableForm>>) {
+        let (types, id) = registry_with::<Call>();
+        let fields = match types.resolve(id).unwrap().type_def() {
+            TypeDef::Variant(variant) => variant.variants()[0].fields().to_vec(),
+            _ => unreachable!(),
+        };
+        (types, fields)
+    }
+
+    fn transfer_args(amount: u128) -> Composite {
+        Composite::Named(vec![
+            ("value".into(), Value::Primitive(Primitive::U128(1_000))),
+            (
+                "dest".into(),
+                Value::Variant(Variant {
+                    name: "Transfer".into(),
+                    index: 0,
+                    fields: Composite::Named(vec![
+                        (
+                            "from".into(),
+                            Value::Sequence(vec![
+                                Value::Primitive(Primitive::U128(1)),
+                                Value::Primitive(Primitive::U128(2)),
+                            ]),
+                        ),
+                        ("amount".into(), Value::Primitive(Primitive::U128(amount))),
+                    ]),
+                }),
+            ),
+        ])
+    }
+
+    #[test]
+    fn encodes_named_fields_in_field_order_with_compact() {
+        let (types, fields) = transfer_fields();
+        let mut encoded = Vec::new();
+        transfer_args(42)
+            .encode_as_fields(&types, &fields, &mut encoded)
+            .unwrap();
+
+        let expected = Call::transfer {
+            dest: Event::Transfer {
+                from: [1, 2],
+                amount: 42,
+            },
+            value: 1_000,
+        }
+        .encode();
+        // without the call index
+        assert_eq!(encoded, expected[1..]);
+    }
+
+    #[test]
+    fn encoding_reports_mismatching_values() {
+        let (types, fields) = transfer_fields();
+
+        let err = transfer_args(u128::MAX)
+            .encode_as_fields(&types, &fields, &mut Vec::new())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::ValueEncoding(EncodeError::OutOfRange { field, expected })
+                if field == "dest.Transfer.amount" && expected == "u64"
+        ));
+
+        let args = Composite::Named(vec![("value".into(), Value::Primitive(Primitive::U128(1)))]);
+        let err = args
+            .encode_as_fields(&types, &fields, &mut Vec::new())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::ValueEncoding(EncodeError::MissingField { field }) if field == "dest"
+        ));
+
+        let args = Composite::Unnamed(vec![
+            Value::Primitive(Primitive::Bool(true)),
+            Value::Primitive(Primitive::U128(1)),
+        ]);
+        let err = args
+            .encode_as_fields(&types, &fields, &mut Vec::new())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::ValueEncoding(EncodeError::TypeMismatch { field, .. }) if field == "dest"
+        ));
+    }
+
+    #[test]
+    fn decoded_value_encodes_to_the_same_bytes() {
+        let (types, id) = registry_with::<Account>();
+        let encoded = Account {
+            nonce: 7,
+            free: 1_000,
+            flags: (true, 'x'),
+        }
+        .encode();
+
+        let value = Value::decode_as_type(&types, id, &mut encoded.as_slice()).unwrap();
+        let mut reencoded = Vec::new();
+        value.encode_as_type(&types, id, &mut reencoded).unwrap();
+        assert_eq!(reencoded, encoded);
+    }
+
+    #[test]
+    fn chars_256_bit_integers_and_bit_sequences_round_trip() {
+        let mut signed = [0xff; 32];
+        signed[0] = 0xfe;
+        let encoded = Exotic {
+            initial: '€',
+            big: U256([3; 32]),
+            signed: I256(signed),
+            lsb0_u8: bits(11).into_iter().collect(),
+            msb0_u8: bits(11).into_iter().collect(),
+            lsb0_u32: bits(45).into_iter().collect(),
+            msb0_u32: bits(45).into_iter().collect(),
+        }
+        .encode();
+        let (types, id) = registry_with::<Exotic>();
+
+        let value = Value::decode_as_type(&types, id, &mut encoded.as_slice()).unwrap();
+        assert_eq!(
+            value.field("initial").unwrap(),
+            &Value::Primitive(Primitive::Char('€'))
+        );
+        assert_eq!(
+            value.field("big").unwrap(),
+            &Value::Primitive(Primitive::U256([3; 32]))
+        );
+        assert_eq!(
+            value.field("signed").unwrap(),
+            &Value::Primitive(Primitive::I256(signed))
+        );
+        for (field, len) in [
+            ("lsb0_u8", 11),
+            ("msb0_u8", 11),
+            ("lsb0_u32", 45),
+            ("msb0_u32", 45),
+        ] {
+            assert_eq!(
+                value.field(field).unwrap(),
+                &Value::BitSequence(bits(len)),
+                "{}",
+                field
+            );
+        }
+
+        let mut reencoded = Vec::new();
+        value.encode_as_type(&types, id, &mut reencoded).unwrap();
+        assert_eq!(reencoded, encoded);
+    }
+
+    #[test]
+    fn bit_sequence_longer_than_the_input_is_an_error() {
+        let (types, id) = registry_with::<BitVec<u32, Lsb0>>();
+        let mut encoded = Compact(u32::MAX).encode();
+        encoded.extend([0xff; 4]);
+
+        assert!(matches!(
+            Value::decode_as_type(&types, id, &mut encoded.as_slice()),
+            Err(Error::Codec(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_char_is_a_decoding_error() {
+        let (types, id) = registry_with::<char>();
+        let encoded = 0xd800u32.encode();
+
+        assert!(matches!(
+            Value::decode_as_type(&types, id, &mut encoded.as_slice()),
+            Err(Error::Other(_))
+        ));
+    }
+}
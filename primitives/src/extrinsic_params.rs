@@ -32,10 +32,10 @@ pub type SubstrateDefaultAdditionalSigned = ((), u32, u32, H256, H256, (), (), (
 pub trait ExtrinsicParams {
     /// These parameters can be provided to the constructor along with
     /// some default parameters in order to help construct your [`ExtrinsicParams`] object.
-    type OtherParams: Default + Clone;
+    type OtherParams: Clone;
 
     /// SignedExtra format of the node.
-    type SignedExtra: Copy + Encode;
+    type SignedExtra: Clone + Encode;
 
     /// Additional Signed format of the node
     type AdditionalSigned: Encode;
@@ -57,6 +57,20 @@ pub trait ExtrinsicParams {
     /// taken into account when signing it, meaning the client and node must agree
     /// on their values.
     fn additional_signed(&self) -> Self::AdditionalSigned;
+
+    /// The [`Self::OtherParams`] used if none have been given. `None` if they can not be built
+    /// without knowing the node, e.g. because they depend on its metadata.
+    fn default_other_params() -> Option<Self::OtherParams>;
+
+    /// Sets the [`Era`] and the block hash it starts at on `other_params`. Returns `None` if
+    /// these parameters do not support mortal eras.
+    fn with_era(
+        _other_params: Self::OtherParams,
+        _era: Era,
+        _checkpoint: H256,
+    ) -> Option<Self::OtherParams> {
+        None
+    }
 }
 
 /// A struct representing the signed extra and additional parameters required
@@ -164,6 +178,18 @@ where
         Self::SignedExtra::new(self.era, self.nonce, self.tip)
     }
 
+    fn default_other_params() -> Option<Self::OtherParams> {
+        Some(Self::OtherParams::default())
+    }
+
+    fn with_era(
+        other_params: Self::OtherParams,
+        era: Era,
+        checkpoint: H256,
+    ) -> Option<Self::OtherParams> {
+        Some(other_params.era(era, checkpoint))
+    }
+
     fn additional_signed(&self) -> Self::AdditionalSigned {
         (
             (),
@@ -4,9 +4,11 @@ use codec::{Decode, Encode};
 
 pub use extrinsic_params::*;
 pub use extrinsics::*;
+pub use signer::*;
 
 pub mod extrinsic_params;
 pub mod extrinsics;
+pub mod signer;
 
 /// The block number type used in this runtime.
 pub type BlockNumber = u64;
@@ -0,0 +1,93 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+//! Signer of extrinsics.
+
+use crate::{AccountId, GenericAddress};
+#[cfg(feature = "full_crypto")]
+use sp_core::Pair;
+use sp_runtime::MultiSignature;
+#[cfg(feature = "full_crypto")]
+use sp_runtime::{traits::IdentifyAccount, MultiSigner};
+use sp_std::string::String;
+
+/// Error of a [`Signer`] that could not sign a payload, e.g. because its key is no longer
+/// available.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignError(pub String);
+
+/// Signs extrinsics on behalf of an account.
+///
+/// With the `full_crypto` feature, it is implemented for every `Pair` of a crypto
+/// `MultiSignature` supports. Implement it for keys that are not available as a `Pair`, e.g.
+/// keys held by a hardware security module or a remote signing service.
+pub trait Signer {
+    /// Account the extrinsics are signed for.
+    fn account_id(&self) -> AccountId;
+
+    /// Address of the account, as put into the extrinsic.
+    fn address(&self) -> GenericAddress {
+        GenericAddress::from(self.account_id())
+    }
+
+    /// Signs the payload of an extrinsic.
+    fn sign(&self, payload: &[u8]) -> Result<MultiSignature, SignError>;
+}
+
+#[cfg(feature = "full_crypto")]
+impl<P> Signer for P
+where
+    P: Pair,
+    MultiSignature: From<P::Signature>,
+    MultiSigner: From<P::Public>,
+{
+    fn account_id(&self) -> AccountId {
+        MultiSigner::from(self.public()).into_account()
+    }
+
+    fn sign(&self, payload: &[u8]) -> Result<MultiSignature, SignError> {
+        Ok(Pair::sign(self, payload).into())
+    }
+}
+
+#[cfg(all(test, feature = "full_crypto"))]
+mod tests {
+    use super::*;
+    use sp_core::{ecdsa, sr25519};
+    use sp_runtime::traits::Verify;
+
+    #[test]
+    fn pair_signatures_verify_against_the_account() {
+        let (pair, _) = sr25519::Pair::generate();
+        let signature = Signer::sign(&pair, b"payload").unwrap();
+
+        assert_eq!(pair.account_id(), AccountId::from(pair.public()));
+        assert!(signature.verify(&b"payload"[..], &pair.account_id()));
+    }
+
+    #[test]
+    fn ecdsa_account_is_the_hash_of_the_public_key() {
+        let (pair, _) = ecdsa::Pair::generate();
+        let signature = Signer::sign(&pair, b"payload").unwrap();
+
+        assert_eq!(
+            pair.address(),
+            GenericAddress::from(MultiSigner::from(pair.public()).into_account())
+        );
+        assert!(signature.verify(&b"payload"[..], &pair.account_id()));
+    }
+}
@@ -19,10 +19,10 @@
 
 use crate::std::{Api, RpcClient};
 use ac_compose_macros::compose_extrinsic;
-use ac_primitives::{Balance, CallIndex, ExtrinsicParams, GenericAddress, UncheckedExtrinsicV4};
+use ac_primitives::{
+    Balance, CallIndex, ExtrinsicParams, GenericAddress, Signer, UncheckedExtrinsicV4,
+};
 use codec::Compact;
-use sp_core::crypto::Pair;
-use sp_runtime::{MultiSignature, MultiSigner};
 
 pub const BALANCES_MODULE: &str = "Balances";
 pub const BALANCES_TRANSFER: &str = "transfer";
@@ -42,9 +42,7 @@ pub type BalanceSetBalanceXt<SignedExtra> = UncheckedExtrinsicV4<BalanceSetBalan
 #[cfg(feature = "std")]
 impl<P, Client, Params> Api<P, Client, Params>
 where
-    P: Pair,
-    MultiSignature: From<P::Signature>,
-    MultiSigner: From<P::Public>,
+    P: Signer,
     Client: RpcClient,
     Params: ExtrinsicParams,
 {
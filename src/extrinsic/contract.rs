@@ -20,11 +20,11 @@
 
 use crate::std::{Api, RpcClient};
 use ac_compose_macros::compose_extrinsic;
-use ac_primitives::{Balance, CallIndex, ExtrinsicParams, GenericAddress, UncheckedExtrinsicV4};
+use ac_primitives::{
+    Balance, CallIndex, ExtrinsicParams, GenericAddress, Signer, UncheckedExtrinsicV4,
+};
 use codec::Compact;
-use sp_core::crypto::Pair;
 use sp_core::H256 as Hash;
-use sp_runtime::{MultiSignature, MultiSigner};
 use sp_std::prelude::*;
 
 pub const CONTRACTS_MODULE: &str = "Contracts";
@@ -58,9 +58,7 @@ pub type ContractCallXt<SignedExtra> = UncheckedExtrinsicV4<ContractCallFn, Sign
 #[cfg(feature = "std")]
 impl<P, Client, Params> Api<P, Client, Params>
 where
-    P: Pair,
-    MultiSignature: From<P::Signature>,
-    MultiSigner: From<P::Public>,
+    P: Signer,
     Client: RpcClient,
     Params: ExtrinsicParams,
 {
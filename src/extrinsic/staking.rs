@@ -19,10 +19,8 @@
 
 use crate::{Api, RpcClient};
 use ac_compose_macros::compose_extrinsic;
-use ac_primitives::{Balance, CallIndex, GenericAddress, UncheckedExtrinsicV4};
+use ac_primitives::{Balance, CallIndex, GenericAddress, Signer, UncheckedExtrinsicV4};
 use codec::Compact;
-use sp_core::Pair;
-use sp_runtime::{MultiSignature, MultiSigner};
 
 pub use staking::RewardDestination;
 
@@ -62,9 +60,7 @@ pub type StakingSetControllerXt = UncheckedExtrinsicV4<StakingSetControllerFn>;
 // https://polkadot.js.org/docs/substrate/extrinsics#staking
 impl<P, Client> Api<P, Client>
 where
-    P: Pair,
-    MultiSignature: From<P::Signature>,
-    MultiSigner: From<P::Public>,
+    P: Signer,
     Client: RpcClient,
 {
     /// Bond `value` amount to `controller`
@@ -35,4 +35,6 @@ pub use ac_node_api::*;
 #[cfg(feature = "std")]
 pub use ac_compose_macros::compose_extrinsic;
 
-pub use ac_compose_macros::{compose_call, compose_extrinsic_offline};
+pub use ac_compose_macros::{
+    compose_call, compose_extrinsic_offline, try_compose_extrinsic_offline,
+};
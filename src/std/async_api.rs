@@ -0,0 +1,605 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+//! Async counterpart of [`Api`](crate::Api), available with the `async-client` feature.
+//!
+//! It uses the same request builders in [`json_req`] and the same [`Metadata`] handling as the
+//! blocking api, only the rpc-backend is async.
+
+use std::sync::mpsc::RecvError;
+
+use async_trait::async_trait;
+use codec::{Decode, Encode};
+use futures::future;
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use log::{debug, info};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use sp_core::storage::StorageKey;
+use sp_core::H256 as Hash;
+use sp_runtime::generic::SignedBlock;
+use sp_runtime::traits::{Block, Header};
+use sp_runtime::AccountId32 as AccountId;
+use sp_version::RuntimeVersion;
+
+use ac_node_api::events::{EventsDecoder, Raw};
+use ac_node_api::metadata::{Metadata, SUPPORTED_METADATA_VERSIONS};
+use ac_node_api::Phase;
+use ac_primitives::{AccountInfo, ExtrinsicParams, Signer};
+use metadata::RuntimeMetadataPrefixed;
+
+use crate::std::rpc::helpers::decode_notification;
+use crate::std::rpc::{json_req, DecodeFn};
+use crate::std::{ApiClientError, ApiResult, FromHexString, TransactionStatus, XtStatus};
+use crate::utils;
+
+#[async_trait]
+pub trait AsyncRpcClient {
+    /// Sends a RPC request that returns a String
+    async fn get_request(&self, jsonreq: Value) -> ApiResult<String>;
+
+    /// Sends the extrinsic and waits until `exit_on` is reached. Returns the hash of the
+    /// extrinsic for [`XtStatus::SubmitOnly`] and the block hash for [`XtStatus::InBlock`] and
+    /// [`XtStatus::Finalized`].
+    async fn send_extrinsic(
+        &self,
+        xthex_prefixed: String,
+        exit_on: XtStatus,
+    ) -> ApiResult<Option<Hash>>;
+}
+
+#[async_trait]
+pub trait AsyncSubscriber {
+    /// Opens the subscription requested by `jsonreq`. Its notifications are decoded with
+    /// `decode`, see [`decode_notifications`].
+    ///
+    /// The subscription is closed on the node once the stream is dropped.
+    async fn subscribe<T: Send + 'static>(
+        &self,
+        jsonreq: Value,
+        decode: DecodeFn<T>,
+    ) -> ApiResult<BoxStream<'static, ApiResult<T>>>;
+}
+
+/// Decodes the raw json messages of a subscription with `decode`, for implementors of
+/// [`AsyncSubscriber`].
+///
+/// Behaves like the blocking `Subscription`: the response confirming the subscription and
+/// notifications without an item are skipped. Errors are yielded as items. Rpc errors and the
+/// end of `notifications`, i.e. a lost connection, end the stream, decoding errors do not.
+pub fn decode_notifications<T: Send + 'static>(
+    notifications: impl Stream<Item = String> + Send + 'static,
+    decode: DecodeFn<T>,
+) -> BoxStream<'static, ApiResult<T>> {
+    notifications
+        .map(Some)
+        .chain(stream::once(future::ready(None)))
+        .scan(false, move |terminated, msg| {
+            if *terminated {
+                return future::ready(None);
+            }
+            let item = match msg {
+                Some(msg) => match decode_notification(&msg, &decode) {
+                    Ok(item) => item.map(Ok),
+                    Err(e) => {
+                        *terminated = matches!(e, ApiClientError::RpcClient(_));
+                        Some(Err(e))
+                    }
+                },
+                None => {
+                    *terminated = true;
+                    Some(Err(ApiClientError::Disconnected(RecvError)))
+                }
+            };
+            future::ready(Some(item))
+        })
+        .filter_map(future::ready)
+        .boxed()
+}
+
+/// Async Api to talk with substrate-nodes
+///
+/// It is generic over the `AsyncRpcClient` trait, so you can use any async rpc-backend you like.
+#[derive(Clone)]
+pub struct AsyncApi<P, Client, Params>
+where
+    Client: AsyncRpcClient,
+    Params: ExtrinsicParams,
+{
+    pub signer: Option<P>,
+    pub genesis_hash: Hash,
+    pub metadata: Metadata,
+    pub runtime_version: RuntimeVersion,
+    client: Client,
+    pub extrinsic_params_builder: Option<Params::OtherParams>,
+}
+
+impl<P, Client, Params> AsyncApi<P, Client, Params>
+where
+    P: Signer,
+    Client: AsyncRpcClient,
+    Params: ExtrinsicParams,
+{
+    pub fn signer_account(&self) -> Option<AccountId> {
+        Some(self.signer.as_ref()?.account_id())
+    }
+
+    pub async fn get_nonce(&self) -> ApiResult<u32> {
+        let account = self.signer_account().ok_or(ApiClientError::NoSigner)?;
+        self.get_account_info(&account)
+            .await
+            .map(|acc_opt| acc_opt.map_or_else(|| 0, |acc| acc.nonce))
+    }
+}
+
+impl<P, Client, Params> AsyncApi<P, Client, Params>
+where
+    Client: AsyncRpcClient,
+    Params: ExtrinsicParams,
+{
+    pub async fn new(client: Client) -> ApiResult<Self> {
+        let genesis_hash = Self::_get_genesis_hash(&client).await?;
+        info!("Got genesis hash: {:?}", genesis_hash);
+
+        let metadata = Self::_get_metadata(&client)
+            .await
+            .map(Metadata::try_from)??;
+        debug!("Metadata: {:?}", metadata);
+
+        let runtime_version = Self::_get_runtime_version(&client).await?;
+        info!("Runtime Version: {:?}", runtime_version);
+
+        Ok(Self {
+            signer: None,
+            genesis_hash,
+            metadata,
+            runtime_version,
+            client,
+            extrinsic_params_builder: None,
+        })
+    }
+
+    #[must_use]
+    pub fn set_signer(mut self, signer: P) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    pub fn set_extrinsic_params_builder(mut self, extrinsic_params: Params::OtherParams) -> Self {
+        self.extrinsic_params_builder = Some(extrinsic_params);
+        self
+    }
+
+    async fn _get_genesis_hash(client: &Client) -> ApiResult<Hash> {
+        let jsonreq = json_req::chain_get_genesis_hash();
+        let genesis = Self::_get_request(client, jsonreq).await?;
+
+        match genesis {
+            Some(g) => Hash::from_hex(g).map_err(|e| e.into()),
+            None => Err(ApiClientError::Genesis),
+        }
+    }
+
+    async fn _get_runtime_version(client: &Client) -> ApiResult<RuntimeVersion> {
+        let jsonreq = json_req::state_get_runtime_version();
+        let version = Self::_get_request(client, jsonreq).await?;
+
+        match version {
+            Some(v) => serde_json::from_str(&v).map_err(|e| e.into()),
+            None => Err(ApiClientError::RuntimeVersion),
+        }
+    }
+
+    /// Fetches the newest metadata version both the node and [`Metadata`] support. Falls back
+    /// to `state_getMetadata` for runtimes without the `Metadata_metadata_at_version` api.
+    async fn _get_metadata(client: &Client) -> ApiResult<RuntimeMetadataPrefixed> {
+        let versions = Self::_get_metadata_versions(client)
+            .await
+            .unwrap_or_else(|e| {
+                debug!("Could not fetch the metadata versions: {:?}", e);
+                None
+            });
+        let version = versions.and_then(|versions| {
+            SUPPORTED_METADATA_VERSIONS
+                .into_iter()
+                .find(|version| versions.contains(version))
+        });
+        if let Some(version) = version {
+            if let Some(metadata) = Self::_get_metadata_at_version(client, version).await? {
+                info!("Got metadata version {}", version);
+                return Ok(metadata);
+            }
+        }
+
+        let jsonreq = json_req::state_get_metadata();
+        let meta = Self::_get_request(client, jsonreq)
+            .await?
+            .ok_or(ApiClientError::MetadataFetch)?;
+
+        let metadata = Vec::from_hex(meta)?;
+        RuntimeMetadataPrefixed::decode(&mut metadata.as_slice()).map_err(|e| e.into())
+    }
+
+    async fn _get_metadata_versions(client: &Client) -> ApiResult<Option<Vec<u32>>> {
+        let jsonreq = json_req::state_call("Metadata_metadata_versions", &[], None);
+        match Self::_get_request(client, jsonreq).await? {
+            Some(versions) => Ok(Some(Decode::decode(
+                &mut Vec::from_hex(versions)?.as_slice(),
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn _get_metadata_at_version(
+        client: &Client,
+        version: u32,
+    ) -> ApiResult<Option<RuntimeMetadataPrefixed>> {
+        let jsonreq = json_req::state_call("Metadata_metadata_at_version", &version.encode(), None);
+        let opaque: Option<Vec<u8>> = match Self::_get_request(client, jsonreq).await? {
+            Some(opaque) => Decode::decode(&mut Vec::from_hex(opaque)?.as_slice())?,
+            None => None,
+        };
+        opaque
+            .map(|metadata| RuntimeMetadataPrefixed::decode(&mut metadata.as_slice()))
+            .transpose()
+            .map_err(|e| e.into())
+    }
+
+    // low level access
+    async fn _get_request(client: &Client, jsonreq: Value) -> ApiResult<Option<String>> {
+        let str = client.get_request(jsonreq).await?;
+
+        match &str[..] {
+            "null" => Ok(None),
+            _ => Ok(Some(str)),
+        }
+    }
+
+    /// Builds the extrinsic params with the builder set in the api, or the default one of
+    /// `Params`. Fails with [`ApiClientError::NoExtrinsicParamsBuilder`] if neither exists.
+    pub fn extrinsic_params(&self, nonce: u32) -> ApiResult<Params> {
+        let extrinsic_params_builder = self
+            .extrinsic_params_builder
+            .clone()
+            .or_else(Params::default_other_params)
+            .ok_or(ApiClientError::NoExtrinsicParamsBuilder)?;
+        Ok(<Params as ExtrinsicParams>::new(
+            self.runtime_version.spec_version,
+            self.runtime_version.transaction_version,
+            nonce,
+            self.genesis_hash,
+            extrinsic_params_builder,
+        ))
+    }
+
+    pub async fn get_metadata(&self) -> ApiResult<RuntimeMetadataPrefixed> {
+        Self::_get_metadata(&self.client).await
+    }
+
+    pub async fn get_spec_version(&self) -> ApiResult<u32> {
+        Self::_get_runtime_version(&self.client)
+            .await
+            .map(|v| v.spec_version)
+    }
+
+    pub async fn get_genesis_hash(&self) -> ApiResult<Hash> {
+        Self::_get_genesis_hash(&self.client).await
+    }
+
+    pub async fn get_request(&self, jsonreq: Value) -> ApiResult<Option<String>> {
+        Self::_get_request(&self.client, jsonreq).await
+    }
+
+    pub async fn get_account_info(&self, address: &AccountId) -> ApiResult<Option<AccountInfo>> {
+        let storagekey: StorageKey =
+            self.metadata
+                .storage_map_key::<AccountId>("System", "Account", address.clone())?;
+
+        info!("storage key is: 0x{}", hex::encode(&storagekey));
+        self.get_storage_by_key_hash(storagekey, None).await
+    }
+
+    pub async fn get_finalized_head(&self) -> ApiResult<Option<Hash>> {
+        let h = self
+            .get_request(json_req::chain_get_finalized_head())
+            .await?;
+        match h {
+            Some(hash) => Ok(Some(Hash::from_hex(hash)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_header<H>(&self, hash: Option<Hash>) -> ApiResult<Option<H>>
+    where
+        H: Header + DeserializeOwned,
+    {
+        let h = self.get_request(json_req::chain_get_header(hash)).await?;
+        match h {
+            Some(hash) => Ok(Some(serde_json::from_str(&hash)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_block_hash(&self, number: Option<u32>) -> ApiResult<Option<Hash>> {
+        let h = self
+            .get_request(json_req::chain_get_block_hash(number))
+            .await?;
+        match h {
+            Some(hash) => Ok(Some(Hash::from_hex(hash)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_block<B>(&self, hash: Option<Hash>) -> ApiResult<Option<B>>
+    where
+        B: Block + DeserializeOwned,
+    {
+        self.get_signed_block(hash)
+            .await
+            .map(|sb_opt| sb_opt.map(|sb| sb.block))
+    }
+
+    pub async fn get_block_by_num<B>(&self, number: Option<u32>) -> ApiResult<Option<B>>
+    where
+        B: Block + DeserializeOwned,
+    {
+        self.get_signed_block_by_num(number)
+            .await
+            .map(|sb_opt| sb_opt.map(|sb| sb.block))
+    }
+
+    /// A signed block is a block with Justification ,i.e., a Grandpa finality proof.
+    /// See [`Api::get_signed_block`](crate::Api::get_signed_block).
+    pub async fn get_signed_block<B>(&self, hash: Option<Hash>) -> ApiResult<Option<SignedBlock<B>>>
+    where
+        B: Block + DeserializeOwned,
+    {
+        let b = self.get_request(json_req::chain_get_block(hash)).await?;
+        match b {
+            Some(block) => Ok(Some(serde_json::from_str(&block)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_signed_block_by_num<B>(
+        &self,
+        number: Option<u32>,
+    ) -> ApiResult<Option<SignedBlock<B>>>
+    where
+        B: Block + DeserializeOwned,
+    {
+        let hash = self.get_block_hash(number).await?;
+        self.get_signed_block(hash).await
+    }
+
+    pub async fn get_storage_value<V: Decode>(
+        &self,
+        storage_prefix: &'static str,
+        storage_key_name: &'static str,
+        at_block: Option<Hash>,
+    ) -> ApiResult<Option<V>> {
+        let storagekey = self
+            .metadata
+            .storage_value_key(storage_prefix, storage_key_name)?;
+        info!("storage key is: 0x{}", hex::encode(&storagekey));
+        self.get_storage_by_key_hash(storagekey, at_block).await
+    }
+
+    pub async fn get_storage_map<K: Encode, V: Decode + Clone>(
+        &self,
+        storage_prefix: &'static str,
+        storage_key_name: &'static str,
+        map_key: K,
+        at_block: Option<Hash>,
+    ) -> ApiResult<Option<V>> {
+        let storagekey =
+            self.metadata
+                .storage_map_key::<K>(storage_prefix, storage_key_name, map_key)?;
+        info!("storage key is: 0x{}", hex::encode(&storagekey));
+        self.get_storage_by_key_hash(storagekey, at_block).await
+    }
+
+    pub async fn get_storage_double_map<K: Encode, Q: Encode, V: Decode + Clone>(
+        &self,
+        storage_prefix: &'static str,
+        storage_key_name: &'static str,
+        first: K,
+        second: Q,
+        at_block: Option<Hash>,
+    ) -> ApiResult<Option<V>> {
+        let storagekey = self.metadata.storage_double_map_key::<K, Q>(
+            storage_prefix,
+            storage_key_name,
+            first,
+            second,
+        )?;
+        info!("storage key is: 0x{}", hex::encode(&storagekey));
+        self.get_storage_by_key_hash(storagekey, at_block).await
+    }
+
+    pub async fn get_storage_by_key_hash<V: Decode>(
+        &self,
+        key: StorageKey,
+        at_block: Option<Hash>,
+    ) -> ApiResult<Option<V>> {
+        let s = self.get_opaque_storage_by_key_hash(key, at_block).await?;
+        match s {
+            Some(storage) => Ok(Some(Decode::decode(&mut storage.as_slice())?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_opaque_storage_by_key_hash(
+        &self,
+        key: StorageKey,
+        at_block: Option<Hash>,
+    ) -> ApiResult<Option<Vec<u8>>> {
+        let jsonreq = json_req::state_get_storage(key, at_block);
+        let s = self.get_request(jsonreq).await?;
+
+        match s {
+            Some(storage) => Ok(Some(Vec::from_hex(storage)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn send_extrinsic(
+        &self,
+        xthex_prefixed: String,
+        exit_on: XtStatus,
+    ) -> ApiResult<Option<Hash>> {
+        debug!("sending extrinsic: {:?}", xthex_prefixed);
+        self.client.send_extrinsic(xthex_prefixed, exit_on).await
+    }
+}
+
+impl<P, Client, Params> AsyncApi<P, Client, Params>
+where
+    Client: AsyncRpcClient + AsyncSubscriber,
+    Params: ExtrinsicParams,
+{
+    /// Yields the decoded events of every block, like
+    /// [`Api::subscribe_events`](crate::Api::subscribe_events).
+    pub async fn subscribe_events(
+        &self,
+    ) -> ApiResult<impl Stream<Item = ApiResult<Vec<(Phase, Raw)>>>> {
+        debug!("subscribing to events");
+        let key = utils::storage_key("System", "Events");
+        let jsonreq = json_req::state_subscribe_storage(vec![key]);
+        let decoder = EventsDecoder::new(self.metadata.clone());
+        self.client
+            .subscribe(
+                jsonreq,
+                Box::new(move |result| {
+                    let change_set = match result["changes"][0][1].as_str() {
+                        Some(change_set) => Vec::from_hex(change_set.to_string())?,
+                        None => {
+                            debug!("No events happened");
+                            return Ok(None);
+                        }
+                    };
+                    Ok(Some(decoder.decode_events(&mut change_set.as_slice())?))
+                }),
+            )
+            .await
+    }
+
+    /// Yields the header of every finalized block, like
+    /// [`Api::subscribe_finalized_heads`](crate::Api::subscribe_finalized_heads).
+    pub async fn subscribe_finalized_heads<H>(&self) -> ApiResult<impl Stream<Item = ApiResult<H>>>
+    where
+        H: Header + DeserializeOwned + Send + 'static,
+    {
+        debug!("subscribing to finalized heads");
+        let jsonreq = json_req::chain_subscribe_finalized_heads();
+        self.client
+            .subscribe(
+                jsonreq,
+                Box::new(|result| Ok(serde_json::from_value(result)?)),
+            )
+            .await
+    }
+
+    /// Submits the extrinsic and yields every status update until it reached a final status,
+    /// like [`Api::watch_extrinsic`](crate::Api::watch_extrinsic).
+    pub async fn watch_extrinsic(
+        &self,
+        xthex_prefixed: &str,
+    ) -> ApiResult<impl Stream<Item = ApiResult<TransactionStatus<Hash, Hash>>>> {
+        debug!("watching extrinsic: {:?}", xthex_prefixed);
+        let jsonreq = json_req::author_submit_and_watch_extrinsic(xthex_prefixed);
+        let updates = self
+            .client
+            .subscribe(
+                jsonreq,
+                Box::new(|result| Ok(Some(serde_json::from_value(result)?))),
+            )
+            .await?;
+        Ok(updates.scan(false, |done, status| {
+            if *done {
+                return future::ready(None);
+            }
+            // Errors end the stream as well, the node does not report anything afterwards.
+            *done = status.as_ref().map_or(true, TransactionStatus::is_final);
+            future::ready(Some(status))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::assert_matches::assert_matches;
+
+    fn numbers(msgs: &[&str]) -> Vec<ApiResult<u64>> {
+        let notifications = stream::iter(msgs.iter().map(ToString::to_string).collect::<Vec<_>>());
+        block_on(
+            decode_notifications(notifications, Box::new(|result| Ok(result.as_u64()))).collect(),
+        )
+    }
+
+    #[test]
+    fn yields_decoded_notifications_and_skips_confirmation() {
+        let items = numbers(&[
+            r#"{"jsonrpc":"2.0","result":"SXuvtB4Bbr4ImLAb","id":"1"}"#,
+            r#"{"jsonrpc":"2.0","method":"m","params":{"result":42,"subscription":"SXuvtB4Bbr4ImLAb"}}"#,
+            r#"{"jsonrpc":"2.0","method":"m","params":{"result":null,"subscription":"SXuvtB4Bbr4ImLAb"}}"#,
+            r#"{"jsonrpc":"2.0","method":"m","params":{"result":43,"subscription":"SXuvtB4Bbr4ImLAb"}}"#,
+        ]);
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].as_ref().unwrap(), &42);
+        assert_eq!(items[1].as_ref().unwrap(), &43);
+        // The node closed the connection.
+        assert_matches!(items[2], Err(ApiClientError::Disconnected(_)));
+    }
+
+    #[test]
+    fn yields_rpc_error_and_ends() {
+        let items = numbers(&[
+            r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":"1"}"#,
+            r#"{"jsonrpc":"2.0","method":"m","params":{"result":42,"subscription":"SXuvtB4Bbr4ImLAb"}}"#,
+        ]);
+        assert_eq!(items.len(), 1);
+        assert_matches!(items[0], Err(ApiClientError::RpcClient(_)));
+    }
+
+    #[test]
+    fn goes_on_after_decoding_error() {
+        let items = numbers(&[
+            "no json",
+            r#"{"jsonrpc":"2.0","method":"m","params":{"result":42,"subscription":"SXuvtB4Bbr4ImLAb"}}"#,
+        ]);
+        assert_matches!(items[0], Err(ApiClientError::Deserializing(_)));
+        assert_eq!(items[1].as_ref().unwrap(), &42);
+    }
+
+    #[test]
+    fn yields_typed_transaction_status() {
+        let notifications = stream::iter(vec![
+            r#"{"jsonrpc":"2.0","result":"SXuvtB4Bbr4ImLAb","id":"1"}"#.to_string(),
+            r#"{"jsonrpc":"2.0","method":"author_extrinsicUpdate","params":{"result":{"usurped":"0x3104d362365ff5ddb61845e1de441b56c6722e94c1aee362f8aa8ba75bd7a3aa"},"subscription":"SXuvtB4Bbr4ImLAb"}}"#.to_string(),
+        ]);
+        let decode: DecodeFn<TransactionStatus<Hash, Hash>> =
+            Box::new(|result| Ok(Some(serde_json::from_value(result)?)));
+        let mut updates = decode_notifications(notifications, decode);
+        assert_matches!(
+            block_on(updates.next()),
+            Some(Ok(TransactionStatus::Usurped(_)))
+        );
+    }
+}
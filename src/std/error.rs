@@ -1,5 +1,6 @@
-use crate::std::rpc::XtStatus;
+use crate::std::rpc::{RpcClientError, XtStatus};
 use ac_node_api::metadata::{InvalidMetadataError, MetadataError};
+use ac_primitives::SignError;
 
 pub type ApiResult<T> = Result<T, Error>;
 
@@ -11,20 +12,42 @@ pub enum Error {
     RuntimeVersion,
     #[error("Fetching Metadata failed. Are you connected to the correct endpoint?")]
     MetadataFetch,
+    #[error("Runtime api call {0} returned no result")]
+    RuntimeCall(String),
     #[error("Operation needs a signer to be set in the api")]
     NoSigner,
+    #[error("Signing the extrinsic failed: {0:?}")]
+    Signer(SignError),
+    #[error("The nonce of the signer can not be incremented any further")]
+    NonceOverflow,
+    #[error("The extrinsic params of the api do not support mortal eras")]
+    EraNotSupported,
+    #[error("The extrinsic params need a builder, set one in the api")]
+    NoExtrinsicParamsBuilder,
     #[cfg(feature = "ws-client")]
     #[error("WebSocket Error: {0}")]
     WebSocket(#[from] ws::Error),
+    #[cfg(feature = "async-client")]
+    #[error("Async WebSocket Error: {0}")]
+    AsyncWebSocket(#[from] tokio_tungstenite::tungstenite::Error),
+    #[cfg(feature = "http-client")]
+    #[error("Http Error: {0}")]
+    Http(Box<ureq::Error>),
+    #[cfg(feature = "ws-client")]
+    #[error(
+        "Connection to the node has been re-established, notifications in between are missing"
+    )]
+    Reconnected,
     #[error("RpcClient error: {0}")]
     RpcClient(String),
+    #[error("Timeout: the node did not answer in time")]
+    Timeout,
     #[error("ChannelReceiveError, sender is disconnected: {0}")]
     Disconnected(#[from] sp_std::sync::mpsc::RecvError),
     #[error("Metadata Error: {0:?}")]
     Metadata(MetadataError),
     #[error("InvalidMetadata: {0:?}")]
     InvalidMetadata(InvalidMetadataError),
-    #[cfg(feature = "ws-client")]
     #[error("Events Error: {0:?}")]
     NodeApi(ac_node_api::error::Error),
     #[error("Error decoding storage value: {0}")]
@@ -33,6 +56,8 @@ pub enum Error {
     InvalidHexString(#[from] hex::FromHexError),
     #[error("Error deserializing with serde: {0}")]
     Deserializing(#[from] serde_json::Error),
+    #[error("Extrinsic {0:?} not found in block {1:?}")]
+    ExtrinsicNotFound(sp_core::H256, sp_core::H256),
     #[error("UnsupportedXtStatus Error: Can only wait for finalized, in block, broadcast and ready. Waited for: {0:?}")]
     UnsupportedXtStatus(XtStatus),
     #[error("Error converting NumberOrHex to Balance")]
@@ -41,19 +66,30 @@ pub enum Error {
     Other(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
 }
 
+impl From<SignError> for Error {
+    fn from(error: SignError) -> Self {
+        Error::Signer(error)
+    }
+}
+
 impl From<InvalidMetadataError> for Error {
     fn from(error: InvalidMetadataError) -> Self {
         Error::InvalidMetadata(error)
     }
 }
 
+impl From<RpcClientError> for Error {
+    fn from(error: RpcClientError) -> Self {
+        Error::RpcClient(error.to_string())
+    }
+}
+
 impl From<MetadataError> for Error {
     fn from(error: MetadataError) -> Self {
         Error::Metadata(error)
     }
 }
 
-#[cfg(feature = "ws-client")]
 impl From<ac_node_api::error::Error> for Error {
     fn from(error: ac_node_api::error::Error) -> Self {
         Error::NodeApi(error)
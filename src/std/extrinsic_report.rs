@@ -0,0 +1,255 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+//! Outcome of an extrinsic that has been included in a block.
+
+use ac_node_api::events::{Raw, RawEvent};
+use ac_node_api::metadata::Metadata;
+use ac_node_api::{Phase, RuntimeError};
+use ac_primitives::Balance;
+use codec::Decode;
+use serde_json::Value;
+use sp_core::hashing::blake2_256;
+use sp_core::H256 as Hash;
+use sp_runtime::{AccountId32 as AccountId, DispatchError};
+
+use crate::std::{ApiResult, FromHexString};
+
+/// Everything the chain reports about an included extrinsic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtrinsicReport {
+    pub extrinsic_hash: Hash,
+    pub block_hash: Hash,
+    /// Position of the extrinsic within the block.
+    pub extrinsic_index: u32,
+    /// `Err` with the dispatch error of the `System::ExtrinsicFailed` event if the extrinsic
+    /// has been included, but its dispatch failed.
+    pub result: Result<(), RuntimeError>,
+    /// Taken from the `TransactionPayment::TransactionFeePaid` event, if the runtime emits it.
+    pub fee_paid: Option<Balance>,
+    /// All events emitted while applying the extrinsic.
+    pub events: Vec<RawEvent>,
+}
+
+impl ExtrinsicReport {
+    /// Builds the report from all events of the block the extrinsic has been included in.
+    /// `metadata` resolves the pallet errors the extrinsic may have failed with.
+    pub fn from_block_events(
+        metadata: &Metadata,
+        extrinsic_hash: Hash,
+        block_hash: Hash,
+        extrinsic_index: u32,
+        block_events: Vec<(Phase, Raw)>,
+    ) -> ApiResult<Self> {
+        let mut result = Ok(());
+        let mut fee_paid = None;
+        let mut events = Vec::new();
+        for (phase, event) in block_events {
+            if phase != Phase::ApplyExtrinsic(extrinsic_index) {
+                continue;
+            }
+            match event {
+                Raw::Event(event) => {
+                    if event.pallet == "TransactionPayment" && event.variant == "TransactionFeePaid"
+                    {
+                        let (_who, actual_fee, _tip) =
+                            <(AccountId, Balance, Balance)>::decode(&mut &event.data[..])?;
+                        fee_paid = Some(actual_fee);
+                    }
+                    // The events decoder turns the dispatch error into a `Raw::Error`, unless
+                    // the runtime's `DispatchError` type is not recognized as such.
+                    if event.pallet == "System" && event.variant == "ExtrinsicFailed" {
+                        let dispatch_error = DispatchError::decode(&mut &event.data[..])?;
+                        result = Err(RuntimeError::from_dispatch(metadata, dispatch_error)?);
+                    }
+                    events.push(event);
+                }
+                Raw::Error(error) => result = Err(error),
+            }
+        }
+
+        Ok(Self {
+            extrinsic_hash,
+            block_hash,
+            extrinsic_index,
+            result,
+            fee_paid,
+            events,
+        })
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Returns the index of the extrinsic with `extrinsic_hash` within the json encoded signed block
+/// returned by `chain_getBlock`.
+pub(crate) fn extrinsic_index(
+    signed_block: &Value,
+    extrinsic_hash: &Hash,
+) -> ApiResult<Option<u32>> {
+    let extrinsics = match signed_block["block"]["extrinsics"].as_array() {
+        Some(extrinsics) => extrinsics,
+        None => return Ok(None),
+    };
+    for (index, xt) in extrinsics.iter().enumerate() {
+        let xt = Vec::from_hex(xt.as_str().unwrap_or_default().to_string())?;
+        if Hash::from(blake2_256(&xt)) == *extrinsic_hash {
+            return Ok(Some(index as u32));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std::RuntimeMetadataPrefixed;
+    use ac_node_api::PalletError;
+    use codec::Encode;
+    use sp_runtime::ModuleError;
+    use std::assert_matches::assert_matches;
+
+    fn node_template_metadata() -> Metadata {
+        let encoded = node_template_runtime::Runtime::metadata().encode();
+        Metadata::try_from(RuntimeMetadataPrefixed::decode(&mut encoded.as_slice()).unwrap())
+            .unwrap()
+    }
+
+    fn raw_event(pallet: &str, variant: &str, data: Vec<u8>) -> Raw {
+        Raw::Event(RawEvent {
+            pallet: pallet.to_string(),
+            pallet_index: 0,
+            variant: variant.to_string(),
+            variant_index: 0,
+            data: data.into(),
+            topics: vec![],
+        })
+    }
+
+    #[test]
+    fn report_contains_only_events_of_the_extrinsic() {
+        let fee_paid = (AccountId::new([1u8; 32]), 1_000u128, 0u128).encode();
+        let block_events = vec![
+            (
+                Phase::ApplyExtrinsic(0),
+                raw_event("System", "ExtrinsicSuccess", vec![]),
+            ),
+            (
+                Phase::ApplyExtrinsic(1),
+                raw_event("Balances", "Transfer", vec![]),
+            ),
+            (
+                Phase::ApplyExtrinsic(1),
+                raw_event("TransactionPayment", "TransactionFeePaid", fee_paid),
+            ),
+            (
+                Phase::ApplyExtrinsic(1),
+                raw_event("System", "ExtrinsicSuccess", vec![]),
+            ),
+            (
+                Phase::Finalization,
+                raw_event("System", "Finalized", vec![]),
+            ),
+        ];
+
+        let report = ExtrinsicReport::from_block_events(
+            &node_template_metadata(),
+            Hash::zero(),
+            Hash::zero(),
+            1,
+            block_events,
+        )
+        .unwrap();
+        assert!(report.is_success());
+        assert_eq!(report.fee_paid, Some(1_000));
+        let events: Vec<_> = report.events.iter().map(|e| e.variant.as_str()).collect();
+        assert_eq!(
+            events,
+            vec!["Transfer", "TransactionFeePaid", "ExtrinsicSuccess"]
+        );
+    }
+
+    #[test]
+    fn report_contains_runtime_error_of_failed_extrinsic() {
+        let error = RuntimeError::Module(PalletError {
+            pallet: "Balances".to_string(),
+            error: "InsufficientBalance".to_string(),
+            description: vec![],
+        });
+        let block_events = vec![(Phase::ApplyExtrinsic(0), Raw::Error(error.clone()))];
+
+        let report = ExtrinsicReport::from_block_events(
+            &node_template_metadata(),
+            Hash::zero(),
+            Hash::zero(),
+            0,
+            block_events,
+        )
+        .unwrap();
+        assert_eq!(report.result, Err(error));
+        assert_eq!(report.fee_paid, None);
+        assert!(report.events.is_empty());
+    }
+
+    #[test]
+    fn report_contains_dispatch_error_of_undecoded_extrinsic_failed_event() {
+        let metadata = node_template_metadata();
+        let dispatch_error = DispatchError::Module(ModuleError {
+            index: metadata.pallet("Balances").unwrap().index,
+            error: [2, 0, 0, 0],
+            message: None,
+        });
+        let block_events = vec![(
+            Phase::ApplyExtrinsic(0),
+            raw_event("System", "ExtrinsicFailed", dispatch_error.encode()),
+        )];
+
+        let report = ExtrinsicReport::from_block_events(
+            &metadata,
+            Hash::zero(),
+            Hash::zero(),
+            0,
+            block_events,
+        )
+        .unwrap();
+        assert!(!report.is_success());
+        assert_matches!(
+            report.result,
+            Err(RuntimeError::Module(PalletError { pallet, error, .. }))
+                if pallet == "Balances" && error == "InsufficientBalance"
+        );
+        assert_eq!(report.events.len(), 1);
+    }
+
+    #[test]
+    fn extrinsic_index_is_found_by_hash() {
+        let xt = vec![4u8, 5, 6];
+        let block = serde_json::json!({
+            "block": {
+                "header": {},
+                "extrinsics": ["0x010203", format!("0x{}", hex::encode(&xt))],
+            },
+            "justifications": null,
+        });
+
+        let hash = Hash::from(blake2_256(&xt));
+        assert_eq!(extrinsic_index(&block, &hash).unwrap(), Some(1));
+        assert_eq!(extrinsic_index(&block, &Hash::zero()).unwrap(), None);
+    }
+}
@@ -1,13 +1,26 @@
+#[cfg(feature = "async-client")]
+pub use crate::std::async_api::{AsyncApi, AsyncRpcClient, AsyncSubscriber};
 pub use crate::std::error::{ApiResult, Error as ApiClientError};
-pub use crate::std::rpc::XtStatus;
+#[cfg(feature = "ws-client")]
+pub use crate::std::extrinsic_report::ExtrinsicReport;
+pub use crate::std::nonce_manager::NonceManager;
+pub use crate::std::rpc::{TransactionStatus, XtStatus};
 pub use crate::utils::FromHexString;
-use ac_node_api::metadata::{Metadata, MetadataError};
-use ac_primitives::{AccountData, AccountInfo, Balance, ExtrinsicParams};
+use ac_node_api::events::{EventsDecoder, Raw};
+use ac_node_api::metadata::{Metadata, MetadataError, SUPPORTED_METADATA_VERSIONS};
+use ac_node_api::value;
+use ac_node_api::Phase;
+use ac_primitives::{
+    AccountData, AccountInfo, Balance, BlockNumber, ExtrinsicParams, Index, Signer,
+};
 pub use metadata::RuntimeMetadataPrefixed;
+use metadata::StorageEntryType;
 pub use serde_json::Value;
 pub use sp_core::crypto::Pair;
 pub use sp_core::storage::StorageKey;
 use sp_core::H256 as Hash;
+use sp_runtime::generic::Era;
+use sp_runtime::traits::BlakeTwo256;
 pub use sp_runtime::traits::{Block, Header};
 pub use sp_runtime::{
     generic::SignedBlock, traits::IdentifyAccount, AccountId32 as AccountId, MultiSignature,
@@ -17,10 +30,18 @@ pub use sp_std::prelude::*;
 pub use sp_version::RuntimeVersion;
 pub use transaction_payment::FeeDetails;
 
+#[cfg(feature = "async-client")]
+pub mod async_api;
 pub mod error;
+#[cfg(feature = "ws-client")]
+pub mod extrinsic_report;
+pub mod nonce_manager;
 pub mod rpc;
+pub mod runtime_api;
 
 use std::convert::{TryFrom, TryInto};
+use std::sync::Arc;
+use std::time::Duration;
 
 use codec::{Decode, Encode};
 use log::{debug, info};
@@ -34,13 +55,67 @@ pub trait RpcClient {
     /// Sends a RPC request that returns a String
     fn get_request(&self, jsonreq: serde_json::Value) -> ApiResult<String>;
 
-    /// Send a RPC request that returns a SHA256 hash
+    /// Sends the extrinsic and waits until `exit_on` is reached. Returns the hash of the
+    /// extrinsic for [`XtStatus::SubmitOnly`] and the block hash for [`XtStatus::InBlock`] and
+    /// [`XtStatus::Finalized`].
     fn send_extrinsic(&self, xthex_prefixed: String, exit_on: XtStatus) -> ApiResult<Option<Hash>>;
+
+    /// Like [`Self::get_request`], but fails with the error the node responded with instead of
+    /// returning `null`. Clients that can not tell errors apart return `null` for both.
+    fn get_request_or_error(&self, jsonreq: serde_json::Value) -> ApiResult<String> {
+        self.get_request(jsonreq)
+    }
+
+    /// Like [`Self::get_request`], but fails with [`ApiClientError::Timeout`] if the node does
+    /// not answer within `timeout`. Clients without support for per-call timeouts ignore it.
+    fn get_request_with_timeout(
+        &self,
+        jsonreq: serde_json::Value,
+        timeout: Duration,
+    ) -> ApiResult<String> {
+        let _ = timeout;
+        self.get_request(jsonreq)
+    }
+
+    /// Like [`Self::send_extrinsic`], but fails with [`ApiClientError::Timeout`] if `exit_on`
+    /// is not reached within `timeout`. Clients without support for per-call timeouts ignore it.
+    fn send_extrinsic_with_timeout(
+        &self,
+        xthex_prefixed: String,
+        exit_on: XtStatus,
+        timeout: Duration,
+    ) -> ApiResult<Option<Hash>> {
+        let _ = timeout;
+        self.send_extrinsic(xthex_prefixed, exit_on)
+    }
+}
+
+/// Header of any chain with a blake2 hasher, to read the block number. The block number is
+/// compact encoded, so its width does not affect the hash.
+type NumberedHeader = sp_runtime::generic::Header<BlockNumber, BlakeTwo256>;
+
+/// Hook called with the new runtime version and metadata after a runtime upgrade.
+type OnRuntimeUpdateFn = Arc<dyn Fn(&RuntimeVersion, &Metadata) + Send + Sync>;
+
+/// Builds the extrinsic params builder from the metadata of the runtime.
+type ExtrinsicParamsBuilderFn<Params> =
+    Arc<dyn Fn(&Metadata) -> ApiResult<<Params as ExtrinsicParams>::OtherParams> + Send + Sync>;
+
+/// Block the era of a mortal extrinsic starts at, see [`Api::mortal_era`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EraCheckpoint {
+    /// The latest block. The extrinsic is valid immediately, but becomes invalid if the block is
+    /// retracted.
+    Latest,
+    /// The latest finalized block.
+    Finalized,
 }
 
 /// Api to talk with substrate-nodes
 ///
 /// It is generic over the `RpcClient` trait, so you can use any rpc-backend you like.
+/// This crate ships a `WsRpcClient` (`ws-client` feature) and an `HttpRpcClient`
+/// (`http-client` feature).
 ///
 /// # Custom Client Example
 ///
@@ -108,20 +183,20 @@ where
     pub runtime_version: RuntimeVersion,
     client: Client,
     pub extrinsic_params_builder: Option<Params::OtherParams>,
+    extrinsic_params_builder_fn: Option<ExtrinsicParamsBuilderFn<Params>>,
+    on_runtime_update: Option<OnRuntimeUpdateFn>,
+    nonce_manager: Option<NonceManager>,
+    mortality: Option<(u64, EraCheckpoint)>,
 }
 
 impl<P, Client, Params> Api<P, Client, Params>
 where
-    P: Pair,
-    MultiSignature: From<P::Signature>,
-    MultiSigner: From<P::Public>,
+    P: Signer,
     Client: RpcClient,
     Params: ExtrinsicParams,
 {
     pub fn signer_account(&self) -> Option<AccountId> {
-        let pair = self.signer.as_ref()?;
-        let multi_signer = MultiSigner::from(pair.public());
-        Some(multi_signer.into_account())
+        Some(self.signer.as_ref()?.account_id())
     }
 
     pub fn get_nonce(&self) -> ApiResult<u32> {
@@ -132,6 +207,19 @@ where
         self.get_account_info(&self.signer_account().unwrap())
             .map(|acc_opt| acc_opt.map_or_else(|| 0, |acc| acc.nonce))
     }
+
+    /// Returns the nonce for the next extrinsic of the signer. With a [`NonceManager`] set, see
+    /// [`Self::set_nonce_manager`], the nonce is reserved, so consecutive calls return
+    /// consecutive nonces. Otherwise, it is the same as [`Self::get_nonce`].
+    pub fn next_nonce(&self) -> ApiResult<Index> {
+        match &self.nonce_manager {
+            Some(nonce_manager) => nonce_manager.next_nonce(|| {
+                let account = self.signer_account().ok_or(ApiClientError::NoSigner)?;
+                self.get_account_next_index(&account)
+            }),
+            None => self.get_nonce(),
+        }
+    }
 }
 
 impl<P, Client, Params> Api<P, Client, Params>
@@ -156,20 +244,113 @@ where
             runtime_version,
             client,
             extrinsic_params_builder: None,
+            extrinsic_params_builder_fn: None,
+            on_runtime_update: None,
+            nonce_manager: None,
+            mortality: None,
         })
     }
 
     #[must_use]
     pub fn set_signer(mut self, signer: P) -> Self {
         self.signer = Some(signer);
+        if let Some(nonce_manager) = &self.nonce_manager {
+            nonce_manager.resync();
+        }
+        self
+    }
+
+    /// Hands out the nonces of the signer's extrinsics with `nonce_manager` instead of reading
+    /// them from the storage for every extrinsic, see [`Self::next_nonce`]. Clones of the api
+    /// share the nonce manager.
+    #[must_use]
+    pub fn set_nonce_manager(mut self, nonce_manager: NonceManager) -> Self {
+        self.nonce_manager = Some(nonce_manager);
         self
     }
 
+    pub fn nonce_manager(&self) -> Option<&NonceManager> {
+        self.nonce_manager.as_ref()
+    }
+
+    /// Sets the builder of the extrinsic params. It is kept across runtime upgrades, set builders
+    /// depending on the runtime with [`Self::set_extrinsic_params_builder_from_metadata`].
     pub fn set_extrinsic_params_builder(mut self, extrinsic_params: Params::OtherParams) -> Self {
         self.extrinsic_params_builder = Some(extrinsic_params);
+        self.extrinsic_params_builder_fn = None;
         self
     }
 
+    /// Sets the builder of the extrinsic params to the one `build` creates from the metadata of
+    /// the node, e.g. a [`ac_node_api::MetadataExtrinsicParamsBuilder`]. `build` is called again
+    /// with the new metadata after every runtime upgrade, see [`Self::set_runtime_version`].
+    pub fn set_extrinsic_params_builder_from_metadata(
+        mut self,
+        build: impl Fn(&Metadata) -> ApiResult<Params::OtherParams> + Send + Sync + 'static,
+    ) -> ApiResult<Self> {
+        self.extrinsic_params_builder = Some(build(&self.metadata)?);
+        self.extrinsic_params_builder_fn = Some(Arc::new(build));
+        Ok(self)
+    }
+
+    /// Makes the extrinsics composed with [`Self::default_extrinsic_params`], and thus the
+    /// `compose_extrinsic!` macro, mortal: valid for `period` blocks from `checkpoint` on. Their
+    /// era is computed anew for every extrinsic and overrides the era of the extrinsic params
+    /// builder.
+    #[must_use]
+    pub fn set_mortality(mut self, period: u64, checkpoint: EraCheckpoint) -> Self {
+        self.mortality = Some((period, checkpoint));
+        self
+    }
+
+    /// Sets a hook that is called whenever the api has been updated to a new runtime, see
+    /// [`Self::update_runtime`].
+    #[must_use]
+    pub fn set_on_runtime_update(
+        mut self,
+        on_runtime_update: impl Fn(&RuntimeVersion, &Metadata) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_runtime_update = Some(Arc::new(on_runtime_update));
+        self
+    }
+
+    /// Fetches the current runtime version of the node. If the runtime has been upgraded since,
+    /// the metadata is refetched, see [`Self::set_runtime_version`]. Returns whether the runtime
+    /// has changed.
+    pub fn update_runtime(&mut self) -> ApiResult<bool> {
+        let runtime_version = Self::_get_runtime_version(&self.client)?;
+        self.set_runtime_version(runtime_version)
+    }
+
+    /// Updates the api to `runtime_version`, e.g. received by a runtime version subscription.
+    /// If its spec or transaction version differs from the cached one, the metadata is
+    /// refetched, the extrinsic params builder set with
+    /// [`Self::set_extrinsic_params_builder_from_metadata`] is rebuilt and the hook set with
+    /// [`Self::set_on_runtime_update`] is called. Returns whether the runtime has changed.
+    pub fn set_runtime_version(&mut self, runtime_version: RuntimeVersion) -> ApiResult<bool> {
+        if runtime_version.spec_version == self.runtime_version.spec_version
+            && runtime_version.transaction_version == self.runtime_version.transaction_version
+        {
+            return Ok(false);
+        }
+        info!(
+            "Runtime upgraded from spec version {} to {}",
+            self.runtime_version.spec_version, runtime_version.spec_version
+        );
+        let metadata = Self::_get_metadata(&self.client).map(Metadata::try_from)??;
+        debug!("Metadata: {:?}", metadata);
+        if let Some(build) = &self.extrinsic_params_builder_fn {
+            self.extrinsic_params_builder = Some(build(&metadata)?);
+        }
+        self.metadata = metadata;
+        self.runtime_version = runtime_version;
+
+        if let Some(on_runtime_update) = &self.on_runtime_update {
+            on_runtime_update(&self.runtime_version, &self.metadata);
+        }
+        Ok(true)
+    }
+
     fn _get_genesis_hash(client: &Client) -> ApiResult<Hash> {
         let jsonreq = json_req::chain_get_genesis_hash();
         let genesis = Self::_get_request(client, jsonreq)?;
@@ -190,7 +371,24 @@ where
         }
     }
 
+    /// Fetches the newest metadata version both the node and [`Metadata`] support. Falls back
+    /// to `state_getMetadata` for runtimes without the `Metadata_metadata_at_version` api.
     fn _get_metadata(client: &Client) -> ApiResult<RuntimeMetadataPrefixed> {
+        let versions = Self::_get_metadata_versions(client)
+            .map_err(|e| debug!("Could not fetch the metadata versions: {:?}", e))
+            .ok();
+        let version = versions.and_then(|versions| {
+            SUPPORTED_METADATA_VERSIONS
+                .into_iter()
+                .find(|version| versions.contains(version))
+        });
+        if let Some(version) = version {
+            if let Some(metadata) = Self::_get_metadata_at_version(client, version)? {
+                info!("Got metadata version {}", version);
+                return Ok(metadata);
+            }
+        }
+
         let jsonreq = json_req::state_get_metadata();
         let meta = Self::_get_request(client, jsonreq)?;
 
@@ -201,6 +399,43 @@ where
         RuntimeMetadataPrefixed::decode(&mut metadata.as_slice()).map_err(|e| e.into())
     }
 
+    fn _get_metadata_versions(client: &Client) -> ApiResult<Vec<u32>> {
+        Self::_runtime_call(client, "Metadata_metadata_versions", &[], None)
+    }
+
+    fn _get_metadata_at_version(
+        client: &Client,
+        version: u32,
+    ) -> ApiResult<Option<RuntimeMetadataPrefixed>> {
+        let opaque: Option<Vec<u8>> = Self::_runtime_call(
+            client,
+            "Metadata_metadata_at_version",
+            &version.encode(),
+            None,
+        )?;
+        opaque
+            .map(|metadata| RuntimeMetadataPrefixed::decode(&mut metadata.as_slice()))
+            .transpose()
+            .map_err(|e| e.into())
+    }
+
+    /// Fails with the error of the node if it can not call `method`, e.g. because the runtime
+    /// does not provide it.
+    fn _runtime_call<R: Decode>(
+        client: &Client,
+        method: &str,
+        encoded_args: &[u8],
+        at_block: Option<Hash>,
+    ) -> ApiResult<R> {
+        let jsonreq = json_req::state_call(method, encoded_args, at_block);
+        match &client.get_request_or_error(jsonreq)?[..] {
+            "null" => Err(ApiClientError::RuntimeCall(method.to_string())),
+            result => Ok(Decode::decode(
+                &mut Vec::from_hex(result.to_string())?.as_slice(),
+            )?),
+        }
+    }
+
     // low level access
     fn _get_request(client: &Client, jsonreq: Value) -> ApiResult<Option<String>> {
         let str = client.get_request(jsonreq)?;
@@ -211,20 +446,86 @@ where
         }
     }
 
-    pub fn extrinsic_params(&self, nonce: u32) -> Params {
-        let extrinsic_params_builder = self.extrinsic_params_builder.clone().unwrap_or_default();
+    /// Builds the extrinsic params with the builder set in the api, or the default one of
+    /// `Params`. Fails with [`ApiClientError::NoExtrinsicParamsBuilder`] if neither exists.
+    pub fn extrinsic_params(&self, nonce: u32) -> ApiResult<Params> {
+        let extrinsic_params_builder = self.extrinsic_params_builder()?;
+        Ok(self.extrinsic_params_from(nonce, extrinsic_params_builder))
+    }
+
+    /// Like [`Self::extrinsic_params`], but with a mortal era if the api has been made mortal
+    /// with [`Self::set_mortality`].
+    pub fn default_extrinsic_params(&self, nonce: u32) -> ApiResult<Params> {
+        let mut extrinsic_params_builder = self.extrinsic_params_builder()?;
+        if let Some((period, checkpoint)) = self.mortality {
+            let (era, checkpoint) = self.mortal_era(period, checkpoint)?;
+            extrinsic_params_builder = Params::with_era(extrinsic_params_builder, era, checkpoint)
+                .ok_or(ApiClientError::EraNotSupported)?;
+        }
+        Ok(self.extrinsic_params_from(nonce, extrinsic_params_builder))
+    }
+
+    fn extrinsic_params_builder(&self) -> ApiResult<Params::OtherParams> {
+        self.extrinsic_params_builder
+            .clone()
+            .or_else(Params::default_other_params)
+            .ok_or(ApiClientError::NoExtrinsicParamsBuilder)
+    }
+
+    fn extrinsic_params_from(&self, nonce: u32, builder: Params::OtherParams) -> Params {
         <Params as ExtrinsicParams>::new(
             self.runtime_version.spec_version,
             self.runtime_version.transaction_version,
             nonce,
             self.genesis_hash,
-            extrinsic_params_builder,
+            builder,
         )
     }
+
+    /// Returns a mortal era of `period` blocks starting at `checkpoint`, together with the hash
+    /// of the block it starts at, as needed by e.g. `BaseExtrinsicParamsBuilder::era`.
+    ///
+    /// The period is rounded to a power of two between 4 and 65536 blocks.
+    pub fn mortal_era(&self, period: u64, checkpoint: EraCheckpoint) -> ApiResult<(Era, Hash)> {
+        let hash = match checkpoint {
+            EraCheckpoint::Latest => self.get_block_hash(None)?,
+            EraCheckpoint::Finalized => self.get_finalized_head()?,
+        }
+        .ok_or_else(|| ApiClientError::RpcClient("node returned no head".into()))?;
+        let header: NumberedHeader = self
+            .get_header(Some(hash))?
+            .ok_or_else(|| ApiClientError::RpcClient(format!("header of {:?} not found", hash)))?;
+
+        let era = Era::mortal(period, header.number);
+        // Long eras are quantized, they may start some blocks before the checkpoint.
+        let birth = era.birth(header.number);
+        if birth == header.number {
+            return Ok((era, hash));
+        }
+        let birth_hash = self.get_block_hash(Some(birth as u32))?.ok_or_else(|| {
+            ApiClientError::RpcClient(format!("hash of block {} not found", birth))
+        })?;
+        Ok((era, birth_hash))
+    }
+
     pub fn get_metadata(&self) -> ApiResult<RuntimeMetadataPrefixed> {
         Self::_get_metadata(&self.client)
     }
 
+    /// Returns the metadata versions provided by the runtime, or `None` if it does not support
+    /// the `Metadata_metadata_versions` api.
+    pub fn get_metadata_versions(&self) -> ApiResult<Option<Vec<u32>>> {
+        Self::_get_metadata_versions(&self.client)
+    }
+
+    /// Returns the metadata in `version`, or `None` if the runtime does not provide it.
+    pub fn get_metadata_at_version(
+        &self,
+        version: u32,
+    ) -> ApiResult<Option<RuntimeMetadataPrefixed>> {
+        Self::_get_metadata_at_version(&self.client, version)
+    }
+
     pub fn get_spec_version(&self) -> ApiResult<u32> {
         Self::_get_runtime_version(&self.client).map(|v| v.spec_version)
     }
@@ -242,6 +543,17 @@ where
         self.get_storage_by_key_hash(storagekey, None)
     }
 
+    /// Returns the next nonce of `account`, taking the extrinsics in the transaction pool into
+    /// account.
+    pub fn get_account_next_index(&self, account: &AccountId) -> ApiResult<Index> {
+        let index = self
+            .get_request(json_req::system_account_next_index(account))?
+            .ok_or_else(|| {
+                ApiClientError::RpcClient("system_accountNextIndex returned no nonce".into())
+            })?;
+        Ok(serde_json::from_str(&index)?)
+    }
+
     pub fn get_account_data(&self, address: &AccountId) -> ApiResult<Option<AccountData>> {
         self.get_account_info(address)
             .map(|info| info.map(|i| i.data))
@@ -318,6 +630,31 @@ where
         Self::_get_request(&self.client, jsonreq)
     }
 
+    /// Calls `method` of a runtime api, e.g. `AccountNonceApi_account_nonce`, with the SCALE
+    /// encoded arguments `encoded_args` and decodes its result as `R`. See [`runtime_api`] for
+    /// typed wrappers of common runtime apis.
+    pub fn runtime_call<R: Decode>(
+        &self,
+        method: &str,
+        encoded_args: &[u8],
+        at_block: Option<Hash>,
+    ) -> ApiResult<R> {
+        Self::_runtime_call(&self.client, method, encoded_args, at_block)
+    }
+
+    pub fn get_request_with_timeout(
+        &self,
+        jsonreq: Value,
+        timeout: Duration,
+    ) -> ApiResult<Option<String>> {
+        let str = self.client.get_request_with_timeout(jsonreq, timeout)?;
+
+        match &str[..] {
+            "null" => Ok(None),
+            _ => Ok(Some(str)),
+        }
+    }
+
     pub fn get_storage_value<V: Decode>(
         &self,
         storage_prefix: &'static str,
@@ -373,6 +710,69 @@ where
         self.get_storage_by_key_hash(storagekey, at_block)
     }
 
+    /// Like [`Self::get_storage_value`], but decodes the value with the type information of the
+    /// metadata instead of a compiled runtime type.
+    pub fn get_storage_value_dynamic(
+        &self,
+        storage_prefix: &'static str,
+        storage_key_name: &'static str,
+        at_block: Option<Hash>,
+    ) -> ApiResult<Option<value::Value>> {
+        let entry = self
+            .metadata
+            .pallet(storage_prefix)?
+            .storage(storage_key_name)?;
+        let type_id = match &entry.ty {
+            StorageEntryType::Plain(ty) => ty.id(),
+            _ => return Err(MetadataError::StorageTypeError.into()),
+        };
+        let storagekey = self
+            .metadata
+            .storage_value_key(storage_prefix, storage_key_name)?;
+        info!("storage key is: 0x{}", hex::encode(&storagekey));
+        self.get_storage_dynamic_by_key_hash(storagekey, type_id, at_block)
+    }
+
+    /// Like [`Self::get_storage_map`], but decodes the value with the type information of the
+    /// metadata instead of a compiled runtime type.
+    pub fn get_storage_map_dynamic<K: Encode>(
+        &self,
+        storage_prefix: &'static str,
+        storage_key_name: &'static str,
+        map_key: K,
+        at_block: Option<Hash>,
+    ) -> ApiResult<Option<value::Value>> {
+        let entry = self
+            .metadata
+            .pallet(storage_prefix)?
+            .storage(storage_key_name)?;
+        let type_id = match &entry.ty {
+            StorageEntryType::Map { value, .. } => value.id(),
+            _ => return Err(MetadataError::StorageTypeError.into()),
+        };
+        let storagekey =
+            self.metadata
+                .storage_map_key::<K>(storage_prefix, storage_key_name, map_key)?;
+        info!("storage key is: 0x{}", hex::encode(&storagekey));
+        self.get_storage_dynamic_by_key_hash(storagekey, type_id, at_block)
+    }
+
+    fn get_storage_dynamic_by_key_hash(
+        &self,
+        key: StorageKey,
+        type_id: u32,
+        at_block: Option<Hash>,
+    ) -> ApiResult<Option<value::Value>> {
+        match self.get_opaque_storage_by_key_hash(key, at_block)? {
+            Some(storage) => Ok(Some(value::Value::decode_as_type(
+                &self.metadata.runtime_metadata().types,
+                type_id,
+                &mut storage.as_slice(),
+            )?)),
+            None => Ok(None),
+        }
+    }
+
     pub fn get_storage_by_key_hash<V: Decode>(
         &self,
         key: StorageKey,
@@ -518,6 +918,21 @@ where
         Ok(Decode::decode(&mut c.value.as_slice())?)
     }
 
+    /// Like [`Self::get_constant`], but decodes the constant with the type information of the
+    /// metadata instead of a compiled runtime type.
+    pub fn get_constant_dynamic(
+        &self,
+        pallet: &'static str,
+        constant: &'static str,
+    ) -> ApiResult<value::Value> {
+        let c = self.metadata.pallet(pallet)?.constant(constant)?;
+        Ok(value::Value::decode_as_type(
+            &self.metadata.runtime_metadata().types,
+            c.ty.id(),
+            &mut c.value.as_slice(),
+        )?)
+    }
+
     pub fn get_existential_deposit(&self) -> ApiResult<Balance> {
         self.get_constant("Balances", "ExistentialDeposit")
     }
@@ -529,15 +944,116 @@ where
         exit_on: XtStatus,
     ) -> ApiResult<Option<Hash>> {
         debug!("sending extrinsic: {:?}", xthex_prefixed);
-        self.client.send_extrinsic(xthex_prefixed, exit_on)
+        self.client
+            .send_extrinsic(xthex_prefixed, exit_on)
+            .map_err(|e| self.resync_nonce_on(e))
+    }
+
+    /// Like [`Self::send_extrinsic`], but fails with [`ApiClientError::Timeout`] if `exit_on`
+    /// is not reached within `timeout`, see [`RpcClient::send_extrinsic_with_timeout`].
+    pub fn send_extrinsic_with_timeout(
+        &self,
+        xthex_prefixed: String,
+        exit_on: XtStatus,
+        timeout: Duration,
+    ) -> ApiResult<Option<Hash>> {
+        debug!("sending extrinsic: {:?}", xthex_prefixed);
+        self.client
+            .send_extrinsic_with_timeout(xthex_prefixed, exit_on, timeout)
+            .map_err(|e| self.resync_nonce_on(e))
+    }
+
+    /// Resyncs the nonce manager after the extrinsic could not be sent, see
+    /// [`NonceManager::resync_on`].
+    fn resync_nonce_on(&self, error: ApiClientError) -> ApiClientError {
+        if let Some(nonce_manager) = &self.nonce_manager {
+            if nonce_manager.resync_on(&error) {
+                log::warn!(
+                    "extrinsic has been sent with a wrong nonce, resyncing: {}",
+                    error
+                );
+            }
+        }
+        error
+    }
+
+    /// Sends the extrinsic and waits until it is included in a block, [`XtStatus::InBlock`], or
+    /// finalized, [`XtStatus::Finalized`]. Returns its outcome together with the events it
+    /// emitted.
+    #[cfg(feature = "ws-client")]
+    pub fn send_extrinsic_and_get_report(
+        &self,
+        xthex_prefixed: String,
+        exit_on: XtStatus,
+    ) -> ApiResult<ExtrinsicReport> {
+        if !matches!(exit_on, XtStatus::InBlock | XtStatus::Finalized) {
+            return Err(ApiClientError::UnsupportedXtStatus(exit_on));
+        }
+        let extrinsic_hash =
+            Hash::from(sp_core::blake2_256(&Vec::from_hex(xthex_prefixed.clone())?));
+        let block_hash = self
+            .send_extrinsic(xthex_prefixed, exit_on)?
+            .ok_or_else(|| {
+                ApiClientError::RpcClient("extrinsic has not been included in a block".into())
+            })?;
+
+        let signed_block: Value = self
+            .get_request(json_req::chain_get_block(Some(block_hash)))?
+            .map(|block| serde_json::from_str(&block))
+            .transpose()?
+            .unwrap_or_default();
+        let extrinsic_index =
+            extrinsic_report::extrinsic_index(&signed_block, &extrinsic_hash)?.ok_or(
+                ApiClientError::ExtrinsicNotFound(extrinsic_hash, block_hash),
+            )?;
+
+        let block_events = self.get_events(Some(block_hash))?;
+        ExtrinsicReport::from_block_events(
+            &self.metadata,
+            extrinsic_hash,
+            block_hash,
+            extrinsic_index,
+            block_events,
+        )
+    }
+
+    /// Returns the events of the block `at_block`, or of the latest block if `None`.
+    pub fn get_events(&self, at_block: Option<Hash>) -> ApiResult<Vec<(Phase, Raw)>> {
+        let key = crate::utils::storage_key("System", "Events");
+        let events = self
+            .get_opaque_storage_by_key_hash(key, at_block)?
+            .unwrap_or_default();
+        if events.is_empty() {
+            return Ok(Vec::new());
+        }
+        let decoder = EventsDecoder::new(self.metadata.clone());
+        Ok(decoder.decode_events(&mut events.as_slice())?)
+    }
+
+    /// Returns the events emitted while applying the extrinsic at position `extrinsic_index`
+    /// within block `block_hash`.
+    pub fn get_events_for_extrinsic(
+        &self,
+        block_hash: Hash,
+        extrinsic_index: u32,
+    ) -> ApiResult<Vec<Raw>> {
+        let events = self
+            .get_events(Some(block_hash))?
+            .into_iter()
+            .filter(|(phase, _)| *phase == Phase::ApplyExtrinsic(extrinsic_index))
+            .map(|(_, event)| event)
+            .collect();
+        Ok(events)
     }
 
+    /// Without the `ws-client` feature there are no subscriptions, so the extrinsic is only
+    /// submitted. See [`XtStatus::SubmitOnly`].
     #[cfg(not(feature = "ws-client"))]
     pub fn send_extrinsic(&self, xthex_prefixed: String) -> ApiResult<Option<Hash>> {
         debug!("sending extrinsic: {:?}", xthex_prefixed);
-        // XtStatus should never be used used but we need to put something
         self.client
-            .send_extrinsic(xthex_prefixed, XtStatus::Broadcast)
+            .send_extrinsic(xthex_prefixed, XtStatus::SubmitOnly)
+            .map_err(|e| self.resync_nonce_on(e))
     }
 }
 
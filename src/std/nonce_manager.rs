@@ -0,0 +1,165 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+//! Local nonce bookkeeping, so that extrinsics can be submitted faster than they are included.
+
+use ac_primitives::Index;
+use std::sync::{Arc, Mutex};
+
+use crate::std::{ApiClientError, ApiResult};
+
+/// Messages of the node indicating that the nonce of an extrinsic is not the next one of its
+/// signer: `InvalidTransaction::Stale`, `InvalidTransaction::Future` and the `future` status of
+/// a watched extrinsic.
+const NONCE_ERRORS: [&str; 3] = [
+    "Transaction is outdated",
+    "Transaction will be valid in the future",
+    "extrinsic has 'future' status",
+];
+
+/// Hands out consecutive nonces of one signer.
+///
+/// The nonce is fetched from the node once and incremented locally for every extrinsic. Clones
+/// share the counter, so an [`Api`](crate::std::Api) can be cloned into several threads without
+/// two extrinsics getting the same nonce.
+///
+/// A nonce is reserved for good once handed out. If the extrinsic does not reach the node, all
+/// following extrinsics wait in its pool as `Future`, so the nonce has to be fetched again after
+/// any failed submission, see [`Self::resync_on`]. The [`Api`](crate::std::Api) does so itself.
+#[derive(Clone, Debug, Default)]
+pub struct NonceManager {
+    next: Arc<Mutex<Option<Index>>>,
+}
+
+impl NonceManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the nonce of the next extrinsic and reserves it. If the nonce is not known, it is
+    /// fetched with `fetch` first.
+    pub fn next_nonce(&self, fetch: impl FnOnce() -> ApiResult<Index>) -> ApiResult<Index> {
+        let mut next = self.next.lock().expect("Nonce lock is never poisoned; qed");
+        let nonce = match *next {
+            Some(nonce) => nonce,
+            None => fetch()?,
+        };
+        *next = Some(nonce.checked_add(1).ok_or(ApiClientError::NonceOverflow)?);
+        Ok(nonce)
+    }
+
+    /// Forgets the nonce, the next one is fetched from the node again.
+    pub fn resync(&self) {
+        *self.next.lock().expect("Nonce lock is never poisoned; qed") = None;
+    }
+
+    /// Resyncs after the submission of an extrinsic failed with `error`, as the node may not
+    /// have received its nonce. Returns whether `error` indicates that the extrinsic has been
+    /// sent with a stale or future nonce, in which case it has to be composed again.
+    pub fn resync_on(&self, error: &ApiClientError) -> bool {
+        self.resync();
+        is_nonce_error(error)
+    }
+}
+
+/// Whether `error` is the node's answer to an extrinsic with a stale or future nonce.
+fn is_nonce_error(error: &ApiClientError) -> bool {
+    match error {
+        ApiClientError::RpcClient(message) => NONCE_ERRORS.iter().any(|e| message.contains(e)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::assert_matches::assert_matches;
+    use std::thread;
+
+    #[test]
+    fn fetches_once_and_increments_locally() {
+        let manager = NonceManager::new();
+
+        assert_eq!(manager.next_nonce(|| Ok(7)).unwrap(), 7);
+        assert_eq!(manager.next_nonce(|| panic!("nonce is known")).unwrap(), 8);
+        assert_eq!(manager.next_nonce(|| panic!("nonce is known")).unwrap(), 9);
+    }
+
+    #[test]
+    fn failed_fetches_are_retried() {
+        let manager = NonceManager::new();
+
+        assert!(manager.next_nonce(|| Err(ApiClientError::Timeout)).is_err());
+        assert_eq!(manager.next_nonce(|| Ok(3)).unwrap(), 3);
+    }
+
+    #[test]
+    fn fails_instead_of_overflowing() {
+        let manager = NonceManager::new();
+
+        assert_matches!(
+            manager.next_nonce(|| Ok(Index::MAX)),
+            Err(ApiClientError::NonceOverflow)
+        );
+        assert_eq!(manager.next_nonce(|| Ok(3)).unwrap(), 3);
+    }
+
+    #[test]
+    fn resyncs_on_any_error_and_reports_nonce_errors() {
+        let manager = NonceManager::new();
+
+        manager.next_nonce(|| Ok(1)).unwrap();
+        assert!(!manager.resync_on(&ApiClientError::Timeout));
+        assert_eq!(manager.next_nonce(|| Ok(1)).unwrap(), 1);
+
+        let fees = ApiClientError::RpcClient(
+            "Extrinsic Error: extrinsic error code 1010: Invalid Transaction: Inability to pay some fees (e.g. account balance too low)".into(),
+        );
+        assert!(!manager.resync_on(&fees));
+        assert_eq!(manager.next_nonce(|| Ok(2)).unwrap(), 2);
+
+        let stale = ApiClientError::RpcClient(
+            "Extrinsic Error: extrinsic error code 1010: Invalid Transaction: Transaction is outdated".into(),
+        );
+        assert!(manager.resync_on(&stale));
+        assert_eq!(manager.next_nonce(|| Ok(5)).unwrap(), 5);
+    }
+
+    #[test]
+    fn clones_share_the_nonce_across_threads() {
+        let manager = NonceManager::new();
+        manager.next_nonce(|| Ok(0)).unwrap();
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let manager = manager.clone();
+                thread::spawn(move || {
+                    (0..25)
+                        .map(|_| manager.next_nonce(|| panic!("nonce is known")).unwrap())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let mut nonces: Vec<_> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        nonces.sort_unstable();
+
+        assert_eq!(nonces, (1..=100).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,490 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+//! Async WebSocket rpc client running on tokio.
+//!
+//! Mirrors the blocking `WsRpcClient`: a single connection is shared by all requests and
+//! subscriptions, which are multiplexed by their JSON-RPC `id` in a background task.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::mpsc::RecvError;
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use futures::channel::{mpsc, oneshot};
+use futures::stream::BoxStream;
+use futures::{SinkExt, Stream, StreamExt};
+use log::{debug, error, info, warn};
+use serde_json::Value;
+use sp_core::H256 as Hash;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+use crate::std::async_api::decode_notifications;
+use crate::std::rpc::helpers::{
+    parse_status, response_id, result_from_json_response, subscription_id, unsubscribe_method,
+};
+use crate::std::rpc::{json_req, DecodeFn};
+use crate::std::{
+    ApiClientError, ApiResult, AsyncRpcClient, AsyncSubscriber, FromHexString, XtStatus,
+};
+
+/// Async WebSocket rpc client. Clones share the same connection.
+///
+/// Must be created within a tokio runtime, as the connection is served by a spawned task.
+#[derive(Debug, Clone)]
+pub struct AsyncWsRpcClient {
+    to_backend: mpsc::UnboundedSender<FrontendMessage>,
+}
+
+impl AsyncWsRpcClient {
+    pub async fn new(url: &str) -> ApiResult<Self> {
+        let (ws_stream, _) = connect_async(url).await?;
+        info!("connected to {}", url);
+
+        let (to_backend, from_frontend) = mpsc::unbounded();
+        tokio::spawn(run_backend(ws_stream, from_frontend));
+        Ok(Self { to_backend })
+    }
+
+    async fn request(&self, jsonreq: Value) -> ApiResult<String> {
+        let (response_in, response_out) = oneshot::channel();
+        self.send_to_backend(FrontendMessage::Request {
+            jsonreq,
+            response: response_in,
+        })?;
+        response_out
+            .await
+            .map_err(|_| ApiClientError::Disconnected(RecvError))
+    }
+
+    /// Opens a subscription and returns all raw json messages the node sends for it.
+    fn subscribe_raw(&self, jsonreq: Value) -> ApiResult<Notifications> {
+        let (notifications_in, notifications_out) = mpsc::unbounded();
+        self.send_to_backend(FrontendMessage::Subscribe {
+            jsonreq,
+            notifications: notifications_in,
+        })?;
+        Ok(Notifications {
+            notifications: notifications_out,
+            to_backend: self.to_backend.clone(),
+        })
+    }
+
+    fn send_to_backend(&self, msg: FrontendMessage) -> ApiResult<()> {
+        self.to_backend
+            .unbounded_send(msg)
+            .map_err(|_| ApiClientError::Disconnected(RecvError))
+    }
+}
+
+#[async_trait]
+impl AsyncRpcClient for AsyncWsRpcClient {
+    async fn get_request(&self, jsonreq: Value) -> ApiResult<String> {
+        let response = self.request(jsonreq).await?;
+        let value: Value = serde_json::from_str(&response)?;
+        Ok(value["result"].to_string())
+    }
+
+    async fn send_extrinsic(
+        &self,
+        xthex_prefixed: String,
+        exit_on: XtStatus,
+    ) -> ApiResult<Option<Hash>> {
+        if exit_on == XtStatus::SubmitOnly {
+            let jsonreq = json_req::author_submit_extrinsic(&xthex_prefixed);
+            let res = result_from_json_response(&self.request(jsonreq).await?)?;
+            info!("submitted xt: {}", res);
+            return Ok(Some(Hash::from_hex(res)?));
+        }
+        if !matches!(
+            exit_on,
+            XtStatus::Finalized | XtStatus::InBlock | XtStatus::Broadcast | XtStatus::Ready
+        ) {
+            return Err(ApiClientError::UnsupportedXtStatus(exit_on));
+        }
+
+        let jsonreq = json_req::author_submit_and_watch_extrinsic(&xthex_prefixed);
+        // Dropping `updates` on return unwatches the extrinsic.
+        let mut updates = self.subscribe_raw(jsonreq)?;
+        while let Some(msg) = updates.next().await {
+            let (status, value) = parse_status(&msg)?;
+            let reached = match status {
+                XtStatus::Finalized => true,
+                XtStatus::Future => {
+                    warn!("extrinsic has 'future' status. aborting");
+                    return Err(ApiClientError::RpcClient(
+                        "extrinsic has 'future' status".to_string(),
+                    ));
+                }
+                XtStatus::InBlock | XtStatus::Broadcast | XtStatus::Ready => status == exit_on,
+                _ => false,
+            };
+            if reached {
+                info!("{:?}: {:?}", status, value);
+                return match exit_on {
+                    XtStatus::Finalized | XtStatus::InBlock => {
+                        Ok(Some(Hash::from_hex(value.unwrap_or_default())?))
+                    }
+                    _ => Ok(None),
+                };
+            }
+        }
+        Err(ApiClientError::Disconnected(RecvError))
+    }
+}
+
+#[async_trait]
+impl AsyncSubscriber for AsyncWsRpcClient {
+    async fn subscribe<T: Send + 'static>(
+        &self,
+        jsonreq: Value,
+        decode: DecodeFn<T>,
+    ) -> ApiResult<BoxStream<'static, ApiResult<T>>> {
+        let notifications = self.subscribe_raw(jsonreq)?;
+        Ok(decode_notifications(notifications, decode))
+    }
+}
+
+/// Raw json messages of a subscription. Dropping it closes the subscription on the node.
+struct Notifications {
+    notifications: mpsc::UnboundedReceiver<String>,
+    to_backend: mpsc::UnboundedSender<FrontendMessage>,
+}
+
+impl Stream for Notifications {
+    type Item = String;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<String>> {
+        self.notifications.poll_next_unpin(cx)
+    }
+}
+
+impl Drop for Notifications {
+    fn drop(&mut self) {
+        // The closed channel tells the backend which subscription has been dropped.
+        self.notifications.close();
+        let _ = self
+            .to_backend
+            .unbounded_send(FrontendMessage::SubscriptionDropped);
+    }
+}
+
+#[derive(Debug)]
+enum FrontendMessage {
+    Request {
+        jsonreq: Value,
+        response: oneshot::Sender<String>,
+    },
+    Subscribe {
+        jsonreq: Value,
+        notifications: mpsc::UnboundedSender<String>,
+    },
+    SubscriptionDropped,
+}
+
+#[derive(Debug)]
+enum Pending {
+    Request(oneshot::Sender<String>),
+    Subscription {
+        notifications: mpsc::UnboundedSender<String>,
+        unsubscribe_method: &'static str,
+    },
+}
+
+#[derive(Debug)]
+struct Subscription {
+    /// The subscription id exactly as returned by the node.
+    id: Value,
+    notifications: mpsc::UnboundedSender<String>,
+    unsubscribe_method: &'static str,
+}
+
+/// Requests waiting for their response and subscriptions waiting for notifications.
+#[derive(Debug, Default)]
+struct BackendState {
+    last_id: u32,
+    pending: HashMap<u32, Pending>,
+    subscriptions: HashMap<String, Subscription>,
+}
+
+impl BackendState {
+    fn next_id(&mut self) -> u32 {
+        self.last_id = self.last_id.wrapping_add(1);
+        self.last_id
+    }
+
+    /// Handles a message of a client and returns the messages to be sent to the node.
+    fn on_frontend_message(&mut self, msg: FrontendMessage) -> Vec<String> {
+        match msg {
+            FrontendMessage::Request { jsonreq, response } => {
+                let id = self.next_id();
+                self.pending.insert(id, Pending::Request(response));
+                vec![Self::with_id(jsonreq, id)]
+            }
+            FrontendMessage::Subscribe {
+                jsonreq,
+                notifications,
+            } => match jsonreq["method"].as_str().and_then(unsubscribe_method) {
+                Some(unsubscribe_method) => {
+                    let id = self.next_id();
+                    let pending = Pending::Subscription {
+                        notifications,
+                        unsubscribe_method,
+                    };
+                    self.pending.insert(id, pending);
+                    vec![Self::with_id(jsonreq, id)]
+                }
+                None => {
+                    // Dropping `notifications` ends the subscriber's stream right away.
+                    error!("{} is not a known subscription", jsonreq["method"]);
+                    Vec::new()
+                }
+            },
+            FrontendMessage::SubscriptionDropped => self.unsubscribe_dropped(),
+        }
+    }
+
+    fn with_id(mut jsonreq: Value, id: u32) -> String {
+        jsonreq["id"] = Value::String(id.to_string());
+        info!("sending request: {}", jsonreq);
+        jsonreq.to_string()
+    }
+
+    /// Removes the subscriptions whose receiver has been dropped and returns the requests
+    /// closing them on the node. Subscriptions the node has not confirmed yet are closed once
+    /// it does, see [`Self::route`].
+    fn unsubscribe_dropped(&mut self) -> Vec<String> {
+        let dropped: Vec<String> = self
+            .subscriptions
+            .iter()
+            .filter(|(_, s)| s.notifications.is_closed())
+            .map(|(subscription, _)| subscription.clone())
+            .collect();
+
+        let mut unsubscribe_requests = Vec::new();
+        for subscription in dropped {
+            debug!(
+                "Subscription {} has been dropped. will unsubscribe",
+                subscription
+            );
+            if let Some(s) = self.subscriptions.remove(&subscription) {
+                unsubscribe_requests.push(self.unsubscribe(s));
+            }
+        }
+        unsubscribe_requests
+    }
+
+    fn unsubscribe(&mut self, subscription: Subscription) -> String {
+        let id = self.next_id();
+        json_req::unsubscribe_with_id(subscription.unsubscribe_method, subscription.id, id)
+            .to_string()
+    }
+
+    /// Routes a message from the node to its receiver. Returns an unsubscribe request to be sent
+    /// to the node if the receiver of a subscription has been dropped.
+    fn route(&mut self, msg: String) -> Option<String> {
+        let value: Value = match serde_json::from_str(&msg) {
+            Ok(value) => value,
+            Err(e) => {
+                error!("Could not parse message as json: {:?}", e);
+                return None;
+            }
+        };
+
+        if let Some(id) = response_id(&value) {
+            match self.pending.remove(&id) {
+                Some(Pending::Request(response)) => {
+                    let _ = response.send(msg);
+                }
+                Some(Pending::Subscription {
+                    notifications,
+                    unsubscribe_method,
+                }) => {
+                    if let Some(subscription) = subscription_id(&value["result"]) {
+                        debug!("request {} opened subscription {}", id, subscription);
+                        let subscription_entry = Subscription {
+                            id: value["result"].clone(),
+                            notifications: notifications.clone(),
+                            unsubscribe_method,
+                        };
+                        if notifications.is_closed() {
+                            debug!("Subscription {} has already been dropped", subscription);
+                            return Some(self.unsubscribe(subscription_entry));
+                        }
+                        self.subscriptions.insert(subscription, subscription_entry);
+                    }
+                    // Forward the response too, it may carry an error.
+                    let _ = notifications.unbounded_send(msg);
+                }
+                None => debug!("No pending request with id {}", id),
+            }
+            return None;
+        }
+
+        let subscription = subscription_id(&value["params"]["subscription"])?;
+        let closed = match self.subscriptions.get(&subscription) {
+            Some(s) => s.notifications.unbounded_send(msg).is_err(),
+            None => {
+                debug!("No active subscription {}", subscription);
+                return None;
+            }
+        };
+        if !closed {
+            return None;
+        }
+
+        debug!(
+            "Subscription {} has been dropped. will unsubscribe",
+            subscription
+        );
+        let s = self.subscriptions.remove(&subscription)?;
+        Some(self.unsubscribe(s))
+    }
+}
+
+async fn run_backend<S>(ws_stream: S, mut from_frontend: mpsc::UnboundedReceiver<FrontendMessage>)
+where
+    S: futures::Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>>
+        + futures::Sink<Message, Error = tokio_tungstenite::tungstenite::Error>
+        + Unpin,
+{
+    let (mut sink, mut stream) = ws_stream.split();
+    let mut state = BackendState::default();
+
+    loop {
+        let to_node = tokio::select! {
+            msg = from_frontend.next() => match msg {
+                Some(msg) => state.on_frontend_message(msg),
+                // All clients have been dropped.
+                None => break,
+            },
+            msg = stream.next() => match msg {
+                Some(Ok(Message::Text(msg))) => state.route(msg).into_iter().collect(),
+                Some(Ok(Message::Close(frame))) => {
+                    info!("WebSocket connection closed: {:?}", frame);
+                    break;
+                }
+                Some(Ok(_)) => Vec::new(),
+                Some(Err(e)) => {
+                    error!("WebSocket connection terminated with error: {:?}", e);
+                    break;
+                }
+                None => break,
+            },
+        };
+
+        for msg in to_node {
+            if let Err(e) = sink.send(Message::Text(msg)).await {
+                error!("Could not send message: {:?}", e);
+                return;
+            }
+        }
+    }
+    // Dropping the state closes all channels, which unblocks everyone still waiting for an answer.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_gets_unique_id_and_response_is_routed_back() {
+        let mut state = BackendState::default();
+        let (response_in, mut response_out) = oneshot::channel();
+
+        let sent = state.on_frontend_message(FrontendMessage::Request {
+            jsonreq: json_req::chain_get_genesis_hash(),
+            response: response_in,
+        });
+        let sent: Value = serde_json::from_str(&sent[0]).unwrap();
+        assert_eq!(sent["id"], "1");
+
+        let response = r#"{"jsonrpc":"2.0","result":"0x00","id":"1"}"#;
+        assert_eq!(state.route(response.to_string()), None);
+        assert_eq!(response_out.try_recv().unwrap().unwrap(), response);
+    }
+
+    #[test]
+    fn subscription_is_unsubscribed_on_notification_after_drop() {
+        let mut state = BackendState::default();
+        let (notifications_in, notifications_out) = mpsc::unbounded();
+
+        state.on_frontend_message(FrontendMessage::Subscribe {
+            jsonreq: json_req::chain_subscribe_finalized_heads(),
+            notifications: notifications_in,
+        });
+        state.route(r#"{"jsonrpc":"2.0","result":"SXuvtB4Bbr4ImLAb","id":"1"}"#.to_string());
+        drop(notifications_out);
+
+        let notification = r#"{"jsonrpc":"2.0","method":"chain_finalizedHead","params":{"result":{},"subscription":"SXuvtB4Bbr4ImLAb"}}"#;
+        let unsubscribe: Value =
+            serde_json::from_str(&state.route(notification.to_string()).unwrap()).unwrap();
+        assert_eq!(unsubscribe["method"], "chain_unsubscribeFinalizedHeads");
+        assert_eq!(unsubscribe["params"][0], "SXuvtB4Bbr4ImLAb");
+        assert!(state.subscriptions.is_empty());
+    }
+
+    #[test]
+    fn dropping_the_notifications_unsubscribes() {
+        let mut state = BackendState::default();
+        let (to_backend, mut from_frontend) = mpsc::unbounded();
+        let (notifications_in, notifications_out) = mpsc::unbounded();
+
+        state.on_frontend_message(FrontendMessage::Subscribe {
+            jsonreq: json_req::chain_subscribe_finalized_heads(),
+            notifications: notifications_in,
+        });
+        state.route(r#"{"jsonrpc":"2.0","result":"SXuvtB4Bbr4ImLAb","id":"1"}"#.to_string());
+        drop(Notifications {
+            notifications: notifications_out,
+            to_backend,
+        });
+
+        let msg = from_frontend.try_next().unwrap().unwrap();
+        assert!(matches!(msg, FrontendMessage::SubscriptionDropped));
+        let sent = state.on_frontend_message(msg);
+        assert_eq!(sent.len(), 1);
+        let unsubscribe: Value = serde_json::from_str(&sent[0]).unwrap();
+        assert_eq!(unsubscribe["method"], "chain_unsubscribeFinalizedHeads");
+        assert_eq!(unsubscribe["params"][0], "SXuvtB4Bbr4ImLAb");
+        assert!(state.subscriptions.is_empty());
+    }
+
+    #[test]
+    fn subscription_dropped_before_it_is_confirmed_is_unsubscribed() {
+        let mut state = BackendState::default();
+        let (notifications_in, notifications_out) = mpsc::unbounded();
+
+        state.on_frontend_message(FrontendMessage::Subscribe {
+            jsonreq: json_req::author_submit_and_watch_extrinsic("0x00"),
+            notifications: notifications_in,
+        });
+        drop(notifications_out);
+        assert!(state
+            .on_frontend_message(FrontendMessage::SubscriptionDropped)
+            .is_empty());
+
+        let unwatch = state
+            .route(r#"{"jsonrpc":"2.0","result":"SXuvtB4Bbr4ImLAb","id":"1"}"#.to_string())
+            .unwrap();
+        let unwatch: Value = serde_json::from_str(&unwatch).unwrap();
+        assert_eq!(unwatch["method"], "author_unwatchExtrinsic");
+        assert_eq!(unwatch["params"][0], "SXuvtB4Bbr4ImLAb");
+        assert!(state.subscriptions.is_empty());
+    }
+}
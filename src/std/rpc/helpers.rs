@@ -0,0 +1,340 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+//! Transport independent helpers to interpret the node's JSON-RPC responses.
+
+use log::{debug, info};
+use serde_json::Value;
+
+use crate::std::rpc::{DecodeFn, RpcClientError, RpcResult, XtStatus};
+use crate::std::{ApiClientError, ApiResult};
+
+pub(crate) fn parse_status(msg: &str) -> RpcResult<(XtStatus, Option<String>)> {
+    let value: serde_json::Value = serde_json::from_str(msg)?;
+
+    if value["error"].as_object().is_some() {
+        return Err(into_extrinsic_err(&value));
+    }
+
+    match value["params"]["result"].as_object() {
+        Some(obj) => {
+            if let Some(hash) = obj.get("finalized") {
+                info!("finalized: {:?}", hash);
+                Ok((XtStatus::Finalized, Some(hash.to_string())))
+            } else if let Some(hash) = obj.get("inBlock") {
+                info!("inBlock: {:?}", hash);
+                Ok((XtStatus::InBlock, Some(hash.to_string())))
+            } else if let Some(array) = obj.get("broadcast") {
+                info!("broadcast: {:?}", array);
+                Ok((XtStatus::Broadcast, Some(array.to_string())))
+            } else if let Some(hash) = obj.get("usurped") {
+                Err(RpcClientError::Extrinsic(format!(
+                    "extrinsic has been usurped by {}",
+                    hash
+                )))
+            } else if let Some(hash) = obj.get("finalityTimeout") {
+                Err(RpcClientError::Extrinsic(format!(
+                    "finality timeout in block {}",
+                    hash
+                )))
+            } else {
+                // `retracted`: the extrinsic goes back into the pool and is reported again.
+                Ok((XtStatus::Unknown, None))
+            }
+        }
+        None => match value["params"]["result"].as_str() {
+            Some("ready") => Ok((XtStatus::Ready, None)),
+            Some("future") => Ok((XtStatus::Future, None)),
+            Some("dropped") => Err(RpcClientError::Extrinsic(
+                "extrinsic has been dropped from the pool".to_string(),
+            )),
+            Some("invalid") => Err(RpcClientError::Extrinsic(
+                "extrinsic is no longer valid".to_string(),
+            )),
+            Some(&_) => Ok((XtStatus::Unknown, None)),
+            None => Ok((XtStatus::Unknown, None)),
+        },
+    }
+}
+
+/// Todo: this is the code that was used in `parse_status` Don't we want to just print the
+/// error as is instead of introducing our custom format here?
+pub(crate) fn into_extrinsic_err(resp_with_err: &Value) -> RpcClientError {
+    let err_obj = resp_with_err["error"].as_object().unwrap();
+
+    let error = err_obj
+        .get("message")
+        .map_or_else(|| "", |e| e.as_str().unwrap());
+    let code = err_obj
+        .get("code")
+        .map_or_else(|| -1, |c| c.as_i64().unwrap());
+    let details = err_obj
+        .get("data")
+        .map_or_else(|| "", |d| d.as_str().unwrap());
+
+    RpcClientError::Extrinsic(format!(
+        "extrinsic error code {}: {}: {}",
+        code, error, details
+    ))
+}
+
+pub(crate) fn result_from_json_response(resp: &str) -> RpcResult<String> {
+    let value: serde_json::Value = serde_json::from_str(resp)?;
+
+    let resp = value["result"]
+        .as_str()
+        .ok_or_else(|| into_extrinsic_err(&value))?;
+
+    Ok(resp.to_string())
+}
+
+/// Returns the `result` of the response `resp` as JSON, or an [`ApiClientError::RpcClient`]
+/// holding the error the node responded with.
+pub(crate) fn result_or_error(resp: &str) -> ApiResult<String> {
+    let value: Value = serde_json::from_str(resp)?;
+    if !value["error"].is_null() {
+        return Err(ApiClientError::RpcClient(value["error"].to_string()));
+    }
+    Ok(value["result"].to_string())
+}
+
+/// Our requests are sent with string ids, but nodes may answer with numbers as well.
+pub(crate) fn response_id(value: &Value) -> Option<u32> {
+    match &value["id"] {
+        Value::String(id) => id.parse().ok(),
+        Value::Number(id) => id.as_u64().and_then(|id| id.try_into().ok()),
+        _ => None,
+    }
+}
+
+pub(crate) fn subscription_id(value: &Value) -> Option<String> {
+    match value {
+        Value::String(id) => Some(id.clone()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// Returns the method that closes a subscription opened by `subscribe_method`, or `None` if
+/// `subscribe_method` is a plain request.
+pub(crate) fn unsubscribe_method(subscribe_method: &str) -> Option<&'static str> {
+    match subscribe_method {
+        "author_submitAndWatchExtrinsic" => Some("author_unwatchExtrinsic"),
+        "chain_subscribeAllHeads" => Some("chain_unsubscribeAllHeads"),
+        "chain_subscribeNewHeads" => Some("chain_unsubscribeNewHeads"),
+        "chain_subscribeFinalizedHeads" => Some("chain_unsubscribeFinalizedHeads"),
+        "state_subscribeRuntimeVersion" => Some("state_unsubscribeRuntimeVersion"),
+        "state_subscribeStorage" => Some("state_unsubscribeStorage"),
+        _ => None,
+    }
+}
+
+/// Decodes a message received for a subscription with `decode`. Returns `Ok(None)` for the
+/// response confirming the subscription and an [`ApiClientError::RpcClient`] for errors.
+pub(crate) fn decode_notification<T>(msg: &str, decode: &DecodeFn<T>) -> ApiResult<Option<T>> {
+    let mut value: Value = serde_json::from_str(msg)?;
+    if !value["error"].is_null() {
+        return Err(ApiClientError::RpcClient(value["error"].to_string()));
+    }
+    if !value["id"].is_null() {
+        debug!("subscription confirmed: {}", msg);
+        return Ok(None);
+    }
+    decode(value["params"]["result"].take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::assert_matches::assert_matches;
+    use std::fmt::Debug;
+
+    fn assert_extrinsic_err<T: Debug>(result: Result<T, RpcClientError>, msg: &str) {
+        assert_matches!(result.unwrap_err(), RpcClientError::Extrinsic(
+			m,
+		) if &m == msg)
+    }
+
+    #[test]
+    fn result_from_json_response_works() {
+        let msg = r#"{"jsonrpc":"2.0","result":"0xe7640c3e8ba8d10ed7fed07118edb0bfe2d765d3ea2f3a5f6cf781ae3237788f","id":"3"}"#;
+
+        assert_eq!(
+            result_from_json_response(msg).unwrap(),
+            "0xe7640c3e8ba8d10ed7fed07118edb0bfe2d765d3ea2f3a5f6cf781ae3237788f"
+        );
+    }
+
+    #[test]
+    fn result_from_json_response_errs_on_error_response() {
+        let _err_raw =
+            r#"{"code":-32602,"message":"Invalid params: invalid hex character: h, at 284."}"#;
+
+        let err_msg = format!(
+            "extrinsic error code {}: {}: {}",
+            -32602, "Invalid params: invalid hex character: h, at 284.", ""
+        );
+
+        let msg = r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params: invalid hex character: h, at 284."},"id":"3"}"#;
+
+        assert_extrinsic_err(result_from_json_response(msg), &err_msg)
+    }
+
+    #[test]
+    fn node_errors_for_wrong_nonces_are_recognized() {
+        use crate::std::NonceManager;
+        use sp_runtime::transaction_validity::InvalidTransaction;
+
+        let manager = NonceManager::new();
+        // The node reports invalid transactions with the description of `InvalidTransaction`.
+        for (invalid, is_nonce_error) in [
+            (InvalidTransaction::Stale, true),
+            (InvalidTransaction::Future, true),
+            (InvalidTransaction::Payment, false),
+            (InvalidTransaction::BadProof, false),
+        ] {
+            let description: &str = invalid.into();
+            let response = json!({
+                "jsonrpc": "2.0",
+                "error": {"code": 1010, "message": "Invalid Transaction", "data": description},
+                "id": "1",
+            });
+            let error = ApiClientError::from(into_extrinsic_err(&response));
+            assert_eq!(manager.resync_on(&error), is_nonce_error, "{}", description);
+        }
+    }
+
+    #[test]
+    fn extrinsic_status_parsed_correctly() {
+        let msg = "{\"jsonrpc\":\"2.0\",\"result\":7185,\"id\":\"3\"}";
+        assert_eq!(parse_status(msg).unwrap(), (XtStatus::Unknown, None));
+
+        let msg = "{\"jsonrpc\":\"2.0\",\"method\":\"author_extrinsicUpdate\",\"params\":{\"result\":\"ready\",\"subscription\":7185}}";
+        assert_eq!(parse_status(msg).unwrap(), (XtStatus::Ready, None));
+
+        let msg = "{\"jsonrpc\":\"2.0\",\"method\":\"author_extrinsicUpdate\",\"params\":{\"result\":{\"broadcast\":[\"QmfSF4VYWNqNf5KYHpDEdY8Rt1nPUgSkMweDkYzhSWirGY\",\"Qmchhx9SRFeNvqjUK4ZVQ9jH4zhARFkutf9KhbbAmZWBLx\",\"QmQJAqr98EF1X3YfjVKNwQUG9RryqX4Hv33RqGChbz3Ncg\"]},\"subscription\":232}}";
+        assert_eq!(
+            parse_status(msg).unwrap(),
+            (
+                XtStatus::Broadcast,
+                Some(
+                    "[\"QmfSF4VYWNqNf5KYHpDEdY8Rt1nPUgSkMweDkYzhSWirGY\",\"Qmchhx9SRFeNvqjUK4ZVQ9jH4zhARFkutf9KhbbAmZWBLx\",\"QmQJAqr98EF1X3YfjVKNwQUG9RryqX4Hv33RqGChbz3Ncg\"]"
+                        .to_string()
+                )
+            )
+        );
+
+        let msg = "{\"jsonrpc\":\"2.0\",\"method\":\"author_extrinsicUpdate\",\"params\":{\"result\":{\"inBlock\":\"0x3104d362365ff5ddb61845e1de441b56c6722e94c1aee362f8aa8ba75bd7a3aa\"},\"subscription\":232}}";
+        assert_eq!(
+            parse_status(msg).unwrap(),
+            (
+                XtStatus::InBlock,
+                Some(
+                    "\"0x3104d362365ff5ddb61845e1de441b56c6722e94c1aee362f8aa8ba75bd7a3aa\""
+                        .to_string()
+                )
+            )
+        );
+
+        let msg = "{\"jsonrpc\":\"2.0\",\"method\":\"author_extrinsicUpdate\",\"params\":{\"result\":{\"finalized\":\"0x934385b11c483498e2b5bca64c2e8ef76ad6c74d3372a05595d3a50caf758d52\"},\"subscription\":7185}}";
+        assert_eq!(
+            parse_status(msg).unwrap(),
+            (
+                XtStatus::Finalized,
+                Some(
+                    "\"0x934385b11c483498e2b5bca64c2e8ef76ad6c74d3372a05595d3a50caf758d52\""
+                        .to_string()
+                )
+            )
+        );
+
+        let msg = "{\"jsonrpc\":\"2.0\",\"method\":\"author_extrinsicUpdate\",\"params\":{\"result\":\"future\",\"subscription\":2}}";
+        assert_eq!(parse_status(msg).unwrap(), (XtStatus::Future, None));
+
+        let msg = r#"{"jsonrpc":"2.0","method":"author_extrinsicUpdate","params":{"result":{"retracted":"0x3104d362365ff5ddb61845e1de441b56c6722e94c1aee362f8aa8ba75bd7a3aa"},"subscription":2}}"#;
+        assert_eq!(parse_status(msg).unwrap(), (XtStatus::Unknown, None));
+
+        let msg = r#"{"jsonrpc":"2.0","method":"author_extrinsicUpdate","params":{"result":"dropped","subscription":2}}"#;
+        assert_extrinsic_err(
+            parse_status(msg),
+            "extrinsic has been dropped from the pool",
+        );
+
+        let msg = r#"{"jsonrpc":"2.0","method":"author_extrinsicUpdate","params":{"result":"invalid","subscription":2}}"#;
+        assert_extrinsic_err(parse_status(msg), "extrinsic is no longer valid");
+
+        let msg = r#"{"jsonrpc":"2.0","method":"author_extrinsicUpdate","params":{"result":{"usurped":"0x01"},"subscription":2}}"#;
+        assert_extrinsic_err(parse_status(msg), "extrinsic has been usurped by \"0x01\"");
+
+        let msg = "{\"jsonrpc\":\"2.0\",\"error\":{\"code\":-32700,\"message\":\"Parse error\"},\"id\":null}";
+        assert_extrinsic_err(
+            parse_status(msg),
+            "extrinsic error code -32700: Parse error: ",
+        );
+
+        let msg = "{\"jsonrpc\":\"2.0\",\"error\":{\"code\":1010,\"message\":\"Invalid Transaction\",\"data\":\"Bad Signature\"},\"id\":\"4\"}";
+        assert_extrinsic_err(
+            parse_status(msg),
+            "extrinsic error code 1010: Invalid Transaction: Bad Signature",
+        );
+
+        let msg = "{\"jsonrpc\":\"2.0\",\"error\":{\"code\":1001,\"message\":\"Extrinsic has invalid format.\"},\"id\":\"0\"}";
+        assert_extrinsic_err(
+            parse_status(msg),
+            "extrinsic error code 1001: Extrinsic has invalid format.: ",
+        );
+
+        let msg = r#"{"jsonrpc":"2.0","error":{"code":1002,"message":"Verification Error: Execution(Wasmi(Trap(Trap { kind: Unreachable })))","data":"RuntimeApi(\"Execution(Wasmi(Trap(Trap { kind: Unreachable })))\")"},"id":"3"}"#;
+        assert_extrinsic_err(
+            parse_status(msg),
+            "extrinsic error code 1002: Verification Error: Execution(Wasmi(Trap(Trap { kind: Unreachable }))): RuntimeApi(\"Execution(Wasmi(Trap(Trap { kind: Unreachable })))\")"
+        );
+    }
+
+    #[test]
+    fn response_id_accepts_string_and_number_ids() {
+        assert_eq!(response_id(&json!({"result": "0x00", "id": "7"})), Some(7));
+        assert_eq!(response_id(&json!({"result": "0x00", "id": 7})), Some(7));
+        assert_eq!(response_id(&json!({"method": "state_storage"})), None);
+    }
+
+    #[test]
+    fn subscription_id_accepts_string_and_number_ids() {
+        let msg = json!({"params": {"result": "ready", "subscription": 7185}});
+        assert_eq!(
+            subscription_id(&msg["params"]["subscription"]),
+            Some("7185".to_string())
+        );
+
+        let msg = json!({"params": {"result": "ready", "subscription": "SXuvtB4Bbr4ImLAb"}});
+        assert_eq!(
+            subscription_id(&msg["params"]["subscription"]),
+            Some("SXuvtB4Bbr4ImLAb".to_string())
+        );
+    }
+
+    #[test]
+    fn only_subscriptions_have_an_unsubscribe_method() {
+        assert_eq!(
+            unsubscribe_method("author_submitAndWatchExtrinsic"),
+            Some("author_unwatchExtrinsic")
+        );
+        assert_eq!(unsubscribe_method("author_submitExtrinsic"), None);
+        assert_eq!(unsubscribe_method("state_getStorage"), None);
+    }
+}
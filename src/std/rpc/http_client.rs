@@ -0,0 +1,373 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+//! Blocking HTTP rpc client, available with the `http-client` feature.
+//!
+//! HTTP does not support subscriptions, hence extrinsics can only be sent with
+//! [`XtStatus::SubmitOnly`].
+
+use std::error::Error;
+use std::io;
+use std::time::Duration;
+
+use log::{debug, info};
+use serde_json::Value;
+use sp_core::H256 as Hash;
+use ureq::{Agent, AgentBuilder};
+
+use crate::std::rpc::helpers::{result_from_json_response, result_or_error};
+use crate::std::rpc::json_req;
+use crate::std::{ApiClientError, ApiResult, FromHexString, RpcClient, XtStatus};
+
+#[derive(Debug, Clone)]
+pub struct HttpRpcClient {
+    url: String,
+    agent: Agent,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    headers: Vec<(String, String)>,
+}
+
+impl HttpRpcClient {
+    pub fn new(url: &str) -> HttpRpcClient {
+        HttpRpcClient {
+            url: url.to_string(),
+            agent: Agent::new(),
+            timeout: None,
+            connect_timeout: None,
+            headers: Vec::new(),
+        }
+    }
+
+    /// Sets the maximum duration of a whole request, from connecting until the response has
+    /// been read.
+    #[must_use]
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self.agent = self.build_agent();
+        self
+    }
+
+    /// Sets the maximum duration for establishing the connection to the node.
+    #[must_use]
+    pub fn set_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self.agent = self.build_agent();
+        self
+    }
+
+    /// Adds a header that is sent with every request, e.g. for authentication.
+    #[must_use]
+    pub fn set_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Builds the agent from all settings, so none is lost when it is rebuilt.
+    fn build_agent(&self) -> Agent {
+        let mut builder = AgentBuilder::new();
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.timeout_connect(timeout);
+        }
+        builder.build()
+    }
+
+    fn post(&self, jsonreq: Value, timeout: Option<Duration>) -> ApiResult<String> {
+        let mut request = self.agent.post(&self.url);
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+
+        debug!("sending request: {}", jsonreq);
+        let response = request.send_json(jsonreq).map_err(|e| {
+            if is_timeout(&e) {
+                ApiClientError::Timeout
+            } else {
+                ApiClientError::Http(Box::new(e))
+            }
+        })?;
+        let response = response.into_string().map_err(|e| {
+            if is_timeout(&e) {
+                ApiClientError::Timeout
+            } else {
+                ApiClientError::Other(e.into())
+            }
+        })?;
+        debug!("got response: {}", response);
+        Ok(response)
+    }
+
+    fn get_request_until(&self, jsonreq: Value, timeout: Option<Duration>) -> ApiResult<String> {
+        let response: Value = serde_json::from_str(&self.post(jsonreq, timeout)?)?;
+        Ok(response["result"].to_string())
+    }
+
+    fn submit_extrinsic(
+        &self,
+        xthex_prefixed: String,
+        exit_on: XtStatus,
+        timeout: Option<Duration>,
+    ) -> ApiResult<Option<Hash>> {
+        if exit_on != XtStatus::SubmitOnly {
+            return Err(ApiClientError::UnsupportedXtStatus(exit_on));
+        }
+
+        let jsonreq = json_req::author_submit_extrinsic(&xthex_prefixed);
+        let xt_hash = result_from_json_response(&self.post(jsonreq, timeout)?)?;
+        info!("submitted xt: {}", xt_hash);
+        Ok(Some(Hash::from_hex(xt_hash)?))
+    }
+}
+
+impl RpcClient for HttpRpcClient {
+    fn get_request(&self, jsonreq: Value) -> ApiResult<String> {
+        self.get_request_until(jsonreq, self.timeout)
+    }
+
+    fn get_request_or_error(&self, jsonreq: Value) -> ApiResult<String> {
+        result_or_error(&self.post(jsonreq, self.timeout)?)
+    }
+
+    /// Submits the extrinsic and returns its hash. Only [`XtStatus::SubmitOnly`] is supported.
+    fn send_extrinsic(&self, xthex_prefixed: String, exit_on: XtStatus) -> ApiResult<Option<Hash>> {
+        self.submit_extrinsic(xthex_prefixed, exit_on, self.timeout)
+    }
+
+    fn get_request_with_timeout(&self, jsonreq: Value, timeout: Duration) -> ApiResult<String> {
+        self.get_request_until(jsonreq, Some(timeout))
+    }
+
+    fn send_extrinsic_with_timeout(
+        &self,
+        xthex_prefixed: String,
+        exit_on: XtStatus,
+        timeout: Duration,
+    ) -> ApiResult<Option<Hash>> {
+        self.submit_extrinsic(xthex_prefixed, exit_on, Some(timeout))
+    }
+}
+
+/// Returns true if `error` has been caused by a timed out socket operation.
+fn is_timeout(error: &(dyn Error + 'static)) -> bool {
+    let mut source = Some(error);
+    while let Some(e) = source {
+        if let Some(e) = e.downcast_ref::<io::Error>() {
+            if matches!(
+                e.kind(),
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+            ) {
+                return true;
+            }
+        }
+        source = e.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::assert_matches::assert_matches;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    /// Serves a single request with `response_body` after `delay`. Returns the server's url and
+    /// a handle that yields the received request.
+    fn mock_node(response_body: &'static str, delay: Duration) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(len) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = len.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body = vec![0u8; content_length];
+            reader.read_exact(&mut body).unwrap();
+            request.push_str(&String::from_utf8(body).unwrap());
+
+            thread::sleep(delay);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response_body.len(),
+                response_body
+            );
+            request
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn get_request_returns_result_and_sends_headers() {
+        let (url, node) = mock_node(
+            r#"{"jsonrpc":"2.0","result":"0xe7640c3e8ba8d10ed7fed07118edb0bfe2d765d3ea2f3a5f6cf781ae3237788f","id":"1"}"#,
+            Duration::ZERO,
+        );
+        let client = HttpRpcClient::new(&url).set_header("X-Api-Key", "secret");
+
+        let result = client
+            .get_request(json_req::chain_get_genesis_hash())
+            .unwrap();
+        assert_eq!(
+            result,
+            "\"0xe7640c3e8ba8d10ed7fed07118edb0bfe2d765d3ea2f3a5f6cf781ae3237788f\""
+        );
+
+        let request = node.join().unwrap().to_lowercase();
+        assert!(request.starts_with("post / http/1.1"));
+        assert!(request.contains("x-api-key: secret"));
+        assert!(request.contains(r#""method":"chain_getblockhash""#));
+    }
+
+    #[test]
+    fn get_request_returns_null_on_error_response() {
+        let (url, _node) = mock_node(
+            r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":"1"}"#,
+            Duration::ZERO,
+        );
+        let client = HttpRpcClient::new(&url);
+
+        let result = client.get_request(json_req::state_get_metadata()).unwrap();
+        assert_eq!(result, "null");
+    }
+
+    #[test]
+    fn get_request_or_error_returns_the_error_of_the_node() {
+        let (url, _node) = mock_node(
+            r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":"1"}"#,
+            Duration::ZERO,
+        );
+        let client = HttpRpcClient::new(&url);
+
+        let result = client.get_request_or_error(json_req::state_call("Foo_bar", &[], None));
+        assert_matches!(result, Err(ApiClientError::RpcClient(msg)) if msg.contains("Method not found"));
+    }
+
+    #[test]
+    fn send_extrinsic_submit_only_returns_extrinsic_hash() {
+        let (url, node) = mock_node(
+            r#"{"jsonrpc":"2.0","result":"0xe7640c3e8ba8d10ed7fed07118edb0bfe2d765d3ea2f3a5f6cf781ae3237788f","id":"3"}"#,
+            Duration::ZERO,
+        );
+        let client = HttpRpcClient::new(&url);
+
+        let xt_hash = client
+            .send_extrinsic("0x0102".to_string(), XtStatus::SubmitOnly)
+            .unwrap();
+        assert_eq!(
+            xt_hash,
+            Some(
+                Hash::from_hex(
+                    "0xe7640c3e8ba8d10ed7fed07118edb0bfe2d765d3ea2f3a5f6cf781ae3237788f"
+                        .to_string()
+                )
+                .unwrap()
+            )
+        );
+        assert!(node
+            .join()
+            .unwrap()
+            .contains(r#""method":"author_submitExtrinsic""#));
+    }
+
+    #[test]
+    fn send_extrinsic_errs_on_invalid_transaction() {
+        let (url, _node) = mock_node(
+            r#"{"jsonrpc":"2.0","error":{"code":1010,"message":"Invalid Transaction","data":"Bad Signature"},"id":"3"}"#,
+            Duration::ZERO,
+        );
+        let client = HttpRpcClient::new(&url);
+
+        let result = client.send_extrinsic("0x0102".to_string(), XtStatus::SubmitOnly);
+        assert_matches!(result, Err(ApiClientError::RpcClient(msg)) if msg.contains("Bad Signature"));
+    }
+
+    #[test]
+    fn send_extrinsic_rejects_statuses_that_need_a_subscription() {
+        let client = HttpRpcClient::new("http://127.0.0.1:1");
+
+        let result = client.send_extrinsic("0x0102".to_string(), XtStatus::InBlock);
+        assert_matches!(
+            result,
+            Err(ApiClientError::UnsupportedXtStatus(XtStatus::InBlock))
+        );
+    }
+
+    #[test]
+    fn request_times_out() {
+        let (url, _node) = mock_node(
+            r#"{"jsonrpc":"2.0","result":null,"id":"1"}"#,
+            Duration::from_secs(2),
+        );
+        let client = HttpRpcClient::new(&url).set_timeout(Duration::from_millis(100));
+
+        let result = client.get_request(json_req::chain_get_finalized_head());
+        assert_matches!(result, Err(ApiClientError::Timeout));
+    }
+
+    #[test]
+    fn connect_timeout_keeps_the_timeout() {
+        let (url, _node) = mock_node(
+            r#"{"jsonrpc":"2.0","result":null,"id":"1"}"#,
+            Duration::from_secs(2),
+        );
+        let client = HttpRpcClient::new(&url)
+            .set_timeout(Duration::from_millis(100))
+            .set_connect_timeout(Duration::from_secs(1));
+
+        assert_eq!(client.timeout, Some(Duration::from_millis(100)));
+        assert_eq!(client.connect_timeout, Some(Duration::from_secs(1)));
+        // The agent itself times out, without the per request timeout.
+        let result = client.agent.post(&url).send_string("{}");
+        assert!(is_timeout(&result.unwrap_err()));
+    }
+
+    #[test]
+    fn per_call_timeout_overrides_client_timeout() {
+        let (url, _node) = mock_node(
+            r#"{"jsonrpc":"2.0","result":null,"id":"1"}"#,
+            Duration::from_secs(2),
+        );
+        let client = HttpRpcClient::new(&url).set_timeout(Duration::from_secs(10));
+
+        let result = client.get_request_with_timeout(
+            json_req::chain_get_finalized_head(),
+            Duration::from_millis(100),
+        );
+        assert_matches!(result, Err(ApiClientError::Timeout));
+    }
+}
@@ -17,8 +17,10 @@
 
 use serde::Serialize;
 use serde_json::{json, to_value, Value};
+use sp_core::crypto::Ss58Codec;
 use sp_core::storage::StorageKey;
 use sp_core::H256 as Hash;
+use sp_runtime::AccountId32 as AccountId;
 
 pub const REQUEST_TRANSFER: u32 = 3;
 
@@ -88,6 +90,14 @@ pub fn state_get_runtime_version_with_id(id: u32) -> Value {
     json_req("state_getRuntimeVersion", vec![Value::Null], id)
 }
 
+pub fn state_subscribe_runtime_version() -> Value {
+    state_subscribe_runtime_version_with_id(1)
+}
+
+pub fn state_subscribe_runtime_version_with_id(id: u32) -> Value {
+    json_req("state_subscribeRuntimeVersion", Value::Null, id)
+}
+
 pub fn state_subscribe_storage(key: Vec<StorageKey>) -> Value {
     state_subscribe_storage_with_id(key, 1)
 }
@@ -113,6 +123,22 @@ pub fn state_get_storage_with_id(key: StorageKey, at_block: Option<Hash>, id: u3
     )
 }
 
+pub fn state_call(method: &str, data: &[u8], at_block: Option<Hash>) -> Value {
+    state_call_with_id(method, data, at_block, 1)
+}
+
+pub fn state_call_with_id(method: &str, data: &[u8], at_block: Option<Hash>, id: u32) -> Value {
+    json_req(
+        "state_call",
+        vec![
+            to_value(method).unwrap(),
+            to_value(format!("0x{}", hex::encode(data))).unwrap(),
+            to_value(at_block).unwrap(),
+        ],
+        id,
+    )
+}
+
 pub fn state_get_read_proof(keys: Vec<StorageKey>, at_block: Option<Hash>) -> Value {
     json_req(
         "state_getReadProof",
@@ -129,6 +155,10 @@ pub fn state_get_keys(key: StorageKey, at_block: Option<Hash>) -> Value {
     )
 }
 
+pub fn system_account_next_index(account: &AccountId) -> Value {
+    json_req("system_accountNextIndex", vec![account.to_ss58check()], 1)
+}
+
 pub fn author_submit_extrinsic(xthex_prefixed: &str) -> Value {
     author_submit_extrinsic_with_id(xthex_prefixed, REQUEST_TRANSFER)
 }
@@ -145,6 +175,10 @@ pub fn author_submit_and_watch_extrinsic_with_id(xthex_prefixed: &str, id: u32)
     json_req("author_submitAndWatchExtrinsic", vec![xthex_prefixed], id)
 }
 
+pub fn unsubscribe_with_id(method: &str, subscription: Value, id: u32) -> Value {
+    json_req(method, vec![subscription], id)
+}
+
 fn json_req<S: Serialize>(method: &str, params: S, id: u32) -> Value {
     json!({
         "method": method,
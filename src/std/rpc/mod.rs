@@ -15,13 +15,30 @@
 
 */
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
+use crate::std::ApiResult;
+
+#[cfg(feature = "async-client")]
+pub use async_ws_client::AsyncWsRpcClient;
+#[cfg(feature = "http-client")]
+pub use http_client::HttpRpcClient;
 #[cfg(feature = "ws-client")]
 pub use ws_client::WsRpcClient;
 
+#[cfg(feature = "async-client")]
+pub mod async_ws_client;
+#[cfg(feature = "http-client")]
+pub mod http_client;
 #[cfg(feature = "ws-client")]
 pub mod ws_client;
 
+#[cfg(any(
+    feature = "ws-client",
+    feature = "async-client",
+    feature = "http-client"
+))]
+pub(crate) mod helpers;
 pub mod json_req;
 
 #[derive(Debug, thiserror::Error)]
@@ -34,6 +51,12 @@ pub enum RpcClientError {
     Send(#[from] std::sync::mpsc::SendError<String>),
 }
 
+pub(crate) type RpcResult<T> = Result<T, RpcClientError>;
+
+/// Decodes the `result` of a subscription notification. Returns `Ok(None)` for notifications
+/// that do not carry an item.
+pub type DecodeFn<T> = Box<dyn Fn(Value) -> ApiResult<Option<T>> + Send>;
+
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum XtStatus {
     // Todo: some variants to not return a hash with `send_extrinsics`: #175.
@@ -48,6 +71,50 @@ pub enum XtStatus {
     Unknown,
 }
 
+/// Possible statuses of a watched extrinsic, as reported by `author_submitAndWatchExtrinsic`.
+// Exact structure from
+// https://github.com/paritytech/substrate/blob/master/client/transaction-pool/api/src/lib.rs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransactionStatus<Hash, BlockHash> {
+    /// Transaction is part of the future queue.
+    Future,
+    /// Transaction is part of the ready queue.
+    Ready,
+    /// The transaction has been broadcast to the given peers.
+    Broadcast(Vec<String>),
+    /// Transaction has been included in block with given hash.
+    InBlock(BlockHash),
+    /// The block this transaction was included in has been retracted.
+    Retracted(BlockHash),
+    /// Maximum number of finality watchers has been reached,
+    /// old watchers are being removed.
+    FinalityTimeout(BlockHash),
+    /// Transaction has been finalized by a finality-gadget, e.g GRANDPA
+    Finalized(BlockHash),
+    /// Transaction has been replaced in the pool, by another transaction
+    /// that provides the same tags. (e.g. same (sender, nonce)).
+    Usurped(Hash),
+    /// Transaction has been dropped from the pool because of the limit.
+    Dropped,
+    /// Transaction is no longer valid in the current state.
+    Invalid,
+}
+
+impl<Hash, BlockHash> TransactionStatus<Hash, BlockHash> {
+    /// Returns true if the node will not report any further status of the extrinsic.
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            TransactionStatus::FinalityTimeout(_)
+                | TransactionStatus::Finalized(_)
+                | TransactionStatus::Usurped(_)
+                | TransactionStatus::Dropped
+                | TransactionStatus::Invalid
+        )
+    }
+}
+
 // Exact structure from
 // https://github.com/paritytech/substrate/blob/master/client/rpc-api/src/state/helpers.rs
 // Adding manually so we don't need sc-rpc-api, which brings in async dependencies
@@ -59,3 +126,46 @@ pub struct ReadProof<Hash> {
     /// A proof used to prove that storage entries are included in the storage trie
     pub proof: Vec<sp_core::Bytes>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sp_core::H256;
+    use std::assert_matches::assert_matches;
+
+    type Status = TransactionStatus<H256, H256>;
+
+    #[test]
+    fn transaction_status_deserializes_node_updates() {
+        let status: Status = serde_json::from_str(r#""ready""#).unwrap();
+        assert_eq!(status, TransactionStatus::Ready);
+
+        let status: Status = serde_json::from_str(
+            r#"{"broadcast":["QmfSF4VYWNqNf5KYHpDEdY8Rt1nPUgSkMweDkYzhSWirGY"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            status,
+            TransactionStatus::Broadcast(vec![
+                "QmfSF4VYWNqNf5KYHpDEdY8Rt1nPUgSkMweDkYzhSWirGY".to_string()
+            ])
+        );
+
+        let status: Status = serde_json::from_str(
+            r#"{"retracted":"0x3104d362365ff5ddb61845e1de441b56c6722e94c1aee362f8aa8ba75bd7a3aa"}"#,
+        )
+        .unwrap();
+        assert_matches!(status, TransactionStatus::Retracted(_));
+        assert!(!status.is_final());
+
+        let status: Status = serde_json::from_str(
+            r#"{"finalityTimeout":"0x3104d362365ff5ddb61845e1de441b56c6722e94c1aee362f8aa8ba75bd7a3aa"}"#,
+        )
+        .unwrap();
+        assert!(status.is_final());
+
+        let status: Status = serde_json::from_str(r#""dropped""#).unwrap();
+        assert_eq!(status, TransactionStatus::Dropped);
+        assert!(status.is_final());
+    }
+}
@@ -1,19 +1,21 @@
-use std::sync::mpsc::channel;
 use std::sync::mpsc::Sender as ThreadOut;
-use std::thread;
+use std::sync::mpsc::{channel, RecvError, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use log::info;
 use serde_json::Value;
 use sp_core::H256 as Hash;
-use ws::{connect, Result as WsResult};
 
-use crate::rpc::ws_client::on_extrinsic_msg_submit_only;
+use crate::std::rpc::helpers::{result_from_json_response, result_or_error};
 use crate::std::rpc::json_req;
 use crate::std::rpc::ws_client::Subscriber;
 use crate::std::rpc::ws_client::{
-    on_extrinsic_msg_until_broadcast, on_extrinsic_msg_until_finalized,
-    on_extrinsic_msg_until_in_block, on_extrinsic_msg_until_ready, on_get_request_msg,
-    on_subscription_msg, OnMessageFn, RpcClient,
+    on_extrinsic_msg_submit_only, on_extrinsic_msg_until_broadcast,
+    on_extrinsic_msg_until_finalized, on_extrinsic_msg_until_in_block,
+    on_extrinsic_msg_until_ready, on_get_request_msg, on_response_msg, on_subscription_msg,
+    on_typed_subscription_msg, wait_for_xt_status, DecodeFn, OnMessageFn, ReconnectPolicy,
+    Subscription, WsConnection,
 };
 use crate::std::ApiClientError;
 use crate::std::ApiResult;
@@ -21,22 +23,58 @@ use crate::std::FromHexString;
 use crate::std::RpcClient as RpcClientTrait;
 use crate::std::XtStatus;
 
+/// WebSocket rpc client.
+///
+/// All requests and subscriptions share a single connection, which is established on the first
+/// request and re-established on the next request if the node closed it. Clones of the client
+/// share the connection too.
 #[derive(Debug, Clone)]
 pub struct WsRpcClient {
     url: String,
+    connection: Arc<Mutex<Option<WsConnection>>>,
+    reconnect_policy: Option<ReconnectPolicy>,
+    timeout: Option<Duration>,
 }
 
 impl WsRpcClient {
     pub fn new(url: &str) -> WsRpcClient {
         WsRpcClient {
             url: url.to_string(),
+            connection: Default::default(),
+            reconnect_policy: None,
+            timeout: None,
         }
     }
+
+    /// Sets the maximum duration to wait for the answer of [`RpcClientTrait::get_request`] and
+    /// [`RpcClientTrait::send_extrinsic`]. Per-call timeouts take precedence.
+    #[must_use]
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Re-establishes a lost connection according to `policy` as long as there are active
+    /// subscriptions, which are opened again on the new connection. Their subscribers are told
+    /// about the gap with [`ApiClientError::Reconnected`].
+    ///
+    /// Without a policy, subscriptions end with the connection. Requests always fail if the
+    /// connection is lost while waiting for their response, or if they are sent while
+    /// reconnecting.
+    #[must_use]
+    pub fn set_reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = Some(policy);
+        self
+    }
 }
 
 impl RpcClientTrait for WsRpcClient {
     fn get_request(&self, jsonreq: Value) -> ApiResult<String> {
-        self.direct_rpc_request(jsonreq.to_string(), on_get_request_msg)
+        self.direct_rpc_request(jsonreq, on_get_request_msg, self.timeout)
+    }
+
+    fn get_request_or_error(&self, jsonreq: Value) -> ApiResult<String> {
+        result_or_error(&self.direct_rpc_request(jsonreq, on_response_msg, self.timeout)?)
     }
 
     fn send_extrinsic(
@@ -44,128 +82,157 @@ impl RpcClientTrait for WsRpcClient {
         xthex_prefixed: String,
         exit_on: XtStatus,
     ) -> ApiResult<Option<sp_core::H256>> {
-        // Todo: Make all variants return a H256: #175.
+        self.send_extrinsic_until(xthex_prefixed, exit_on, self.timeout)
+    }
 
-        let jsonreq = match exit_on {
-            XtStatus::SubmitOnly => json_req::author_submit_extrinsic(&xthex_prefixed).to_string(),
-            _ => json_req::author_submit_and_watch_extrinsic(&xthex_prefixed).to_string(),
-        };
+    fn get_request_with_timeout(&self, jsonreq: Value, timeout: Duration) -> ApiResult<String> {
+        self.direct_rpc_request(jsonreq, on_get_request_msg, Some(timeout))
+    }
 
-        match exit_on {
-            XtStatus::Finalized => {
-                let res = self.direct_rpc_request(jsonreq, on_extrinsic_msg_until_finalized)?;
-                info!("finalized: {}", res);
-                Ok(Some(Hash::from_hex(res)?))
-            }
-            XtStatus::InBlock => {
-                let res = self.direct_rpc_request(jsonreq, on_extrinsic_msg_until_in_block)?;
-                info!("inBlock: {}", res);
-                Ok(Some(Hash::from_hex(res)?))
-            }
-            XtStatus::Broadcast => {
-                let res = self.direct_rpc_request(jsonreq, on_extrinsic_msg_until_broadcast)?;
-                info!("broadcast: {}", res);
-                Ok(None)
-            }
-            XtStatus::Ready => {
-                let res = self.direct_rpc_request(jsonreq, on_extrinsic_msg_until_ready)?;
-                info!("ready: {}", res);
-                Ok(None)
-            }
-            XtStatus::SubmitOnly => {
-                let res = self.direct_rpc_request(jsonreq, on_extrinsic_msg_submit_only)?;
-                info!("submitted xt: {}", res);
-                Ok(None)
-            }
-            _ => Err(ApiClientError::UnsupportedXtStatus(exit_on)),
-        }
+    fn send_extrinsic_with_timeout(
+        &self,
+        xthex_prefixed: String,
+        exit_on: XtStatus,
+        timeout: Duration,
+    ) -> ApiResult<Option<sp_core::H256>> {
+        self.send_extrinsic_until(xthex_prefixed, exit_on, Some(timeout))
     }
 }
 
 impl Subscriber for WsRpcClient {
-    fn start_subscriber(
-        &self,
-        json_req: String,
-        result_in: ThreadOut<String>,
-    ) -> Result<(), ws::Error> {
+    fn start_subscriber(&self, json_req: Value, result_in: ThreadOut<String>) -> ApiResult<()> {
         self.start_subscriber(json_req, result_in)
     }
+
+    fn subscribe<T>(&self, json_req: Value, decode: DecodeFn<T>) -> ApiResult<Subscription<T>> {
+        let (result_in, result_out) = channel();
+        let connection = self.connection()?;
+        let request_id = connection.send(json_req, result_in, on_typed_subscription_msg)?;
+        Ok(Subscription::new(result_out, decode, move || {
+            connection.cancel(request_id)
+        }))
+    }
 }
 
 impl WsRpcClient {
-    pub fn get(&self, json_req: String, result_in: ThreadOut<String>) -> WsResult<()> {
-        self.start_rpc_client_thread(json_req, result_in, on_get_request_msg)
+    pub fn get(&self, json_req: Value, result_in: ThreadOut<String>) -> ApiResult<()> {
+        self.start_rpc_request(json_req, result_in, on_get_request_msg)
     }
 
-    pub fn send_extrinsic(&self, json_req: String, result_in: ThreadOut<String>) -> WsResult<()> {
-        self.start_rpc_client_thread(json_req, result_in, on_extrinsic_msg_submit_only)
+    pub fn send_extrinsic(&self, json_req: Value, result_in: ThreadOut<String>) -> ApiResult<()> {
+        self.start_rpc_request(json_req, result_in, on_extrinsic_msg_submit_only)
     }
 
     pub fn send_extrinsic_until_ready(
         &self,
-        json_req: String,
+        json_req: Value,
         result_in: ThreadOut<String>,
-    ) -> WsResult<()> {
-        self.start_rpc_client_thread(json_req, result_in, on_extrinsic_msg_until_ready)
+    ) -> ApiResult<()> {
+        self.start_rpc_request(json_req, result_in, on_extrinsic_msg_until_ready)
     }
 
     pub fn send_extrinsic_and_wait_until_broadcast(
         &self,
-        json_req: String,
+        json_req: Value,
         result_in: ThreadOut<String>,
-    ) -> WsResult<()> {
-        self.start_rpc_client_thread(json_req, result_in, on_extrinsic_msg_until_broadcast)
+    ) -> ApiResult<()> {
+        self.start_rpc_request(json_req, result_in, on_extrinsic_msg_until_broadcast)
     }
 
     pub fn send_extrinsic_and_wait_until_in_block(
         &self,
-        json_req: String,
+        json_req: Value,
         result_in: ThreadOut<String>,
-    ) -> WsResult<()> {
-        self.start_rpc_client_thread(json_req, result_in, on_extrinsic_msg_until_in_block)
+    ) -> ApiResult<()> {
+        self.start_rpc_request(json_req, result_in, on_extrinsic_msg_until_in_block)
     }
 
     pub fn send_extrinsic_and_wait_until_finalized(
         &self,
-        json_req: String,
+        json_req: Value,
         result_in: ThreadOut<String>,
-    ) -> WsResult<()> {
-        self.start_rpc_client_thread(json_req, result_in, on_extrinsic_msg_until_finalized)
+    ) -> ApiResult<()> {
+        self.start_rpc_request(json_req, result_in, on_extrinsic_msg_until_finalized)
     }
 
-    pub fn start_subscriber(&self, json_req: String, result_in: ThreadOut<String>) -> WsResult<()> {
-        self.start_rpc_client_thread(json_req, result_in, on_subscription_msg)
+    pub fn start_subscriber(&self, json_req: Value, result_in: ThreadOut<String>) -> ApiResult<()> {
+        self.start_rpc_request(json_req, result_in, on_subscription_msg)
     }
 
-    fn start_rpc_client_thread(
+    fn send_extrinsic_until(
         &self,
-        jsonreq: String,
+        xthex_prefixed: String,
+        exit_on: XtStatus,
+        timeout: Option<Duration>,
+    ) -> ApiResult<Option<sp_core::H256>> {
+        if exit_on == XtStatus::SubmitOnly {
+            let jsonreq = json_req::author_submit_extrinsic(&xthex_prefixed);
+            let response = self.direct_rpc_request(jsonreq, on_response_msg, timeout)?;
+            let res = result_from_json_response(&response)?;
+            info!("submitted xt: {}", res);
+            return Ok(Some(Hash::from_hex(res)?));
+        }
+        if !matches!(
+            exit_on,
+            XtStatus::Finalized | XtStatus::InBlock | XtStatus::Broadcast | XtStatus::Ready
+        ) {
+            return Err(ApiClientError::UnsupportedXtStatus(exit_on));
+        }
+
+        let jsonreq = json_req::author_submit_and_watch_extrinsic(&xthex_prefixed);
+        let (updates_in, updates_out) = channel();
+        let connection = self.connection()?;
+        let request_id = connection.send(jsonreq, updates_in, on_typed_subscription_msg)?;
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let res = wait_for_xt_status(&updates_out, exit_on, deadline);
+        connection.cancel(request_id);
+        res
+    }
+
+    /// Returns the shared connection, (re-)connecting if there is no open one.
+    fn connection(&self) -> ApiResult<WsConnection> {
+        let mut connection = self.connection.lock().unwrap();
+        match connection.as_ref() {
+            Some(c) if !c.is_closed() => Ok(c.clone()),
+            _ => {
+                info!("connecting to {}", self.url);
+                let c = WsConnection::connect(&self.url, self.reconnect_policy)?;
+                *connection = Some(c.clone());
+                Ok(c)
+            }
+        }
+    }
+
+    fn start_rpc_request(
+        &self,
+        jsonreq: Value,
         result_in: ThreadOut<String>,
         on_message_fn: OnMessageFn,
-    ) -> WsResult<()> {
-        let url = self.url.clone();
-        let _client =
-            thread::Builder::new()
-                .name("client".to_owned())
-                .spawn(move || -> WsResult<()> {
-                    connect(url, |out| RpcClient {
-                        out,
-                        request: jsonreq.clone(),
-                        result: result_in.clone(),
-                        on_message_fn,
-                    })
-                })?;
-        Ok(())
-    }
-
-    fn direct_rpc_request(&self, jsonreq: String, on_message_fn: OnMessageFn) -> ApiResult<String> {
+    ) -> ApiResult<()> {
+        self.connection()?
+            .send(jsonreq, result_in, on_message_fn)
+            .map(|_| ())
+    }
+
+    fn direct_rpc_request(
+        &self,
+        jsonreq: Value,
+        on_message_fn: OnMessageFn,
+        timeout: Option<Duration>,
+    ) -> ApiResult<String> {
         let (result_in, result_out) = channel();
-        connect(self.url.as_str(), |out| RpcClient {
-            out,
-            request: jsonreq.clone(),
-            result: result_in.clone(),
-            on_message_fn,
-        })?;
-        Ok(result_out.recv()?)
+        let connection = self.connection()?;
+        let request_id = connection.send(jsonreq, result_in, on_message_fn)?;
+        let timeout = match timeout {
+            Some(timeout) => timeout,
+            None => return Ok(result_out.recv()?),
+        };
+        result_out.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => {
+                connection.cancel(request_id);
+                ApiClientError::Timeout
+            }
+            RecvTimeoutError::Disconnected => ApiClientError::Disconnected(RecvError),
+        })
     }
 }
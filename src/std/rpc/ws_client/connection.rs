@@ -0,0 +1,489 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+//! A single, long-lived WebSocket connection that multiplexes requests and subscriptions.
+//!
+//! Every request gets a unique JSON-RPC `id` assigned by the connection. Responses are routed
+//! back to the caller by that `id`, subscription notifications by their subscription id.
+//!
+//! With a [`ReconnectPolicy`], a lost connection is re-established as long as there are
+//! subscriptions, which are then opened again on the new connection.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, RecvError, Sender as ThreadOut};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use log::{debug, error, info, warn};
+use serde_json::Value;
+use ws::{connect, CloseCode, Handler, Handshake, Message, Result as WsResult, Sender};
+
+use crate::std::rpc::helpers::{response_id, subscription_id, unsubscribe_method};
+use crate::std::rpc::json_req;
+use crate::std::rpc::ws_client::{MessageOutcome, OnMessageFn};
+use crate::std::{ApiClientError, ApiResult};
+
+/// Passed to the message handler of every subscription that is opened again after the connection
+/// has been re-established. Notifications sent by the node in the meantime are lost.
+pub const RECONNECTED_MSG: &str = r#"{"jsonrpc":"2.0","method":"client_reconnected","params":{}}"#;
+
+/// Defines how often and how fast a lost connection is re-established.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Number of consecutive failed attempts after which the connection is given up.
+    pub max_attempts: u32,
+    /// Delay before the first attempt. It doubles with every failed attempt.
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between two attempts.
+    pub max_backoff: Duration,
+}
+
+impl ReconnectPolicy {
+    /// Returns the delay before the given attempt, starting at 1.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Handle to the shared WebSocket connection. Clones refer to the same connection.
+#[derive(Debug, Clone)]
+pub struct WsConnection {
+    state: Arc<Mutex<ConnectionState>>,
+}
+
+impl WsConnection {
+    /// Opens a new connection to `url` and runs its event loop in a separate thread.
+    ///
+    /// Blocks until the WebSocket handshake is completed. If the connection is lost later on, it
+    /// is re-established according to `reconnect_policy`.
+    pub fn connect(url: &str, reconnect_policy: Option<ReconnectPolicy>) -> ApiResult<Self> {
+        let url = url.to_string();
+        let state = Arc::new(Mutex::new(ConnectionState::default()));
+        let (opened_in, opened_out) = channel();
+
+        let handler_state = state.clone();
+        thread::Builder::new()
+            .name("ws-client".to_owned())
+            .spawn(move || {
+                let mut attempt = 0;
+                loop {
+                    let result = connect(url.as_str(), |out| MultiplexHandler {
+                        out,
+                        state: handler_state.clone(),
+                        opened: opened_in.clone(),
+                    });
+                    if let Err(e) = result {
+                        error!("WebSocket connection terminated with error: {:?}", e);
+                    }
+
+                    let (was_open, has_replays) = handler_state.lock().unwrap().disconnect();
+                    if was_open {
+                        attempt = 0;
+                    }
+                    let policy = match reconnect_policy {
+                        // The initial connection is not retried.
+                        Some(policy) if (was_open || attempt > 0) && has_replays => policy,
+                        _ => break,
+                    };
+                    attempt += 1;
+                    if attempt > policy.max_attempts {
+                        error!(
+                            "Giving up reconnecting after {} attempts",
+                            policy.max_attempts
+                        );
+                        break;
+                    }
+                    let backoff = policy.backoff(attempt);
+                    info!("Reconnecting in {:?}, attempt {}", backoff, attempt);
+                    thread::sleep(backoff);
+                }
+                // Dropping the result channels unblocks everyone still waiting for an answer.
+                handler_state.lock().unwrap().close();
+            })
+            .map_err(ws::Error::from)?;
+
+        // The sender is dropped without sending if the connection could not be established.
+        opened_out.recv()?;
+        Ok(Self { state })
+    }
+
+    /// Returns true if the underlying socket has been closed and will not be re-established.
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+
+    /// Sends `jsonreq` over the connection. Every message belonging to this request is passed to
+    /// `on_message_fn` until it returns [`MessageOutcome::Done`].
+    ///
+    /// The `id` of `jsonreq` is overwritten with a connection-unique one. If `jsonreq` opens a
+    /// subscription, the subscription is closed on the node once the request is done.
+    ///
+    /// Returns the id assigned to the request, which can be passed to [`Self::cancel`].
+    pub fn send(
+        &self,
+        mut jsonreq: Value,
+        result: ThreadOut<String>,
+        on_message_fn: OnMessageFn,
+    ) -> ApiResult<u32> {
+        let unsubscribe_method = jsonreq["method"].as_str().and_then(unsubscribe_method);
+        let mut state = self.state.lock().unwrap();
+        let out = match &state.out {
+            Some(out) => out.clone(),
+            // Currently reconnecting.
+            None => return Err(ApiClientError::Disconnected(RecvError)),
+        };
+        let id = state.next_id();
+        jsonreq["id"] = Value::String(id.to_string());
+        state.pending.insert(
+            id,
+            RequestEntry {
+                handle_id: id,
+                jsonreq: jsonreq.clone(),
+                result,
+                on_message_fn,
+                unsubscribe_method,
+            },
+        );
+
+        info!("sending request: {}", jsonreq);
+        if let Err(e) = out.send(jsonreq.to_string()) {
+            state.pending.remove(&id);
+            return Err(e.into());
+        }
+        Ok(id)
+    }
+
+    /// Cancels the request with `request_id`. No further messages are passed to its message
+    /// handler and the subscription it opened, if any, is closed.
+    ///
+    /// If the node has not confirmed the subscription yet, it is closed as soon as it does.
+    pub fn cancel(&self, request_id: u32) {
+        let mut state = self.state.lock().unwrap();
+        state.replay.retain(|entry| entry.handle_id != request_id);
+        if let Some(entry) = state
+            .pending
+            .values_mut()
+            .find(|entry| entry.handle_id == request_id)
+        {
+            entry.on_message_fn = |_, _| Ok(MessageOutcome::Done);
+            return;
+        }
+
+        let subscription = state
+            .subscriptions
+            .iter()
+            .find(|(_, s)| s.entry.handle_id == request_id)
+            .map(|(key, _)| key.clone());
+        if let Some(s) = subscription.and_then(|key| state.subscriptions.remove(&key)) {
+            if let Some(out) = state.out.clone() {
+                send_unsubscribe(&out, &mut state, s);
+            }
+        }
+    }
+}
+
+/// Requests waiting for their response and subscriptions waiting for notifications.
+#[derive(Debug, Default)]
+struct ConnectionState {
+    /// `None` while there is no open socket.
+    out: Option<Sender>,
+    closed: bool,
+    last_id: u32,
+    pending: HashMap<u32, RequestEntry>,
+    subscriptions: HashMap<String, ActiveSubscription>,
+    /// Subscriptions to be opened again once the connection has been re-established.
+    replay: Vec<RequestEntry>,
+}
+
+impl ConnectionState {
+    fn next_id(&mut self) -> u32 {
+        self.last_id = self.last_id.wrapping_add(1);
+        self.last_id
+    }
+
+    /// Drops all requests that cannot be replayed on a new connection. Returns whether the
+    /// socket had been open and whether there are subscriptions to replay.
+    fn disconnect(&mut self) -> (bool, bool) {
+        let was_open = self.out.take().is_some();
+        let pending = self.pending.drain().map(|(_, entry)| entry);
+        let subscriptions = self.subscriptions.drain().map(|(_, s)| s.entry);
+        let replay: Vec<_> = pending
+            .chain(subscriptions)
+            .filter(RequestEntry::is_replayable)
+            .collect();
+        self.replay.extend(replay);
+        (was_open, !self.replay.is_empty())
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        self.pending.clear();
+        self.subscriptions.clear();
+        self.replay.clear();
+    }
+}
+
+#[derive(Debug)]
+struct RequestEntry {
+    /// The id the request has been sent with initially. It stays the same when the request is
+    /// replayed on a new connection.
+    handle_id: u32,
+    jsonreq: Value,
+    result: ThreadOut<String>,
+    on_message_fn: OnMessageFn,
+    /// Set if the request opens a subscription.
+    unsubscribe_method: Option<&'static str>,
+}
+
+impl RequestEntry {
+    /// Subscriptions can be opened again, but an extrinsic must not be submitted twice.
+    fn is_replayable(&self) -> bool {
+        self.unsubscribe_method.is_some()
+            && self.jsonreq["method"] != "author_submitAndWatchExtrinsic"
+    }
+}
+
+#[derive(Debug)]
+struct ActiveSubscription {
+    /// The subscription id exactly as returned by the node.
+    id: Value,
+    entry: RequestEntry,
+}
+
+struct MultiplexHandler {
+    out: Sender,
+    state: Arc<Mutex<ConnectionState>>,
+    opened: ThreadOut<()>,
+}
+
+impl Handler for MultiplexHandler {
+    fn on_open(&mut self, _: Handshake) -> WsResult<()> {
+        info!("WebSocket connection opened");
+        let mut state = self.state.lock().unwrap();
+        state.out = Some(self.out.clone());
+        for entry in std::mem::take(&mut state.replay) {
+            self.replay(&mut state, entry);
+        }
+        // Only the initial connection is waited for.
+        self.opened
+            .send(())
+            .unwrap_or_else(|_| debug!("Connection has been re-established"));
+        Ok(())
+    }
+
+    fn on_message(&mut self, msg: Message) -> WsResult<()> {
+        let msg = msg.as_text()?;
+        debug!("got msg {}", msg);
+        let value: Value = match serde_json::from_str(msg) {
+            Ok(value) => value,
+            Err(e) => {
+                error!("Could not parse message as json: {:?}", e);
+                return Ok(());
+            }
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(id) = response_id(&value) {
+            self.on_response(&mut state, id, msg, &value)
+        } else if let Some(subscription) = subscription_id(&value["params"]["subscription"]) {
+            self.on_notification(&mut state, subscription, msg)
+        } else {
+            warn!(
+                "Ignoring message that neither has an id nor a subscription: {}",
+                msg
+            );
+        }
+        Ok(())
+    }
+
+    fn on_close(&mut self, code: CloseCode, reason: &str) {
+        info!("WebSocket connection closed: {:?} {}", code, reason);
+    }
+}
+
+impl MultiplexHandler {
+    /// Tells the subscriber about the gap and opens the subscription again.
+    fn replay(&self, state: &mut ConnectionState, mut entry: RequestEntry) {
+        if !handle_message(&entry, RECONNECTED_MSG) {
+            debug!(
+                "Not replaying request {}, nobody is interested anymore",
+                entry.handle_id
+            );
+            return;
+        }
+        let id = state.next_id();
+        entry.jsonreq["id"] = Value::String(id.to_string());
+        info!("replaying request: {}", entry.jsonreq);
+        match self.out.send(entry.jsonreq.to_string()) {
+            Ok(()) => {
+                state.pending.insert(id, entry);
+            }
+            Err(e) => error!("Could not replay request: {:?}", e),
+        }
+    }
+
+    fn on_response(&self, state: &mut ConnectionState, id: u32, msg: &str, value: &Value) {
+        let entry = match state.pending.remove(&id) {
+            Some(entry) => entry,
+            None => {
+                debug!("No pending request with id {}", id);
+                return;
+            }
+        };
+
+        let expects_more = handle_message(&entry, msg);
+        match (entry.unsubscribe_method, subscription_id(&value["result"])) {
+            (Some(_), Some(subscription)) => {
+                debug!("request {} opened subscription {}", id, subscription);
+                let subscription_entry = ActiveSubscription {
+                    id: value["result"].clone(),
+                    entry,
+                };
+                if expects_more {
+                    state.subscriptions.insert(subscription, subscription_entry);
+                } else {
+                    // Nobody is interested in the notifications anymore.
+                    send_unsubscribe(&self.out, state, subscription_entry);
+                }
+            }
+            _ if expects_more => {
+                warn!(
+                    "request {} expects further messages but did not open a subscription",
+                    id
+                )
+            }
+            _ => {}
+        }
+    }
+
+    fn on_notification(&self, state: &mut ConnectionState, subscription: String, msg: &str) {
+        let done = match state.subscriptions.get(&subscription) {
+            Some(s) => !handle_message(&s.entry, msg),
+            None => {
+                debug!("No active subscription {}", subscription);
+                return;
+            }
+        };
+
+        if done {
+            if let Some(s) = state.subscriptions.remove(&subscription) {
+                send_unsubscribe(&self.out, state, s);
+            }
+        }
+    }
+}
+
+fn send_unsubscribe(out: &Sender, state: &mut ConnectionState, subscription: ActiveSubscription) {
+    if let Some(method) = subscription.entry.unsubscribe_method {
+        let jsonreq = json_req::unsubscribe_with_id(method, subscription.id, state.next_id());
+        debug!("unsubscribing: {}", jsonreq);
+        out.send(jsonreq.to_string())
+            .unwrap_or_else(|e| warn!("Could not unsubscribe: {:?}", e));
+    }
+}
+
+/// Passes `msg` to the request's message handler. Returns true if further messages are expected.
+fn handle_message(entry: &RequestEntry, msg: &str) -> bool {
+    match (entry.on_message_fn)(msg, &entry.result) {
+        Ok(MessageOutcome::Continue) => true,
+        Ok(MessageOutcome::Done) => false,
+        Err(e) => {
+            error!("Error handling message: {:?}", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std::rpc::ws_client::{on_get_request_msg, on_typed_subscription_msg};
+
+    fn entry(jsonreq: Value, on_message_fn: OnMessageFn) -> RequestEntry {
+        let (result, _) = channel();
+        RequestEntry {
+            handle_id: 1,
+            unsubscribe_method: jsonreq["method"].as_str().and_then(unsubscribe_method),
+            jsonreq,
+            result,
+            on_message_fn,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let policy = ReconnectPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+        };
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(4), Duration::from_secs(5));
+        assert_eq!(policy.backoff(100), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn only_subscriptions_are_kept_for_replay() {
+        let mut state = ConnectionState::default();
+        state.pending.insert(
+            1,
+            entry(json_req::chain_get_genesis_hash(), on_get_request_msg),
+        );
+        state.pending.insert(
+            2,
+            entry(
+                json_req::author_submit_and_watch_extrinsic("0x00"),
+                on_typed_subscription_msg,
+            ),
+        );
+        state.subscriptions.insert(
+            "SXuvtB4Bbr4ImLAb".to_string(),
+            ActiveSubscription {
+                id: Value::String("SXuvtB4Bbr4ImLAb".to_string()),
+                entry: entry(
+                    json_req::chain_subscribe_finalized_heads(),
+                    on_typed_subscription_msg,
+                ),
+            },
+        );
+
+        assert_eq!(state.disconnect(), (false, true));
+        assert!(state.pending.is_empty());
+        assert!(state.subscriptions.is_empty());
+        assert_eq!(state.replay.len(), 1);
+        assert_eq!(
+            state.replay[0].jsonreq["method"],
+            "chain_subscribeFinalizedHeads"
+        );
+    }
+}
@@ -14,53 +14,56 @@
    limitations under the License.
 
 */
-use std::sync::mpsc::{Receiver, SendError, Sender as ThreadOut};
+use std::sync::mpsc::{Receiver, RecvError, RecvTimeoutError, SendError, Sender as ThreadOut};
+use std::time::{Duration, Instant};
 
-use ac_node_api::events::{EventsDecoder, Raw, RawEvent};
-use ac_primitives::ExtrinsicParams;
-use codec::Decode;
+use ac_node_api::events::{EventsDecoder, Raw, RawEvent, StaticEvent};
+use ac_node_api::Phase;
+use ac_primitives::{ExtrinsicParams, Signer};
 use log::{debug, error, info, warn};
+use serde::de::DeserializeOwned;
 use serde_json::Value;
-use sp_core::Pair;
-use sp_runtime::MultiSignature;
-use ws::{CloseCode, Error, Handler, Handshake, Message, Result as WsResult, Sender};
-
-use crate::std::rpc::RpcClientError;
-use crate::std::{json_req, FromHexString, RpcClient as RpcClientTrait, XtStatus};
-use crate::std::{Api, ApiResult};
+use sp_core::H256 as Hash;
+
+use crate::std::rpc::helpers::{parse_status, result_from_json_response};
+use crate::std::rpc::{RpcClientError, RpcResult};
+use crate::std::{
+    json_req, FromHexString, Header, RpcClient as RpcClientTrait, RuntimeVersion,
+    TransactionStatus, XtStatus,
+};
+use crate::std::{Api, ApiClientError, ApiResult};
 use crate::utils;
 
+pub use crate::std::rpc::DecodeFn;
 pub use client::WsRpcClient;
+pub use connection::{ReconnectPolicy, WsConnection};
+pub use subscription::Subscription;
 
 pub mod client;
-
-pub type OnMessageFn = fn(msg: Message, out: Sender, result: ThreadOut<String>) -> WsResult<()>;
-
-type RpcResult<T> = Result<T, RpcClientError>;
-
-pub struct RpcClient {
-    pub out: Sender,
-    pub request: String,
-    pub result: ThreadOut<String>,
-    pub on_message_fn: OnMessageFn,
+pub mod connection;
+pub mod subscription;
+
+/// Handles a message received for a request. Called for every message until it returns
+/// [`MessageOutcome::Done`] or an error.
+pub type OnMessageFn = fn(msg: &str, result: &ThreadOut<String>) -> RpcResult<MessageOutcome>;
+
+/// Tells the connection whether a request expects further messages.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum MessageOutcome {
+    Continue,
+    Done,
 }
 
-impl Handler for RpcClient {
-    fn on_open(&mut self, _: Handshake) -> WsResult<()> {
-        info!("sending request: {}", self.request);
-        self.out.send(self.request.clone())?;
-        Ok(())
-    }
+pub trait Subscriber {
+    fn start_subscriber(&self, json_req: Value, result_in: ThreadOut<String>) -> ApiResult<()>;
 
-    fn on_message(&mut self, msg: Message) -> WsResult<()> {
-        (self.on_message_fn)(msg, self.out.clone(), self.result.clone())
-    }
+    /// Opens the subscription requested by `json_req`. Its notifications are decoded with
+    /// `decode`.
+    fn subscribe<T>(&self, json_req: Value, decode: DecodeFn<T>) -> ApiResult<Subscription<T>>;
 }
 
-pub trait Subscriber {
-    fn start_subscriber(&self, json_req: String, result_in: ThreadOut<String>)
-        -> Result<(), Error>;
-}
+/// Subscription yielding the events of every new block.
+pub type EventsSubscription = Subscription<Vec<(Phase, Raw)>>;
 
 impl<P, Params> Api<P, WsRpcClient, Params>
 where
@@ -74,94 +77,203 @@ where
 
 impl<P, Client, Params> Api<P, Client, Params>
 where
-    P: Pair,
-    MultiSignature: From<P::Signature>,
+    P: Signer,
     Client: RpcClientTrait + Subscriber,
     Params: ExtrinsicParams,
 {
-    pub fn subscribe_events(&self, sender: ThreadOut<String>) -> ApiResult<()> {
+    pub fn subscribe_events(&self) -> ApiResult<EventsSubscription> {
         debug!("subscribing to events");
         let key = utils::storage_key("System", "Events");
-        let jsonreq = json_req::state_subscribe_storage(vec![key]).to_string();
-        self.client
-            .start_subscriber(jsonreq, sender)
-            .map_err(|e| e.into())
+        let jsonreq = json_req::state_subscribe_storage(vec![key]);
+        let decoder = EventsDecoder::new(self.metadata.clone());
+        self.client.subscribe(
+            jsonreq,
+            Box::new(move |result| {
+                let change_set = match result["changes"][0][1].as_str() {
+                    Some(change_set) => Vec::from_hex(change_set.to_string())?,
+                    None => {
+                        debug!("No events happened");
+                        return Ok(None);
+                    }
+                };
+                Ok(Some(decoder.decode_events(&mut change_set.as_slice())?))
+            }),
+        )
     }
 
-    pub fn subscribe_finalized_heads(&self, sender: ThreadOut<String>) -> ApiResult<()> {
+    pub fn subscribe_finalized_heads<H>(&self) -> ApiResult<Subscription<H>>
+    where
+        H: Header + DeserializeOwned,
+    {
         debug!("subscribing to finalized heads");
-        let jsonreq = json_req::chain_subscribe_finalized_heads().to_string();
-        self.client
-            .start_subscriber(jsonreq, sender)
-            .map_err(|e| e.into())
+        let jsonreq = json_req::chain_subscribe_finalized_heads();
+        self.client.subscribe(
+            jsonreq,
+            Box::new(|result| Ok(serde_json::from_value(result)?)),
+        )
     }
 
-    pub fn wait_for_event<E: Decode>(
+    /// Subscribes to the runtime version of the node. The node notifies the current version
+    /// right away and every upgraded version afterwards.
+    pub fn subscribe_runtime_version(&self) -> ApiResult<Subscription<RuntimeVersion>> {
+        debug!("subscribing to runtime version");
+        let jsonreq = json_req::state_subscribe_runtime_version();
+        self.client.subscribe(
+            jsonreq,
+            Box::new(|result| Ok(Some(serde_json::from_value(result)?))),
+        )
+    }
+
+    /// Updates the api to the latest runtime version received on `subscription`, refetching the
+    /// metadata if the runtime has been upgraded, see [`Self::set_runtime_version`]. Does not
+    /// block, so it can be called before every extrinsic is composed. Returns whether the
+    /// runtime has changed.
+    pub fn apply_runtime_upgrades(
+        &mut self,
+        subscription: &mut Subscription<RuntimeVersion>,
+    ) -> ApiResult<bool> {
+        match latest_runtime_version(subscription) {
+            Ok(Some(runtime_version)) => self.set_runtime_version(runtime_version),
+            Ok(None) => Ok(false),
+            // An upgrade may have been missed while the connection was down.
+            Err(ApiClientError::Reconnected) => self.update_runtime(),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Submits the extrinsic and yields every status update the node reports for it, until it
+    /// reached a final status, see [`TransactionStatus::is_final`].
+    pub fn watch_extrinsic(
         &self,
-        module: &str,
-        variant: &str,
-        decoder: Option<EventsDecoder>,
-        receiver: &Receiver<String>,
+        xthex_prefixed: &str,
+    ) -> ApiResult<Subscription<TransactionStatus<Hash, Hash>>> {
+        debug!("watching extrinsic: {:?}", xthex_prefixed);
+        let jsonreq = json_req::author_submit_and_watch_extrinsic(xthex_prefixed);
+        let subscription = self.client.subscribe(
+            jsonreq,
+            Box::new(|result| Ok(Some(serde_json::from_value(result)?))),
+        )?;
+        Ok(subscription.end_after(TransactionStatus::is_final))
+    }
+
+    /// Waits for the first event `E`, e.g. `api.wait_for_event::<Transfer>(&mut events)`.
+    pub fn wait_for_event<E: StaticEvent>(
+        &self,
+        subscription: &mut EventsSubscription,
+    ) -> ApiResult<E> {
+        let raw = self.wait_for_raw_event(E::PALLET, E::EVENT, subscription)?;
+        E::decode(&mut &raw.data[..]).map_err(|e| e.into())
+    }
+
+    /// Like [`Self::wait_for_event`], but fails with [`ApiClientError::Timeout`] if the event
+    /// does not occur within `timeout`.
+    pub fn wait_for_event_with_timeout<E: StaticEvent>(
+        &self,
+        subscription: &mut EventsSubscription,
+        timeout: Duration,
     ) -> ApiResult<E> {
-        let raw = self.wait_for_raw_event(module, variant, decoder, receiver)?;
+        let raw =
+            self.wait_for_raw_event_with_timeout(E::PALLET, E::EVENT, subscription, timeout)?;
         E::decode(&mut &raw.data[..]).map_err(|e| e.into())
     }
 
+    /// Waits for the first event of `module` and `variant`. Fails with
+    /// [`ApiClientError::Reconnected`] if the connection has been re-established in the meantime,
+    /// as the event may have been missed.
     pub fn wait_for_raw_event(
         &self,
         module: &str,
         variant: &str,
-        decoder: Option<EventsDecoder>,
-        receiver: &Receiver<String>,
+        subscription: &mut EventsSubscription,
     ) -> ApiResult<RawEvent> {
-        let event_decoder = match decoder {
-            Some(d) => d,
-            None => EventsDecoder::new(self.metadata.clone()),
-        };
+        wait_for_raw_event_until(module, variant, subscription, None)
+    }
 
-        loop {
-            let event_str = receiver.recv()?;
-            let _events = event_decoder.decode_events(&mut Vec::from_hex(event_str)?.as_slice());
-            info!("wait for raw event");
-            match _events {
-                Ok(raw_events) => {
-                    for (phase, event) in raw_events.into_iter() {
-                        info!("Decoded Event: {:?}, {:?}", phase, event);
-                        match event {
-                            Raw::Event(raw) if raw.pallet == module && raw.variant == variant => {
-                                return Ok(raw);
-                            }
-                            Raw::Error(runtime_error) => {
-                                error!("Some extrinsic Failed: {:?}", runtime_error);
-                            }
-                            _ => debug!("ignoring unsupported module event: {:?}", event),
-                        }
-                    }
+    /// Like [`Self::wait_for_raw_event`], but fails with [`ApiClientError::Timeout`] if the
+    /// event does not occur within `timeout`.
+    pub fn wait_for_raw_event_with_timeout(
+        &self,
+        module: &str,
+        variant: &str,
+        subscription: &mut EventsSubscription,
+        timeout: Duration,
+    ) -> ApiResult<RawEvent> {
+        let deadline = Instant::now() + timeout;
+        wait_for_raw_event_until(module, variant, subscription, Some(deadline))
+    }
+}
+
+fn wait_for_raw_event_until(
+    module: &str,
+    variant: &str,
+    subscription: &mut EventsSubscription,
+    deadline: Option<Instant>,
+) -> ApiResult<RawEvent> {
+    info!("wait for raw event");
+    loop {
+        let raw_events = match subscription.next_before(deadline) {
+            Some(Ok(raw_events)) => raw_events,
+            Some(Err(ApiClientError::NodeApi(error))) => {
+                error!("couldn't decode event record list: {:?}", error);
+                continue;
+            }
+            Some(Err(e)) => return Err(e),
+            None => return Err(ApiClientError::Disconnected(RecvError)),
+        };
+        for (phase, event) in raw_events.into_iter() {
+            info!("Decoded Event: {:?}, {:?}", phase, event);
+            match event {
+                Raw::Event(raw) if raw.pallet == module && raw.variant == variant => {
+                    return Ok(raw);
                 }
-                Err(error) => error!("couldn't decode event record list: {:?}", error),
+                Raw::Error(runtime_error) => {
+                    error!("Some extrinsic Failed: {:?}", runtime_error);
+                }
+                _ => debug!("ignoring unsupported module event: {:?}", event),
             }
         }
     }
 }
 
-pub fn on_get_request_msg(msg: Message, out: Sender, result: ThreadOut<String>) -> WsResult<()> {
-    out.close(CloseCode::Normal)
-        .unwrap_or_else(|_| warn!("Could not close Websocket normally"));
+/// Returns the last runtime version that has been received on `subscription` so far, without
+/// waiting for further notifications.
+fn latest_runtime_version(
+    subscription: &mut Subscription<RuntimeVersion>,
+) -> ApiResult<Option<RuntimeVersion>> {
+    let mut latest = None;
+    loop {
+        match subscription.next_timeout(Duration::ZERO) {
+            Some(Ok(runtime_version)) => latest = Some(runtime_version),
+            Some(Err(ApiClientError::Timeout)) => return Ok(latest),
+            Some(Err(e)) => return Err(e),
+            None => return Err(ApiClientError::Disconnected(RecvError)),
+        }
+    }
+}
+
+/// Forwards every message of a subscription to [`Subscription`] until it has been dropped.
+pub fn on_typed_subscription_msg(
+    msg: &str,
+    result: &ThreadOut<String>,
+) -> RpcResult<MessageOutcome> {
+    debug!("got subscription msg {}", msg);
+    if let Err(SendError(e)) = result.send(msg.to_owned()) {
+        debug!("SendError: {}. will unsubscribe", e);
+        return Ok(MessageOutcome::Done);
+    }
+    Ok(MessageOutcome::Continue)
+}
 
+pub fn on_get_request_msg(msg: &str, result: &ThreadOut<String>) -> RpcResult<MessageOutcome> {
     info!("Got get_request_msg {}", msg);
-    let result_str = serde_json::from_str(msg.as_text()?)
-        .map(|v: serde_json::Value| v["result"].to_string())
-        .map_err(|e| Box::new(RpcClientError::Serde(e)))?;
-
-    result
-        .send(result_str)
-        .map_err(|e| Box::new(RpcClientError::Send(e)).into())
+    let result_str = serde_json::from_str(msg).map(|v: Value| v["result"].to_string())?;
+    result.send(result_str)?;
+    Ok(MessageOutcome::Done)
 }
 
-pub fn on_subscription_msg(msg: Message, out: Sender, result: ThreadOut<String>) -> WsResult<()> {
+pub fn on_subscription_msg(msg: &str, result: &ThreadOut<String>) -> RpcResult<MessageOutcome> {
     info!("got on_subscription_msg {}", msg);
-    let value: serde_json::Value =
-        serde_json::from_str(msg.as_text()?).map_err(|e| Box::new(RpcClientError::Serde(e)))?;
+    let value: Value = serde_json::from_str(msg)?;
 
     match value["id"].as_str() {
         Some(_idstr) => {}
@@ -175,305 +287,333 @@ pub fn on_subscription_msg(msg: Message, out: Sender, result: ThreadOut<String>)
                     match changes[0][1].as_str() {
                         Some(change_set) => {
                             if let Err(SendError(e)) = result.send(change_set.to_owned()) {
-                                debug!("SendError: {}. will close ws", e);
-                                out.close(CloseCode::Normal)?;
+                                debug!("SendError: {}. will unsubscribe", e);
+                                return Ok(MessageOutcome::Done);
                             }
                         }
                         None => println!("No events happened"),
                     };
                 }
                 Some("chain_finalizedHead") => {
-                    let head = serde_json::to_string(&value["params"]["result"])
-                        .map_err(|e| Box::new(RpcClientError::Serde(e)))?;
+                    let head = serde_json::to_string(&value["params"]["result"])?;
 
                     if let Err(e) = result.send(head) {
-                        debug!("SendError: {}. will close ws", e);
-                        out.close(CloseCode::Normal)?;
+                        debug!("SendError: {}. will unsubscribe", e);
+                        return Ok(MessageOutcome::Done);
                     }
                 }
+                Some("client_reconnected") => {
+                    warn!("connection has been re-established, notifications may have been missed")
+                }
                 _ => error!("unsupported method"),
             }
         }
     };
-    Ok(())
+    Ok(MessageOutcome::Continue)
 }
 
 pub fn on_extrinsic_msg_until_finalized(
-    msg: Message,
-    out: Sender,
-    result: ThreadOut<String>,
-) -> WsResult<()> {
-    let retstr = msg.as_text().unwrap();
-    debug!("got msg {}", retstr);
-    match parse_status(retstr) {
-        Ok((XtStatus::Finalized, val)) => end_process(out, result, val),
+    msg: &str,
+    result: &ThreadOut<String>,
+) -> RpcResult<MessageOutcome> {
+    debug!("got msg {}", msg);
+    match parse_status(msg) {
+        Ok((XtStatus::Finalized, val)) => end_process(result, val),
         Ok((XtStatus::Future, _)) => {
             warn!("extrinsic has 'future' status. aborting");
-            end_process(out, result, None)
+            end_process(result, None)?;
+            Err(future_status_err())
         }
         Err(e) => {
-            end_process(out, result, None)?;
-            Err(Box::new(e).into())
+            end_process(result, None)?;
+            Err(e)
         }
-        _ => Ok(()),
+        _ => Ok(MessageOutcome::Continue),
     }
 }
 
 pub fn on_extrinsic_msg_until_in_block(
-    msg: Message,
-    out: Sender,
-    result: ThreadOut<String>,
-) -> WsResult<()> {
-    let retstr = msg.as_text().unwrap();
-    debug!("got msg {}", retstr);
-    match parse_status(retstr) {
-        Ok((XtStatus::Finalized, val)) => end_process(out, result, val),
-        Ok((XtStatus::InBlock, val)) => end_process(out, result, val),
-        Ok((XtStatus::Future, _)) => end_process(out, result, None),
+    msg: &str,
+    result: &ThreadOut<String>,
+) -> RpcResult<MessageOutcome> {
+    debug!("got msg {}", msg);
+    match parse_status(msg) {
+        Ok((XtStatus::Finalized, val)) => end_process(result, val),
+        Ok((XtStatus::InBlock, val)) => end_process(result, val),
+        Ok((XtStatus::Future, _)) => {
+            warn!("extrinsic has 'future' status. aborting");
+            end_process(result, None)?;
+            Err(future_status_err())
+        }
         Err(e) => {
-            end_process(out, result, None)?;
-            Err(Box::new(e).into())
+            end_process(result, None)?;
+            Err(e)
         }
-        _ => Ok(()),
+        _ => Ok(MessageOutcome::Continue),
     }
 }
 
 pub fn on_extrinsic_msg_until_broadcast(
-    msg: Message,
-    out: Sender,
-    result: ThreadOut<String>,
-) -> WsResult<()> {
-    let retstr = msg.as_text().unwrap();
-    debug!("got msg {}", retstr);
-    match parse_status(retstr) {
-        Ok((XtStatus::Finalized, val)) => end_process(out, result, val),
-        Ok((XtStatus::Broadcast, _)) => end_process(out, result, None),
-        Ok((XtStatus::Future, _)) => end_process(out, result, None),
+    msg: &str,
+    result: &ThreadOut<String>,
+) -> RpcResult<MessageOutcome> {
+    debug!("got msg {}", msg);
+    match parse_status(msg) {
+        Ok((XtStatus::Finalized, val)) => end_process(result, val),
+        Ok((XtStatus::Broadcast, _)) => end_process(result, None),
+        Ok((XtStatus::Future, _)) => {
+            warn!("extrinsic has 'future' status. aborting");
+            end_process(result, None)?;
+            Err(future_status_err())
+        }
         Err(e) => {
-            end_process(out, result, None)?;
-            Err(Box::new(e).into())
+            end_process(result, None)?;
+            Err(e)
         }
-        _ => Ok(()),
+        _ => Ok(MessageOutcome::Continue),
     }
 }
 
 pub fn on_extrinsic_msg_until_ready(
-    msg: Message,
-    out: Sender,
-    result: ThreadOut<String>,
-) -> WsResult<()> {
-    let retstr = msg.as_text().unwrap();
-    debug!("got msg {}", retstr);
-    match parse_status(retstr) {
-        Ok((XtStatus::Finalized, val)) => end_process(out, result, val),
-        Ok((XtStatus::Ready, _)) => end_process(out, result, None),
-        Ok((XtStatus::Future, _)) => end_process(out, result, None),
+    msg: &str,
+    result: &ThreadOut<String>,
+) -> RpcResult<MessageOutcome> {
+    debug!("got msg {}", msg);
+    match parse_status(msg) {
+        Ok((XtStatus::Finalized, val)) => end_process(result, val),
+        Ok((XtStatus::Ready, _)) => end_process(result, None),
+        Ok((XtStatus::Future, _)) => {
+            warn!("extrinsic has 'future' status. aborting");
+            end_process(result, None)?;
+            Err(future_status_err())
+        }
         Err(e) => {
-            end_process(out, result, None)?;
-            Err(Box::new(e).into())
+            end_process(result, None)?;
+            Err(e)
         }
-        _ => Ok(()),
+        _ => Ok(MessageOutcome::Continue),
     }
 }
 
 pub fn on_extrinsic_msg_submit_only(
-    msg: Message,
-    out: Sender,
-    result: ThreadOut<String>,
-) -> WsResult<()> {
-    let retstr = msg.as_text().unwrap();
-    debug!("got msg {}", retstr);
-    match result_from_json_response(retstr) {
-        Ok(val) => end_process(out, result, Some(val)),
+    msg: &str,
+    result: &ThreadOut<String>,
+) -> RpcResult<MessageOutcome> {
+    debug!("got msg {}", msg);
+    match result_from_json_response(msg) {
+        Ok(val) => end_process(result, Some(val)),
         Err(e) => {
-            end_process(out, result, None)?;
-            Err(Box::new(e).into())
+            end_process(result, None)?;
+            Err(e)
         }
     }
 }
 
-fn end_process(out: Sender, result: ThreadOut<String>, value: Option<String>) -> WsResult<()> {
-    // return result to calling thread
-    debug!("Thread end result :{:?} value:{:?}", result, value);
-    let val = value.unwrap_or_else(|| "".to_string());
-
-    out.close(CloseCode::Normal)
-        .unwrap_or_else(|_| warn!("Could not close WebSocket normally"));
-
-    result
-        .send(val)
-        .map_err(|e| Box::new(RpcClientError::Send(e)).into())
+/// Forwards the response to a request to the calling thread unchanged.
+pub fn on_response_msg(msg: &str, result: &ThreadOut<String>) -> RpcResult<MessageOutcome> {
+    debug!("got msg {}", msg);
+    end_process(result, Some(msg.to_owned()))
 }
 
-fn parse_status(msg: &str) -> RpcResult<(XtStatus, Option<String>)> {
-    let value: serde_json::Value = serde_json::from_str(msg)?;
-
-    if value["error"].as_object().is_some() {
-        return Err(into_extrinsic_err(&value));
-    }
-
-    match value["params"]["result"].as_object() {
-        Some(obj) => {
-            if let Some(hash) = obj.get("finalized") {
-                info!("finalized: {:?}", hash);
-                Ok((XtStatus::Finalized, Some(hash.to_string())))
-            } else if let Some(hash) = obj.get("inBlock") {
-                info!("inBlock: {:?}", hash);
-                Ok((XtStatus::InBlock, Some(hash.to_string())))
-            } else if let Some(array) = obj.get("broadcast") {
-                info!("broadcast: {:?}", array);
-                Ok((XtStatus::Broadcast, Some(array.to_string())))
-            } else {
-                Ok((XtStatus::Unknown, None))
+/// Waits until the extrinsic watched by `updates` reached `exit_on`. `updates` yields the raw
+/// messages of `author_submitAndWatchExtrinsic`, as forwarded by [`on_typed_subscription_msg`].
+/// Returns the block hash for [`XtStatus::InBlock`] and [`XtStatus::Finalized`].
+pub(crate) fn wait_for_xt_status(
+    updates: &Receiver<String>,
+    exit_on: XtStatus,
+    deadline: Option<Instant>,
+) -> ApiResult<Option<Hash>> {
+    loop {
+        let msg = match deadline {
+            Some(deadline) => updates
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .map_err(|e| match e {
+                    RecvTimeoutError::Timeout => ApiClientError::Timeout,
+                    RecvTimeoutError::Disconnected => ApiClientError::Disconnected(RecvError),
+                })?,
+            None => updates.recv()?,
+        };
+        let (status, value) = parse_status(&msg)?;
+        let reached = match status {
+            XtStatus::Finalized => true,
+            XtStatus::Future => {
+                warn!("extrinsic has 'future' status. aborting");
+                return Err(future_status_err().into());
             }
+            XtStatus::InBlock | XtStatus::Broadcast | XtStatus::Ready => status == exit_on,
+            _ => false,
+        };
+        if reached {
+            info!("{:?}: {:?}", status, value);
+            return match exit_on {
+                XtStatus::Finalized | XtStatus::InBlock => {
+                    Ok(Some(Hash::from_hex(value.unwrap_or_default())?))
+                }
+                _ => Ok(None),
+            };
         }
-        None => match value["params"]["result"].as_str() {
-            Some("ready") => Ok((XtStatus::Ready, None)),
-            Some("future") => Ok((XtStatus::Future, None)),
-            Some(&_) => Ok((XtStatus::Unknown, None)),
-            None => Ok((XtStatus::Unknown, None)),
-        },
     }
 }
 
-/// Todo: this is the code that was used in `parse_status` Don't we want to just print the
-/// error as is instead of introducing our custom format here?
-fn into_extrinsic_err(resp_with_err: &Value) -> RpcClientError {
-    let err_obj = resp_with_err["error"].as_object().unwrap();
-
-    let error = err_obj
-        .get("message")
-        .map_or_else(|| "", |e| e.as_str().unwrap());
-    let code = err_obj
-        .get("code")
-        .map_or_else(|| -1, |c| c.as_i64().unwrap());
-    let details = err_obj
-        .get("data")
-        .map_or_else(|| "", |d| d.as_str().unwrap());
-
-    RpcClientError::Extrinsic(format!(
-        "extrinsic error code {}: {}: {}",
-        code, error, details
-    ))
+fn future_status_err() -> RpcClientError {
+    RpcClientError::Extrinsic("extrinsic has 'future' status".to_string())
 }
 
-fn result_from_json_response(resp: &str) -> RpcResult<String> {
-    let value: serde_json::Value = serde_json::from_str(resp)?;
-
-    let resp = value["result"]
-        .as_str()
-        .ok_or_else(|| into_extrinsic_err(&value))?;
+fn end_process(result: &ThreadOut<String>, value: Option<String>) -> RpcResult<MessageOutcome> {
+    // return result to calling thread
+    debug!("Thread end result :{:?} value:{:?}", result, value);
+    let val = value.unwrap_or_else(|| "".to_string());
 
-    Ok(resp.to_string())
+    result.send(val)?;
+    Ok(MessageOutcome::Done)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::rpc::RpcClientError;
     use std::assert_matches::assert_matches;
-    use std::fmt::Debug;
+    use std::sync::mpsc::channel;
 
-    fn assert_extrinsic_err<T: Debug>(result: Result<T, RpcClientError>, msg: &str) {
-        assert_matches!(result.unwrap_err(), RpcClientError::Extrinsic(
-			m,
-		) if &m == msg)
+    fn runtime_version_msg(spec_version: u32) -> String {
+        format!(
+            r#"{{"jsonrpc":"2.0","method":"state_runtimeVersion","params":{{"result":{{"specName":"node-template","implName":"node-template","authoringVersion":1,"specVersion":{},"implVersion":1,"apis":[],"transactionVersion":1,"stateVersion":1}},"subscription":"SXuvtB4Bbr4ImLAb"}}}}"#,
+            spec_version
+        )
+    }
+
+    fn runtime_version_subscription(
+        receiver: std::sync::mpsc::Receiver<String>,
+    ) -> Subscription<RuntimeVersion> {
+        Subscription::new(
+            receiver,
+            Box::new(|result| Ok(Some(serde_json::from_value(result)?))),
+            || {},
+        )
     }
 
     #[test]
-    fn result_from_json_response_works() {
-        let msg = r#"{"jsonrpc":"2.0","result":"0xe7640c3e8ba8d10ed7fed07118edb0bfe2d765d3ea2f3a5f6cf781ae3237788f","id":"3"}"#;
+    fn latest_runtime_version_skips_outdated_versions() {
+        let (sender, receiver) = channel();
+        sender.send(runtime_version_msg(100)).unwrap();
+        sender.send(runtime_version_msg(101)).unwrap();
+        let mut subscription = runtime_version_subscription(receiver);
+
+        let latest = latest_runtime_version(&mut subscription).unwrap().unwrap();
+        assert_eq!(latest.spec_version, 101);
+        assert!(latest_runtime_version(&mut subscription).unwrap().is_none());
+
+        sender.send(runtime_version_msg(102)).unwrap();
+        let latest = latest_runtime_version(&mut subscription).unwrap().unwrap();
+        assert_eq!(latest.spec_version, 102);
+    }
 
-        assert_eq!(
-            result_from_json_response(msg).unwrap(),
-            "0xe7640c3e8ba8d10ed7fed07118edb0bfe2d765d3ea2f3a5f6cf781ae3237788f"
+    #[test]
+    fn latest_runtime_version_fails_once_disconnected() {
+        let (sender, receiver) = channel();
+        sender.send(runtime_version_msg(100)).unwrap();
+        drop(sender);
+        let mut subscription = runtime_version_subscription(receiver);
+
+        assert_matches!(
+            latest_runtime_version(&mut subscription),
+            Err(ApiClientError::Disconnected(_))
         );
     }
 
     #[test]
-    fn result_from_json_response_errs_on_error_response() {
-        let _err_raw =
-            r#"{"code":-32602,"message":"Invalid params: invalid hex character: h, at 284."}"#;
+    fn subscription_is_done_once_receiver_is_dropped() {
+        let msg = r#"{"jsonrpc":"2.0","method":"state_storage","params":{"result":{"block":"0x00","changes":[["0x26aa","0x0400"]]},"subscription":"SXuvtB4Bbr4ImLAb"}}"#;
+        let (result_in, result_out) = std::sync::mpsc::channel();
 
-        let err_msg = format!(
-            "extrinsic error code {}: {}: {}",
-            -32602, "Invalid params: invalid hex character: h, at 284.", ""
+        assert_eq!(
+            on_subscription_msg(msg, &result_in).unwrap(),
+            MessageOutcome::Continue
         );
+        assert_eq!(result_out.recv().unwrap(), "0x0400");
 
-        let msg = r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params: invalid hex character: h, at 284."},"id":"3"}"#;
-
-        assert_extrinsic_err(result_from_json_response(msg), &err_msg)
+        drop(result_out);
+        assert_eq!(
+            on_subscription_msg(msg, &result_in).unwrap(),
+            MessageOutcome::Done
+        );
     }
 
     #[test]
-    fn extrinsic_status_parsed_correctly() {
-        let msg = "{\"jsonrpc\":\"2.0\",\"result\":7185,\"id\":\"3\"}";
-        assert_eq!(parse_status(msg).unwrap(), (XtStatus::Unknown, None));
+    fn typed_subscription_msg_is_forwarded_until_receiver_is_dropped() {
+        let msg = r#"{"jsonrpc":"2.0","method":"chain_finalizedHead","params":{"result":{},"subscription":"SXuvtB4Bbr4ImLAb"}}"#;
+        let (result_in, result_out) = std::sync::mpsc::channel();
 
-        let msg = "{\"jsonrpc\":\"2.0\",\"method\":\"author_extrinsicUpdate\",\"params\":{\"result\":\"ready\",\"subscription\":7185}}";
-        assert_eq!(parse_status(msg).unwrap(), (XtStatus::Ready, None));
-
-        let msg = "{\"jsonrpc\":\"2.0\",\"method\":\"author_extrinsicUpdate\",\"params\":{\"result\":{\"broadcast\":[\"QmfSF4VYWNqNf5KYHpDEdY8Rt1nPUgSkMweDkYzhSWirGY\",\"Qmchhx9SRFeNvqjUK4ZVQ9jH4zhARFkutf9KhbbAmZWBLx\",\"QmQJAqr98EF1X3YfjVKNwQUG9RryqX4Hv33RqGChbz3Ncg\"]},\"subscription\":232}}";
         assert_eq!(
-            parse_status(msg).unwrap(),
-            (
-                XtStatus::Broadcast,
-                Some(
-                    "[\"QmfSF4VYWNqNf5KYHpDEdY8Rt1nPUgSkMweDkYzhSWirGY\",\"Qmchhx9SRFeNvqjUK4ZVQ9jH4zhARFkutf9KhbbAmZWBLx\",\"QmQJAqr98EF1X3YfjVKNwQUG9RryqX4Hv33RqGChbz3Ncg\"]"
-                        .to_string()
-                )
-            )
+            on_typed_subscription_msg(msg, &result_in).unwrap(),
+            MessageOutcome::Continue
         );
+        assert_eq!(result_out.recv().unwrap(), msg);
 
-        let msg = "{\"jsonrpc\":\"2.0\",\"method\":\"author_extrinsicUpdate\",\"params\":{\"result\":{\"inBlock\":\"0x3104d362365ff5ddb61845e1de441b56c6722e94c1aee362f8aa8ba75bd7a3aa\"},\"subscription\":232}}";
+        drop(result_out);
         assert_eq!(
-            parse_status(msg).unwrap(),
-            (
-                XtStatus::InBlock,
-                Some(
-                    "\"0x3104d362365ff5ddb61845e1de441b56c6722e94c1aee362f8aa8ba75bd7a3aa\""
-                        .to_string()
-                )
-            )
+            on_typed_subscription_msg(msg, &result_in).unwrap(),
+            MessageOutcome::Done
         );
+    }
 
-        let msg = "{\"jsonrpc\":\"2.0\",\"method\":\"author_extrinsicUpdate\",\"params\":{\"result\":{\"finalized\":\"0x934385b11c483498e2b5bca64c2e8ef76ad6c74d3372a05595d3a50caf758d52\"},\"subscription\":7185}}";
-        assert_eq!(
-            parse_status(msg).unwrap(),
-            (
-                XtStatus::Finalized,
-                Some(
-                    "\"0x934385b11c483498e2b5bca64c2e8ef76ad6c74d3372a05595d3a50caf758d52\""
-                        .to_string()
-                )
-            )
-        );
+    fn xt_status_msg(result: &str) -> String {
+        format!(
+            r#"{{"jsonrpc":"2.0","method":"author_extrinsicUpdate","params":{{"result":{},"subscription":"SXuvtB4Bbr4ImLAb"}}}}"#,
+            result
+        )
+    }
 
-        let msg = "{\"jsonrpc\":\"2.0\",\"method\":\"author_extrinsicUpdate\",\"params\":{\"result\":\"future\",\"subscription\":2}}";
-        assert_eq!(parse_status(msg).unwrap(), (XtStatus::Future, None));
+    #[test]
+    fn waits_for_xt_status_until_exit_on_is_reached() {
+        let block_hash = "0xe7640c3e8ba8d10ed7fed07118edb0bfe2d765d3ea2f3a5f6cf781ae3237788f";
+        let (updates_in, updates_out) = channel();
+        updates_in
+            .send(r#"{"jsonrpc":"2.0","result":"SXuvtB4Bbr4ImLAb","id":"1"}"#.to_string())
+            .unwrap();
+        updates_in.send(xt_status_msg(r#""ready""#)).unwrap();
+        updates_in
+            .send(xt_status_msg(&format!(r#"{{"inBlock":"{}"}}"#, block_hash)))
+            .unwrap();
 
-        let msg = "{\"jsonrpc\":\"2.0\",\"error\":{\"code\":-32700,\"message\":\"Parse error\"},\"id\":null}";
-        assert_extrinsic_err(
-            parse_status(msg),
-            "extrinsic error code -32700: Parse error: ",
+        assert_eq!(
+            wait_for_xt_status(&updates_out, XtStatus::InBlock, None).unwrap(),
+            Some(Hash::from_hex(block_hash.to_string()).unwrap())
         );
+    }
 
-        let msg = "{\"jsonrpc\":\"2.0\",\"error\":{\"code\":1010,\"message\":\"Invalid Transaction\",\"data\":\"Bad Signature\"},\"id\":\"4\"}";
-        assert_extrinsic_err(
-            parse_status(msg),
-            "extrinsic error code 1010: Invalid Transaction: Bad Signature",
-        );
+    #[test]
+    fn future_xt_status_fails_and_resyncs_the_nonce() {
+        let nonce_manager = crate::std::NonceManager::new();
+        nonce_manager.next_nonce(|| Ok(5)).unwrap();
+        let (updates_in, updates_out) = channel();
+        updates_in.send(xt_status_msg(r#""future""#)).unwrap();
+
+        let err = wait_for_xt_status(&updates_out, XtStatus::Finalized, None).unwrap_err();
+        assert_matches!(&err, ApiClientError::RpcClient(msg) if msg.contains("'future' status"));
+        assert!(nonce_manager.resync_on(&err));
+        assert_eq!(nonce_manager.next_nonce(|| Ok(5)).unwrap(), 5);
+    }
 
-        let msg = "{\"jsonrpc\":\"2.0\",\"error\":{\"code\":1001,\"message\":\"Extrinsic has invalid format.\"},\"id\":\"0\"}";
-        assert_extrinsic_err(
-            parse_status(msg),
-            "extrinsic error code 1001: Extrinsic has invalid format.: ",
+    #[test]
+    fn rejected_xt_is_an_rpc_error() {
+        let (updates_in, updates_out) = channel();
+        updates_in
+            .send(r#"{"jsonrpc":"2.0","error":{"code":1014,"message":"Priority is too low: (140 vs 140)","data":"The transaction has too low priority to replace another transaction already in the pool."},"id":"1"}"#.to_string())
+            .unwrap();
+
+        assert_matches!(
+            wait_for_xt_status(&updates_out, XtStatus::Ready, None),
+            Err(ApiClientError::RpcClient(_))
         );
+    }
+
+    #[test]
+    fn waiting_for_xt_status_times_out() {
+        let (_updates_in, updates_out) = channel::<String>();
 
-        let msg = r#"{"jsonrpc":"2.0","error":{"code":1002,"message":"Verification Error: Execution(Wasmi(Trap(Trap { kind: Unreachable })))","data":"RuntimeApi(\"Execution(Wasmi(Trap(Trap { kind: Unreachable })))\")"},"id":"3"}"#;
-        assert_extrinsic_err(
-            parse_status(msg),
-            "extrinsic error code 1002: Verification Error: Execution(Wasmi(Trap(Trap { kind: Unreachable }))): RuntimeApi(\"Execution(Wasmi(Trap(Trap { kind: Unreachable })))\")"
+        assert_matches!(
+            wait_for_xt_status(&updates_out, XtStatus::Ready, Some(Instant::now())),
+            Err(ApiClientError::Timeout)
         );
     }
 }
@@ -0,0 +1,269 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+//! Typed handle to a subscription on the node.
+
+use std::fmt;
+use std::sync::mpsc::{Receiver, RecvError, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use crate::std::rpc::helpers::decode_notification;
+use crate::std::rpc::ws_client::connection::RECONNECTED_MSG;
+use crate::std::rpc::DecodeFn;
+use crate::std::{ApiClientError, ApiResult};
+
+/// A subscription on the node, yielding decoded notifications.
+///
+/// Iterating blocks until the next notification arrives. Errors, e.g. a lost connection, are
+/// yielded as items, after which the iterator ends. If the connection has been re-established
+/// according to the client's [`ReconnectPolicy`](super::ReconnectPolicy),
+/// [`ApiClientError::Reconnected`] is yielded instead and the iterator goes on. The subscription
+/// is closed on the node when the handle is dropped.
+pub struct Subscription<T> {
+    receiver: Receiver<String>,
+    decode: DecodeFn<T>,
+    unsubscribe: Option<Box<dyn FnOnce() + Send>>,
+    is_last: Option<fn(&T) -> bool>,
+    terminated: bool,
+}
+
+impl<T> Subscription<T> {
+    /// Creates a handle yielding the messages received on `receiver`, decoded with `decode`.
+    /// `unsubscribe` is called once the handle is dropped.
+    pub fn new(
+        receiver: Receiver<String>,
+        decode: DecodeFn<T>,
+        unsubscribe: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        Self {
+            receiver,
+            decode,
+            unsubscribe: Some(Box::new(unsubscribe)),
+            is_last: None,
+            terminated: false,
+        }
+    }
+
+    /// Ends the iterator after the first item for which `is_last` returns true, for subscriptions
+    /// that the node stops notifying about at some point.
+    #[must_use]
+    pub fn end_after(mut self, is_last: fn(&T) -> bool) -> Self {
+        self.is_last = Some(is_last);
+        self
+    }
+
+    /// Like [`Iterator::next`], but yields [`ApiClientError::Timeout`] if no item arrives within
+    /// `timeout`. The subscription stays usable after a timeout.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Option<ApiResult<T>> {
+        self.next_before(Some(Instant::now() + timeout))
+    }
+
+    /// Closes the subscription on the node. Same as dropping the handle.
+    pub fn unsubscribe(self) {}
+
+    /// Waits for the next item until `deadline`, or forever if there is none.
+    pub(crate) fn next_before(&mut self, deadline: Option<Instant>) -> Option<ApiResult<T>> {
+        while !self.terminated {
+            let received = match deadline {
+                Some(deadline) => self
+                    .receiver
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                None => self
+                    .receiver
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let msg = match received {
+                Ok(msg) => msg,
+                Err(RecvTimeoutError::Timeout) => return Some(Err(ApiClientError::Timeout)),
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.terminated = true;
+                    return Some(Err(ApiClientError::Disconnected(RecvError)));
+                }
+            };
+            match self.handle_message(&msg) {
+                Ok(Some(item)) => {
+                    self.terminated = self.is_last.map_or(false, |is_last| is_last(&item));
+                    return Some(Ok(item));
+                }
+                Ok(None) => continue,
+                Err(e) => {
+                    // Decoding errors do not affect subsequent notifications, rpc errors do.
+                    if matches!(e, ApiClientError::RpcClient(_)) {
+                        self.terminated = true;
+                    }
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+
+    fn handle_message(&self, msg: &str) -> ApiResult<Option<T>> {
+        if msg == RECONNECTED_MSG {
+            return Err(ApiClientError::Reconnected);
+        }
+        decode_notification(msg, &self.decode)
+    }
+}
+
+impl<T> Iterator for Subscription<T> {
+    type Item = ApiResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_before(None)
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        if let Some(unsubscribe) = self.unsubscribe.take() {
+            unsubscribe();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Subscription<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("terminated", &self.terminated)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::assert_matches::assert_matches;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::channel;
+    use std::sync::Arc;
+
+    fn number_subscription(receiver: Receiver<String>) -> Subscription<u64> {
+        Subscription::new(receiver, Box::new(|result| Ok(result.as_u64())), || {})
+    }
+
+    #[test]
+    fn yields_decoded_notifications_and_skips_confirmation() {
+        let (sender, receiver) = channel();
+        sender
+            .send(r#"{"jsonrpc":"2.0","result":"SXuvtB4Bbr4ImLAb","id":"1"}"#.to_string())
+            .unwrap();
+        sender
+            .send(r#"{"jsonrpc":"2.0","method":"m","params":{"result":42,"subscription":"SXuvtB4Bbr4ImLAb"}}"#.to_string())
+            .unwrap();
+        sender
+            .send(r#"{"jsonrpc":"2.0","method":"m","params":{"result":null,"subscription":"SXuvtB4Bbr4ImLAb"}}"#.to_string())
+            .unwrap();
+        sender
+            .send(r#"{"jsonrpc":"2.0","method":"m","params":{"result":43,"subscription":"SXuvtB4Bbr4ImLAb"}}"#.to_string())
+            .unwrap();
+
+        let mut subscription = number_subscription(receiver);
+        assert_eq!(subscription.next().unwrap().unwrap(), 42);
+        assert_eq!(subscription.next().unwrap().unwrap(), 43);
+    }
+
+    #[test]
+    fn ends_after_last_item() {
+        let (sender, receiver) = channel();
+        for result in [1, 2, 3] {
+            sender
+                .send(format!(r#"{{"jsonrpc":"2.0","method":"m","params":{{"result":{},"subscription":"SXuvtB4Bbr4ImLAb"}}}}"#, result))
+                .unwrap();
+        }
+
+        let subscription = number_subscription(receiver).end_after(|n| *n == 2);
+        let items: Vec<u64> = subscription.map(Result::unwrap).collect();
+        assert_eq!(items, vec![1, 2]);
+    }
+
+    #[test]
+    fn yields_disconnected_once_and_ends() {
+        let (sender, receiver) = channel();
+        drop(sender);
+
+        let mut subscription = number_subscription(receiver);
+        assert_matches!(
+            subscription.next(),
+            Some(Err(ApiClientError::Disconnected(_)))
+        );
+        assert!(subscription.next().is_none());
+    }
+
+    #[test]
+    fn yields_rpc_error_and_ends() {
+        let (sender, receiver) = channel();
+        sender
+            .send(r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":"1"}"#.to_string())
+            .unwrap();
+
+        let mut subscription = number_subscription(receiver);
+        assert_matches!(subscription.next(), Some(Err(ApiClientError::RpcClient(_))));
+        assert!(subscription.next().is_none());
+    }
+
+    #[test]
+    fn next_timeout_yields_timeout_and_goes_on() {
+        let (sender, receiver) = channel();
+
+        let mut subscription = number_subscription(receiver);
+        assert_matches!(
+            subscription.next_timeout(Duration::from_millis(10)),
+            Some(Err(ApiClientError::Timeout))
+        );
+
+        sender
+            .send(r#"{"jsonrpc":"2.0","method":"m","params":{"result":42,"subscription":"SXuvtB4Bbr4ImLAb"}}"#.to_string())
+            .unwrap();
+        assert_eq!(
+            subscription
+                .next_timeout(Duration::from_millis(10))
+                .unwrap()
+                .unwrap(),
+            42
+        );
+    }
+
+    #[test]
+    fn yields_reconnected_and_goes_on() {
+        let (sender, receiver) = channel();
+        sender.send(RECONNECTED_MSG.to_string()).unwrap();
+        sender
+            .send(r#"{"jsonrpc":"2.0","method":"m","params":{"result":42,"subscription":"SXuvtB4Bbr4ImLAb"}}"#.to_string())
+            .unwrap();
+
+        let mut subscription = number_subscription(receiver);
+        assert_matches!(subscription.next(), Some(Err(ApiClientError::Reconnected)));
+        assert_eq!(subscription.next().unwrap().unwrap(), 42);
+    }
+
+    #[test]
+    fn unsubscribes_on_drop() {
+        let (_sender, receiver) = channel();
+        let unsubscribed = Arc::new(AtomicBool::new(false));
+
+        let flag = unsubscribed.clone();
+        let subscription: Subscription<u64> = Subscription::new(
+            receiver,
+            Box::new(|result| Ok(result.as_u64())),
+            move || flag.store(true, Ordering::SeqCst),
+        );
+        drop(subscription);
+        assert!(unsubscribed.load(Ordering::SeqCst));
+    }
+}
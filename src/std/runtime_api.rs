@@ -0,0 +1,283 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+//! Typed wrappers of common runtime apis, called with [`Api::runtime_call`].
+
+use ac_primitives::{Balance, ExtrinsicParams, Index};
+use codec::Encode;
+use sp_core::H256 as Hash;
+use sp_runtime::{AccountId32 as AccountId, ApplyExtrinsicResult};
+use sp_version::RuntimeVersion;
+use transaction_payment::{FeeDetails, RuntimeDispatchInfo};
+
+use crate::std::{Api, ApiResult, FromHexString, RpcClient};
+
+pub const ACCOUNT_NONCE: &str = "AccountNonceApi_account_nonce";
+pub const APPLY_EXTRINSIC: &str = "BlockBuilder_apply_extrinsic";
+pub const CORE_VERSION: &str = "Core_version";
+pub const QUERY_FEE_DETAILS: &str = "TransactionPaymentApi_query_fee_details";
+pub const QUERY_INFO: &str = "TransactionPaymentApi_query_info";
+
+impl<P, Client, Params> Api<P, Client, Params>
+where
+    Client: RpcClient,
+    Params: ExtrinsicParams,
+{
+    /// Returns the next nonce of `account`, the same as `system_accountNextIndex` but without
+    /// considering the transaction pool.
+    pub fn runtime_account_nonce(
+        &self,
+        account: &AccountId,
+        at_block: Option<Hash>,
+    ) -> ApiResult<Index> {
+        self.runtime_call(ACCOUNT_NONCE, &account.encode(), at_block)
+    }
+
+    /// Returns the runtime version at `at_block`, or at the latest block if `None`.
+    pub fn runtime_version_at(&self, at_block: Option<Hash>) -> ApiResult<RuntimeVersion> {
+        self.runtime_call(CORE_VERSION, &[], at_block)
+    }
+
+    /// Like [`Self::get_payment_info`], but calls the runtime api directly instead of the
+    /// `payment_queryInfo` rpc, which nodes may not expose.
+    pub fn runtime_payment_info(
+        &self,
+        xthex_prefixed: &str,
+        at_block: Option<Hash>,
+    ) -> ApiResult<RuntimeDispatchInfo<Balance>> {
+        let extrinsic = Vec::from_hex(xthex_prefixed.to_string())?;
+        self.runtime_call(QUERY_INFO, &with_length(extrinsic), at_block)
+    }
+
+    /// Like [`Self::get_fee_details`], but calls the runtime api directly instead of the
+    /// `payment_queryFeeDetails` rpc, which nodes may not expose.
+    pub fn runtime_fee_details(
+        &self,
+        xthex_prefixed: &str,
+        at_block: Option<Hash>,
+    ) -> ApiResult<FeeDetails<Balance>> {
+        let extrinsic = Vec::from_hex(xthex_prefixed.to_string())?;
+        self.runtime_call(QUERY_FEE_DETAILS, &with_length(extrinsic), at_block)
+    }
+
+    /// Applies the extrinsic on top of `at_block`, or the latest block if `None`, without
+    /// submitting it. The state changes are discarded, so this tells whether the extrinsic
+    /// would be valid and dispatched successfully.
+    pub fn dry_run(
+        &self,
+        xthex_prefixed: &str,
+        at_block: Option<Hash>,
+    ) -> ApiResult<ApplyExtrinsicResult> {
+        let extrinsic = Vec::from_hex(xthex_prefixed.to_string())?;
+        self.runtime_call(APPLY_EXTRINSIC, &extrinsic, at_block)
+    }
+}
+
+/// Arguments of the transaction payment apis: the encoded extrinsic followed by its length.
+fn with_length(mut extrinsic: Vec<u8>) -> Vec<u8> {
+    let len = extrinsic.len() as u32;
+    len.encode_to(&mut extrinsic);
+    extrinsic
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std::rpc::helpers::result_or_error;
+    use crate::std::{ApiClientError, RuntimeMetadataPrefixed, XtStatus};
+    use ac_node_api::metadata::Metadata;
+    use ac_primitives::PlainTipExtrinsicParams;
+    use codec::Decode;
+    use serde_json::{json, Value};
+    use sp_keyring::AccountKeyring;
+    use sp_runtime::transaction_validity::{InvalidTransaction, TransactionValidityError};
+    use std::assert_matches::assert_matches;
+    use std::cell::RefCell;
+    use transaction_payment::InclusionFee;
+
+    /// Answers every request with `response` and records the requests.
+    struct MockClient {
+        response: Value,
+        requests: RefCell<Vec<Value>>,
+    }
+
+    impl RpcClient for MockClient {
+        fn get_request(&self, jsonreq: Value) -> ApiResult<String> {
+            self.requests.borrow_mut().push(jsonreq);
+            Ok(self.response["result"].to_string())
+        }
+
+        fn get_request_or_error(&self, jsonreq: Value) -> ApiResult<String> {
+            self.requests.borrow_mut().push(jsonreq);
+            result_or_error(&self.response.to_string())
+        }
+
+        fn send_extrinsic(&self, _: String, _: XtStatus) -> ApiResult<Option<Hash>> {
+            unimplemented!()
+        }
+    }
+
+    type MockApi = Api<(), MockClient, PlainTipExtrinsicParams>;
+
+    fn api(response: Value) -> MockApi {
+        let metadata = node_template_runtime::Runtime::metadata().encode();
+        let metadata = RuntimeMetadataPrefixed::decode(&mut metadata.as_slice()).unwrap();
+        Api {
+            signer: None,
+            genesis_hash: Hash::zero(),
+            metadata: Metadata::try_from(metadata).unwrap(),
+            runtime_version: RuntimeVersion::default(),
+            client: MockClient {
+                response,
+                requests: RefCell::new(Vec::new()),
+            },
+            extrinsic_params_builder: None,
+            extrinsic_params_builder_fn: None,
+            on_runtime_update: None,
+            nonce_manager: None,
+            mortality: None,
+        }
+    }
+
+    /// Api answering every state call with the SCALE encoded `result`.
+    fn api_returning(result: impl Encode) -> MockApi {
+        api(json!({
+            "jsonrpc": "2.0",
+            "result": format!("0x{}", hex::encode(result.encode())),
+            "id": "1",
+        }))
+    }
+
+    /// The params of the state call `api` sent.
+    fn state_call_params(api: &MockApi) -> Value {
+        let requests = api.client.requests.borrow();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0]["method"], "state_call");
+        requests[0]["params"].clone()
+    }
+
+    fn to_hex(data: &[u8]) -> String {
+        format!("0x{}", hex::encode(data))
+    }
+
+    #[test]
+    fn transaction_payment_args_end_with_extrinsic_length() {
+        let extrinsic = vec![0x0c, 1, 2, 3];
+        assert_eq!(with_length(extrinsic), vec![0x0c, 1, 2, 3, 4, 0, 0, 0]);
+    }
+
+    #[test]
+    fn runtime_account_nonce_calls_account_nonce_api() {
+        let account = AccountKeyring::Alice.to_account_id();
+        let api = api_returning(5u32);
+
+        assert_eq!(api.runtime_account_nonce(&account, None).unwrap(), 5);
+        assert_eq!(
+            state_call_params(&api),
+            json!([ACCOUNT_NONCE, to_hex(&account.encode()), null])
+        );
+    }
+
+    #[test]
+    fn runtime_version_at_calls_core_version_at_the_block() {
+        let block = Hash::from([1u8; 32]);
+        let version = RuntimeVersion {
+            spec_version: 7,
+            transaction_version: 2,
+            ..Default::default()
+        };
+        let api = api_returning(&version);
+
+        assert_eq!(api.runtime_version_at(Some(block)).unwrap(), version);
+        assert_eq!(
+            state_call_params(&api),
+            json!([CORE_VERSION, "0x", to_hex(block.as_bytes())])
+        );
+    }
+
+    #[test]
+    fn runtime_payment_info_passes_the_extrinsic_with_its_length() {
+        let info = RuntimeDispatchInfo::<Balance> {
+            partial_fee: 3,
+            ..Default::default()
+        };
+        let api = api_returning(info);
+
+        let result = api.runtime_payment_info("0x0c010203", None).unwrap();
+        assert_eq!(result.partial_fee, 3);
+        assert_eq!(
+            state_call_params(&api),
+            json!([QUERY_INFO, "0x0c01020304000000", null])
+        );
+    }
+
+    #[test]
+    fn runtime_fee_details_passes_the_extrinsic_with_its_length() {
+        let details = FeeDetails::<Balance> {
+            inclusion_fee: Some(InclusionFee {
+                base_fee: 1,
+                len_fee: 2,
+                adjusted_weight_fee: 3,
+            }),
+            tip: 4,
+        };
+        let api = api_returning(&details);
+
+        assert_eq!(
+            api.runtime_fee_details("0x0c010203", None).unwrap(),
+            details
+        );
+        assert_eq!(
+            state_call_params(&api),
+            json!([QUERY_FEE_DETAILS, "0x0c01020304000000", null])
+        );
+    }
+
+    #[test]
+    fn dry_run_applies_the_extrinsic_without_its_length() {
+        let invalid: ApplyExtrinsicResult = Err(TransactionValidityError::Invalid(
+            InvalidTransaction::BadProof,
+        ));
+        let api = api_returning(&invalid);
+
+        assert_eq!(api.dry_run("0x0c010203", None).unwrap(), invalid);
+        assert_eq!(
+            state_call_params(&api),
+            json!([APPLY_EXTRINSIC, "0x0c010203", null])
+        );
+    }
+
+    #[test]
+    fn runtime_call_keeps_the_error_of_the_node() {
+        let api = api(json!({
+            "jsonrpc": "2.0",
+            "error": {"code": -32000, "message": "Client error: Execution failed: Other: Exported method Foo_bar is not found"},
+            "id": "1",
+        }));
+
+        let result = api.runtime_call::<u32>("Foo_bar", &[], None);
+        assert_matches!(result, Err(ApiClientError::RpcClient(msg)) if msg.contains("Foo_bar is not found"));
+    }
+
+    #[test]
+    fn runtime_call_without_result_is_an_error() {
+        let api = api(json!({"jsonrpc": "2.0", "result": null, "id": "1"}));
+
+        let result = api.runtime_call::<u32>("Foo_bar", &[], None);
+        assert_matches!(result, Err(ApiClientError::RuntimeCall(method)) if method == "Foo_bar");
+    }
+}
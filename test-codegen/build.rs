@@ -0,0 +1,38 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+use std::{env, fs, path::Path};
+
+use codec::Encode;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let out_dir = Path::new(&out_dir);
+
+    // The metadata `state_getMetadata` returns for a node-template node, as SCALE encoded file.
+    let metadata_path = out_dir.join("node_template_metadata.scale");
+    fs::write(
+        &metadata_path,
+        node_template_runtime::Runtime::metadata().encode(),
+    )
+    .unwrap();
+
+    ac_codegen::generate_to_file(&metadata_path, "crate::runtime", out_dir.join("runtime.rs"))
+        .unwrap();
+}
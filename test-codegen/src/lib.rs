@@ -0,0 +1,77 @@
+/*
+   Copyright 2019 Supercomputing Systems AG
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+
+*/
+
+//! The api `ac-codegen` generates for the node-template runtime, see `build.rs`. That it compiles
+//! is the main test, the tests below check that it encodes and decodes like the runtime itself.
+
+pub mod runtime {
+    include!(concat!(env!("OUT_DIR"), "/runtime.rs"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::runtime;
+    use codec::{Decode, Encode};
+    use node_template_runtime::{BalancesCall, Runtime, RuntimeCall};
+    use sp_keyring::AccountKeyring;
+    use sp_runtime::MultiAddress;
+
+    fn transfer() -> runtime::balances::calls::Call {
+        runtime::balances::calls::transfer(
+            runtime::types::sp_runtime::multiaddress::MultiAddress::Id(
+                AccountKeyring::Bob.to_account_id(),
+            ),
+            42,
+        )
+    }
+
+    #[test]
+    fn calls_encode_like_the_runtime_calls() {
+        let call = RuntimeCall::Balances(BalancesCall::transfer {
+            dest: MultiAddress::Id(AccountKeyring::Bob.to_account_id()),
+            value: 42,
+        });
+        assert_eq!(transfer().encode(), call.encode());
+    }
+
+    #[test]
+    fn calls_within_calls_encode_like_the_runtime_calls() {
+        let inner = runtime::types::node_template_runtime::RuntimeCall::Balances(transfer().0);
+        let call = RuntimeCall::Sudo(pallet_sudo::Call::sudo {
+            call: Box::new(RuntimeCall::Balances(BalancesCall::transfer {
+                dest: MultiAddress::Id(AccountKeyring::Bob.to_account_id()),
+                value: 42,
+            })),
+        });
+        assert_eq!(runtime::sudo::calls::sudo(inner).encode(), call.encode());
+    }
+
+    #[test]
+    fn events_decode_from_the_runtime_events() {
+        let event = pallet_balances::Event::<Runtime>::Transfer {
+            from: AccountKeyring::Alice.to_account_id(),
+            to: AccountKeyring::Bob.to_account_id(),
+            amount: 1_000,
+        };
+        // Skip the variant index, as event data is decoded without it.
+        let decoded =
+            runtime::balances::events::Transfer::decode(&mut &event.encode()[1..]).unwrap();
+        assert_eq!(decoded.from, AccountKeyring::Alice.to_account_id());
+        assert_eq!(decoded.to, AccountKeyring::Bob.to_account_id());
+        assert_eq!(decoded.amount, 1_000);
+    }
+}